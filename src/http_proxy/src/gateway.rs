@@ -0,0 +1,186 @@
+use many_client::client::blocking::ManyClient;
+use many_error::{ManyError, ManyErrorCode};
+use many_identity::Identity;
+use many_kvstore::error;
+use many_modules::kvstore::{GetArgs, GetReturns};
+use percent_encoding::percent_decode_str;
+use sha3::{Digest, Sha3_256};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::sync::Arc;
+use tiny_http::{Header, Method, Request, Response};
+use tracing::{debug, warn};
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+/// The file served for paths ending with `/`.
+const INDEX_FILE: &str = "index.html";
+
+/// The prefixes of the keys served, by virtual host.
+pub struct Prefixes {
+    default: String,
+    hosts: BTreeMap<String, String>,
+}
+
+impl Prefixes {
+    pub fn new(default: String, hosts: BTreeMap<String, String>) -> Self {
+        Self { default, hosts }
+    }
+
+    /// The prefix for the value of a `Host` header, ignoring its port.
+    fn for_host(&self, host: Option<&str>) -> &str {
+        host.map(strip_port)
+            .and_then(|host| self.hosts.get(&host.to_ascii_lowercase()))
+            .unwrap_or(&self.default)
+    }
+}
+
+fn strip_port(host: &str) -> &str {
+    match host.rfind(':') {
+        // An IPv6 address without a port, e.g. `[::1]`.
+        Some(i) if host[i..].contains(']') => host,
+        Some(i) => &host[..i],
+        None => host,
+    }
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field, value).unwrap()
+}
+
+fn find_header<'a>(request: &'a Request, field: &str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(field))
+        .map(|h| h.value.as_str())
+}
+
+fn status(code: u16) -> HttpResponse {
+    Response::from_data(Vec::new()).with_status_code(code)
+}
+
+/// The percent-decoded path of a URL without its query, with directories
+/// resolved to their index file. Returns `None` if the decoded path is not
+/// valid UTF-8.
+fn resolve_path(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+    if path.is_empty() || path.ends_with('/') {
+        Some(format!("{path}{INDEX_FILE}"))
+    } else {
+        Some(path.into_owned())
+    }
+}
+
+/// The strong ETag of a value, from its SHA3-256 hash. The KV-Store does not
+/// return the hash of a value, so the gateway computes it from the whole
+/// value.
+fn etag(value: &[u8]) -> String {
+    format!("\"{}\"", hex::encode(Sha3_256::digest(value)))
+}
+
+/// Whether an `If-None-Match` header matches the ETag of the current value.
+fn none_match(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// The HTTP status of an error returned by the server.
+fn error_status(err: &ManyError) -> u16 {
+    let code = err.code();
+    if code == error::permission_denied().code() || code == ManyError::invalid_identity().code() {
+        403
+    } else if code == error::key_not_found().code() || code == error::key_disabled().code() {
+        404
+    } else if code == ManyErrorCode::AttributeNotFound
+        || code == ManyError::invalid_method_name(String::new()).code()
+        || code == ManyError::deserialization_error(String::new()).code()
+    {
+        // The server does not implement the KV-Store attribute, or its
+        // response is invalid.
+        502
+    } else if code == error::storage_get_failed(String::new()).code() {
+        503
+    } else if code == ManyError::unknown(String::new()).code() {
+        // Errors of the client, e.g. the server could not be reached.
+        502
+    } else {
+        500
+    }
+}
+
+/// Serves the values of a KV-Store over HTTP.
+pub struct Gateway<I: Identity> {
+    client: ManyClient<I>,
+    prefixes: Arc<Prefixes>,
+}
+
+impl<I: Identity> Gateway<I> {
+    pub fn new(client: ManyClient<I>, prefixes: Arc<Prefixes>) -> Self {
+        Self { client, prefixes }
+    }
+
+    pub fn handle(&self, request: Request) {
+        let response = match request.method() {
+            // tiny_http does not send the body of responses to HEAD requests.
+            Method::Get | Method::Head => self.get(&request),
+            x => {
+                warn!("Received unknown method: {}", x);
+                status(405).with_header(header("Allow", "GET, HEAD"))
+            }
+        };
+
+        // Ignore errors on return.
+        let _ = request.respond(response);
+    }
+
+    fn get(&self, request: &Request) -> HttpResponse {
+        let path = match resolve_path(request.url()) {
+            Some(path) => path,
+            None => return status(400),
+        };
+        let prefix = self.prefixes.for_host(find_header(request, "Host"));
+        let key = format!("{prefix}{path}");
+
+        let value = match self.get_value(&key) {
+            Ok(Some(value)) => value,
+            Ok(None) => return status(404),
+            Err(err) => {
+                warn!("Could not get key {key}: {err}");
+                return status(error_status(&err));
+            }
+        };
+
+        // A conditional GET only saves sending the value to the client: the
+        // value is still fetched from the server, in full, to compute its
+        // ETag.
+        let etag = etag(&value);
+        if let Some(if_none_match) = find_header(request, "If-None-Match") {
+            if none_match(if_none_match, &etag) {
+                debug!("Not modified: {key}");
+                return status(304).with_header(header("ETag", &etag));
+            }
+        }
+
+        let response = Response::from_data(value).with_header(header("ETag", &etag));
+        match new_mime_guess::from_path(&path).first() {
+            Some(mimetype) => response.with_header(header("Content-Type", mimetype.essence_str())),
+            None => response,
+        }
+    }
+
+    fn get_value(&self, key: &str) -> Result<Option<Vec<u8>>, ManyError> {
+        let payload = self.client.call_(
+            "kvstore.get",
+            GetArgs {
+                key: key.as_bytes().to_vec().into(),
+            },
+        )?;
+        let GetReturns { value } =
+            minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+        Ok(value.map(|value| value.into()))
+    }
+}
@@ -1,14 +1,20 @@
+use crate::gateway::{Gateway, Prefixes};
 use clap::Parser;
 use many_client::client::blocking::ManyClient;
 use many_identity::{Address, AnonymousIdentity, Identity};
 use many_identity_dsa::CoseKeyIdentity;
-use many_modules::kvstore::{GetArgs, GetReturns};
 use std::net::SocketAddr;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
-use tiny_http::{Header, Method, Response, StatusCode};
-use tracing::warn;
+use std::sync::Arc;
+use tracing::info;
 use tracing_subscriber::filter::LevelFilter;
 
+mod gateway;
+
+/// The number of workers if the number of CPUs is unknown.
+const DEFAULT_WORKERS: usize = 4;
+
 #[derive(clap::ArgEnum, Clone)]
 enum LogStrategy {
     Terminal,
@@ -43,6 +49,34 @@ struct Opts {
     /// Use given logging strategy
     #[clap(long, arg_enum, default_value_t = LogStrategy::Terminal)]
     logmode: LogStrategy,
+
+    /// The prefix of the keys served. The path of a request, starting with
+    /// `/`, is appended to it.
+    #[clap(long, default_value = "http/")]
+    prefix: String,
+
+    /// The prefix of the keys served for a virtual host, as `HOST=PREFIX`.
+    /// Can be used multiple times. Other hosts use `--prefix`.
+    #[clap(long = "vhost", parse(try_from_str = parse_vhost))]
+    vhosts: Vec<(String, String)>,
+
+    /// The number of requests handled concurrently. Defaults to the number
+    /// of CPUs.
+    #[clap(long)]
+    workers: Option<usize>,
+}
+
+fn parse_vhost(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(host, prefix)| (host.to_ascii_lowercase(), prefix.to_string()))
+        .ok_or_else(|| format!("Expected HOST=PREFIX, got '{s}'."))
+}
+
+fn identity(pem: Option<String>) -> Box<dyn Identity> {
+    pem.map_or_else(
+        || Box::new(AnonymousIdentity) as Box<dyn Identity>,
+        |pem| Box::new(CoseKeyIdentity::from_pem(pem).unwrap()),
+    )
 }
 
 fn main() {
@@ -54,6 +88,9 @@ fn main() {
         verbose,
         quiet,
         logmode,
+        prefix,
+        vhosts,
+        workers,
     } = Opts::parse();
 
     let verbose_level = 2 + verbose - quiet;
@@ -85,57 +122,36 @@ fn main() {
     };
 
     let server_id = server_id.unwrap_or_default();
-    let key: Box<dyn Identity> = pem.map_or_else(
-        || Box::new(AnonymousIdentity) as Box<dyn Identity>,
-        |p| Box::new(CoseKeyIdentity::from_pem(std::fs::read_to_string(p).unwrap()).unwrap()),
-    );
-
-    let client = ManyClient::new(server, server_id, key).unwrap();
-    let http = tiny_http::Server::http(addr).unwrap();
-
-    // TODO: parallelize this.
-    for request in http.incoming_requests() {
-        let path = request.url();
-        match request.method() {
-            Method::Get => {
-                let result = client.call_(
-                    "kvstore.get",
-                    GetArgs {
-                        key: format!("http/{path}").into_bytes().into(),
-                    },
-                );
-                match result {
-                    Ok(result) => {
-                        let GetReturns { value } = minicbor::decode(&result).unwrap();
-                        match value {
-                            None => request.respond(Response::empty(404)).unwrap(),
-                            Some(value) => {
-                                let mimetype = new_mime_guess::from_path(path).first();
-                                let response = Response::empty(200)
-                                    .with_data(value.as_slice(), Some(value.len()));
-                                let response = if let Some(mimetype) = mimetype {
-                                    response.with_header(
-                                        Header::from_bytes("Content-Type", mimetype.essence_str())
-                                            .unwrap(),
-                                    )
-                                } else {
-                                    response
-                                };
-
-                                // Ignore errors on return.
-                                let _ = request.respond(response);
-                            }
-                        }
-                    }
-                    Err(_) => request.respond(Response::empty(500)).unwrap(),
+    let pem = pem.map(|p| std::fs::read_to_string(p).unwrap());
+
+    let prefixes = Arc::new(Prefixes::new(prefix, vhosts.into_iter().collect()));
+    let workers = workers
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(DEFAULT_WORKERS, NonZeroUsize::get)
+        })
+        .max(1);
+    let http = Arc::new(tiny_http::Server::http(addr).unwrap());
+    info!("Listening on {addr} with {workers} workers");
+
+    // Each worker has its own client, and takes the next request when it is
+    // done with the previous one.
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let http = http.clone();
+            let prefixes = prefixes.clone();
+            let server = server.clone();
+            let pem = pem.clone();
+            std::thread::spawn(move || {
+                let client = ManyClient::new(server, server_id, identity(pem)).unwrap();
+                let gateway = Gateway::new(client, prefixes);
+                for request in http.incoming_requests() {
+                    gateway.handle(request);
                 }
-            }
-            // Method::Head => {}
-            // Method::Options => {}
-            x => {
-                warn!("Received unknown method: {}", x);
-                let _ = request.respond(Response::empty(StatusCode::from(405)));
-            }
-        }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
     }
 }
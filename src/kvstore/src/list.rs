@@ -0,0 +1,149 @@
+use clap::Parser;
+use many_client::client::blocking::ManyClient;
+use many_error::ManyError;
+use many_identity::{Address, Identity};
+use many_types::{CborRange, SortOrder};
+use minicbor::bytes::ByteVec;
+use std::ops::Bound;
+
+/// Arguments of the `kvstore.list` endpoint of many-kvstore.
+#[derive(Default, minicbor::Encode)]
+#[cbor(map)]
+struct ListArgs {
+    #[n(0)]
+    prefix: Option<ByteVec>,
+
+    #[n(1)]
+    range: Option<CborRange<ByteVec>>,
+
+    #[n(2)]
+    order: Option<SortOrder>,
+
+    #[n(3)]
+    count: Option<u64>,
+
+    #[n(4)]
+    after: Option<ByteVec>,
+
+    #[n(5)]
+    owner: Option<Address>,
+}
+
+#[derive(minicbor::Decode)]
+#[cbor(map)]
+struct ListReturns {
+    #[n(0)]
+    keys: Vec<ByteVec>,
+
+    #[n(1)]
+    next: Option<ByteVec>,
+}
+
+#[derive(Debug, Parser)]
+pub struct ListOpt {
+    /// Only list the keys starting with this prefix.
+    #[clap(long, conflicts_with_all(&["start", "end"]))]
+    prefix: Option<String>,
+
+    /// Only list the keys greater than or equal to this key.
+    #[clap(long)]
+    start: Option<String>,
+
+    /// Only list the keys lower than this key.
+    #[clap(long)]
+    end: Option<String>,
+
+    /// Continue a previous listing after this key (the `next` key it printed).
+    #[clap(long)]
+    after: Option<String>,
+
+    /// If the keys are passed as hexadecimal strings, pass this flag.
+    #[clap(long)]
+    hex_key: bool,
+
+    /// Whether to output the keys using hexadecimal.
+    #[clap(long)]
+    hex: bool,
+
+    /// Only list the keys owned by this address.
+    #[clap(long)]
+    owner: Option<Address>,
+
+    /// The maximum number of keys to list.
+    #[clap(long)]
+    count: Option<u64>,
+
+    /// List the keys in descending order.
+    #[clap(long)]
+    descending: bool,
+}
+
+fn print_key(key: &[u8], hex: bool) {
+    if hex {
+        println!("{}", hex::encode(key));
+    } else {
+        println!("{}", String::from_utf8_lossy(key));
+    }
+}
+
+pub fn list(client: ManyClient<impl Identity>, opts: ListOpt) -> Result<(), ManyError> {
+    let ListOpt {
+        prefix,
+        start,
+        end,
+        after,
+        hex_key,
+        hex,
+        owner,
+        count,
+        descending,
+    } = opts;
+
+    let decode = |key: String| -> ByteVec {
+        if hex_key {
+            hex::decode(&key).unwrap().into()
+        } else {
+            key.into_bytes().into()
+        }
+    };
+
+    let range = if start.is_some() || end.is_some() {
+        Some(CborRange {
+            start: start.map_or(Bound::Unbounded, |k| Bound::Included(decode(k))),
+            end: end.map_or(Bound::Unbounded, |k| Bound::Excluded(decode(k))),
+        })
+    } else {
+        None
+    };
+
+    let args = ListArgs {
+        prefix: prefix.map(decode),
+        range,
+        order: Some(if descending {
+            SortOrder::Descending
+        } else {
+            SortOrder::Ascending
+        }),
+        count,
+        after: after.map(decode),
+        owner,
+    };
+
+    let payload = client.call_("kvstore.list", args)?;
+    let result: ListReturns =
+        minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+
+    for key in result.keys {
+        print_key(&key, hex);
+    }
+    if let Some(next) = result.next {
+        let next = if hex_key {
+            hex::encode(next.as_slice())
+        } else {
+            String::from_utf8_lossy(&next).into_owned()
+        };
+        eprintln!("More keys remain, continue with `--after {next}`.");
+    }
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use many_client::client::blocking::ManyClient;
 use many_error::{ManyError, Reason};
 use many_identity::{Address, AnonymousIdentity, Identity};
@@ -7,14 +8,147 @@ use many_modules::kvstore::TransferArgs;
 use many_modules::r#async::{StatusArgs, StatusReturn};
 use many_modules::{kvstore, r#async};
 use many_protocol::ResponseMessage;
-use many_types::Either;
-use std::collections::BTreeMap;
+use many_types::{Either, Timestamp};
+use minicbor::bytes::ByteVec;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::Read;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::time::Duration;
 use tracing::{debug, error, info};
 use tracing_subscriber::filter::LevelFilter;
 
+mod encrypt;
+mod list;
+mod upload;
+
+/// Arguments of `kvstore.get`, with the version to read.
+#[derive(minicbor::Encode)]
+#[cbor(map)]
+struct GetArgs {
+    #[n(0)]
+    key: ByteVec,
+
+    #[n(1)]
+    version: Option<u64>,
+}
+
+#[derive(minicbor::Decode)]
+#[cbor(map)]
+struct GetReturns {
+    #[n(0)]
+    value: Option<ByteVec>,
+
+    #[n(1)]
+    version: Option<u64>,
+}
+
+/// Arguments of `kvstore.put`, with the version the key must be at.
+#[derive(minicbor::Encode)]
+#[cbor(map)]
+struct PutArgs {
+    #[n(0)]
+    key: ByteVec,
+
+    #[n(1)]
+    value: ByteVec,
+
+    #[n(2)]
+    alternative_owner: Option<Address>,
+
+    #[n(3)]
+    if_version: Option<u64>,
+
+    #[n(4)]
+    expires: Option<Timestamp>,
+}
+
+/// Arguments of `kvstore.delete` and `kvstore.enable`.
+#[derive(minicbor::Encode)]
+#[cbor(map)]
+struct KeyArgs {
+    #[n(0)]
+    key: ByteVec,
+
+    #[n(1)]
+    alternative_owner: Option<Address>,
+}
+
+#[derive(
+    clap::ArgEnum,
+    Copy,
+    Clone,
+    Debug,
+    Eq,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    minicbor::Encode,
+    minicbor::Decode,
+)]
+#[cbor(index_only)]
+enum Permission {
+    #[n(0)]
+    Put,
+
+    #[n(1)]
+    Disable,
+
+    #[n(2)]
+    Transfer,
+}
+
+/// Arguments of `kvstore.grant` and `kvstore.revoke`.
+#[derive(minicbor::Encode)]
+#[cbor(map)]
+struct GrantArgs {
+    #[n(0)]
+    key: ByteVec,
+
+    #[n(1)]
+    address: Address,
+
+    #[n(2)]
+    permissions: Option<BTreeSet<Permission>>,
+
+    #[n(3)]
+    alternative_owner: Option<Address>,
+}
+
+#[derive(minicbor::Encode)]
+#[cbor(map)]
+struct GrantsArgs {
+    #[n(0)]
+    key: ByteVec,
+}
+
+#[derive(minicbor::Decode)]
+#[cbor(map)]
+struct GrantsReturns {
+    #[n(0)]
+    grants: BTreeMap<Address, BTreeSet<Permission>>,
+}
+
+#[derive(minicbor::Encode)]
+#[cbor(map)]
+struct UsageArgs {
+    #[n(0)]
+    owner: Option<Address>,
+}
+
+#[derive(minicbor::Decode)]
+#[cbor(map)]
+struct UsageReturns {
+    #[n(0)]
+    usage: u64,
+
+    #[n(1)]
+    quota: Option<u64>,
+
+    #[n(2)]
+    max_value_size: Option<u64>,
+}
+
 #[derive(clap::ArgEnum, Clone, Debug)]
 enum LogStrategy {
     Terminal,
@@ -70,8 +204,29 @@ enum SubCommand {
     /// Disable a value from the store.
     Disable(DisableOpt),
 
+    /// Enable a value which was disabled.
+    Enable(KeyOpt),
+
+    /// Delete a key and its value from the store.
+    Delete(KeyOpt),
+
     /// Transfer ownership of a key.
     Transfer(TransferOpt),
+
+    /// Allow an address to modify a key.
+    Grant(GrantOpt),
+
+    /// Revoke permissions of an address on a key.
+    Revoke(GrantOpt),
+
+    /// Show the permissions granted on a key.
+    Grants(QueryOpt),
+
+    /// Show the storage used by an owner and the limits of the store.
+    Usage(UsageOpt),
+
+    /// List the keys of the store.
+    List(list::ListOpt),
 }
 
 #[derive(Debug, Parser)]
@@ -86,6 +241,14 @@ struct GetOpt {
     /// Whether to output using hexadecimal, or regular value.
     #[clap(long)]
     hex: bool,
+
+    /// Get a previous version of the value.
+    #[clap(long)]
+    version: Option<u64>,
+
+    /// Decrypt a value which was put with `--encrypt`, using the PEM.
+    #[clap(long)]
+    decrypt: bool,
 }
 
 #[derive(Debug, Parser)]
@@ -114,6 +277,25 @@ struct PutOpt {
     /// Use this flag to use STDIN to get the value.
     #[clap(long, conflicts_with = "value")]
     stdin: bool,
+
+    /// Only put the value if the key is at this version (0 if the key must not
+    /// exist).
+    #[clap(long)]
+    if_version: Option<u64>,
+
+    /// Expire the key after this many seconds.
+    #[clap(long)]
+    expires_in: Option<u64>,
+
+    /// Encrypt the value with a key derived from the PEM, so only the holder
+    /// of the PEM can read it.
+    #[clap(long)]
+    encrypt: bool,
+
+    /// Upload the value in chunks of this many bytes, for values too large
+    /// for a single put.
+    #[clap(long, conflicts_with = "if_version", value_parser)]
+    chunk_size: Option<NonZeroUsize>,
 }
 
 #[derive(Debug, Parser)]
@@ -130,6 +312,22 @@ struct DisableOpt {
     reason: Option<String>,
 }
 
+#[derive(Debug, Parser)]
+struct KeyOpt {
+    /// The key.
+    key: String,
+
+    /// If the key is a hexadecimal string, pass this flag.
+    #[clap(long)]
+    hex_key: bool,
+}
+
+#[derive(Debug, Parser)]
+struct UsageOpt {
+    /// The owner to show the usage of. Defaults to the identity used.
+    owner: Option<Address>,
+}
+
 #[derive(Debug, Parser)]
 struct TransferOpt {
     /// The key to disable.
@@ -143,18 +341,48 @@ struct TransferOpt {
     new_owner: Address,
 }
 
-fn get(client: ManyClient<impl Identity>, key: &[u8], hex: bool) -> Result<(), ManyError> {
-    let arguments = kvstore::GetArgs {
+#[derive(Debug, Parser)]
+struct GrantOpt {
+    /// The key.
+    key: String,
+
+    /// If the key is a hexadecimal string, pass this flag.
+    #[clap(long)]
+    hex_key: bool,
+
+    /// The address to grant the permissions to, or revoke them from.
+    address: Address,
+
+    /// The permissions. When revoking, all of them if none are passed.
+    #[clap(long = "permission", arg_enum)]
+    permissions: Vec<Permission>,
+}
+
+fn get(
+    client: ManyClient<impl Identity>,
+    key: &[u8],
+    hex: bool,
+    version: Option<u64>,
+    value_key: Option<&encrypt::ValueKey>,
+) -> Result<(), ManyError> {
+    let arguments = GetArgs {
         key: key.to_vec().into(),
+        version,
     };
 
     let payload = client.call_("kvstore.get", arguments)?;
     if payload.is_empty() {
         Err(ManyError::unexpected_empty_response())
     } else {
-        let result: kvstore::GetReturns =
+        let result: GetReturns =
             minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
-        let value = result.value;
+        if let Some(version) = result.version {
+            info!("Version: {version}");
+        }
+        let value = match (result.value, value_key) {
+            (Some(value), Some(value_key)) => Some(value_key.decrypt(key, &value)?.into()),
+            (value, _) => value,
+        };
 
         if let Some(value) = value {
             if hex {
@@ -199,15 +427,24 @@ fn put(
     alt_owner: Option<Address>,
     key: &[u8],
     value: Vec<u8>,
+    if_version: Option<u64>,
+    expires: Option<Timestamp>,
+    value_key: Option<&encrypt::ValueKey>,
 ) -> Result<(), ManyError> {
-    let arguments = kvstore::PutArgs {
+    let value = match value_key {
+        Some(value_key) => value_key.encrypt(key, &value)?,
+        None => value,
+    };
+    let arguments = PutArgs {
         key: key.to_vec().into(),
         value: value.into(),
         alternative_owner: alt_owner,
+        if_version,
+        expires,
     };
 
     let response = client.call("kvstore.put", arguments)?;
-    let payload = wait_response(client, response)?;
+    let payload = wait_response(&client, response)?;
     println!("{}", minicbor::display(&payload));
     Ok(())
 }
@@ -225,11 +462,88 @@ fn disable(
     };
 
     let response = client.call("kvstore.disable", arguments)?;
-    let payload = wait_response(client, response)?;
+    let payload = wait_response(&client, response)?;
     println!("{}", minicbor::display(&payload));
     Ok(())
 }
 
+/// Call `kvstore.delete` or `kvstore.enable` on a key.
+fn key_command(
+    client: ManyClient<impl Identity>,
+    method: &str,
+    alt_owner: Option<Address>,
+    key: &[u8],
+) -> Result<(), ManyError> {
+    let arguments = KeyArgs {
+        key: key.to_vec().into(),
+        alternative_owner: alt_owner,
+    };
+
+    let response = client.call(method, arguments)?;
+    let payload = wait_response(&client, response)?;
+    println!("{}", minicbor::display(&payload));
+    Ok(())
+}
+
+/// Call `kvstore.grant` or `kvstore.revoke` on a key.
+fn grant_command(
+    client: ManyClient<impl Identity>,
+    method: &str,
+    alt_owner: Option<Address>,
+    key: &[u8],
+    address: Address,
+    permissions: Vec<Permission>,
+) -> Result<(), ManyError> {
+    let arguments = GrantArgs {
+        key: key.to_vec().into(),
+        address,
+        permissions: if permissions.is_empty() {
+            None
+        } else {
+            Some(permissions.into_iter().collect())
+        },
+        alternative_owner: alt_owner,
+    };
+
+    let response = client.call(method, arguments)?;
+    let payload = wait_response(&client, response)?;
+    println!("{}", minicbor::display(&payload));
+    Ok(())
+}
+
+fn grants(client: ManyClient<impl Identity>, key: &[u8]) -> Result<(), ManyError> {
+    let arguments = GrantsArgs {
+        key: key.to_vec().into(),
+    };
+
+    let payload = client.call_("kvstore.grants", arguments)?;
+    if payload.is_empty() {
+        Err(ManyError::unexpected_empty_response())
+    } else {
+        let result: GrantsReturns =
+            minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+        for (address, permissions) in result.grants {
+            println!("{address}: {permissions:?}");
+        }
+        Ok(())
+    }
+}
+
+fn usage(client: ManyClient<impl Identity>, owner: Option<Address>) -> Result<(), ManyError> {
+    let payload = client.call_("kvstore.usage", UsageArgs { owner })?;
+    if payload.is_empty() {
+        Err(ManyError::unexpected_empty_response())
+    } else {
+        let result: UsageReturns =
+            minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+        let unlimited = |limit: Option<u64>| limit.map_or("none".to_string(), |x| x.to_string());
+        println!("Usage: {} bytes", result.usage);
+        println!("Quota: {}", unlimited(result.quota));
+        println!("Maximum value size: {}", unlimited(result.max_value_size));
+        Ok(())
+    }
+}
+
 fn transfer(
     client: ManyClient<impl Identity>,
     alt_owner: Option<Address>,
@@ -243,13 +557,13 @@ fn transfer(
     };
 
     let response = client.call("kvstore.transfer", args)?;
-    let payload = wait_response(client, response)?;
+    let payload = wait_response(&client, response)?;
     println!("{}", minicbor::display(&payload));
     Ok(())
 }
 
 pub(crate) fn wait_response(
-    client: ManyClient<impl Identity>,
+    client: &ManyClient<impl Identity>,
     response: ResponseMessage,
 ) -> Result<Vec<u8>, ManyError> {
     let ResponseMessage {
@@ -313,6 +627,24 @@ pub(crate) fn wait_response(
     }
 }
 
+/// The key encrypting values, from the PEM. Exits with a usage error if there
+/// is no PEM, or it has no private key.
+fn value_key(pem: Option<&str>, flag: &str) -> encrypt::ValueKey {
+    let pem = pem.unwrap_or_else(|| {
+        Opts::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                format!("{flag} requires --pem"),
+            )
+            .exit()
+    });
+    encrypt::ValueKey::from_pem(pem).unwrap_or_else(|e| {
+        Opts::command()
+            .error(ErrorKind::InvalidValue, format!("Invalid --pem: {e}"))
+            .exit()
+    })
+}
+
 fn main() {
     let Opts {
         pem,
@@ -354,20 +686,28 @@ fn main() {
 
     debug!("{:?}", Opts::parse());
 
-    let key = pem.map_or_else(
+    let pem = pem.map(|p| std::fs::read_to_string(p).unwrap());
+    let key = pem.as_ref().map_or_else(
         || Box::new(AnonymousIdentity) as Box<dyn Identity>,
-        |p| Box::new(CoseKeyIdentity::from_pem(std::fs::read_to_string(p).unwrap()).unwrap()),
+        |pem| Box::new(CoseKeyIdentity::from_pem(pem.clone()).unwrap()),
     );
 
     let client = ManyClient::new(server, server_id, key).unwrap();
     let result = match subcommand {
-        SubCommand::Get(GetOpt { key, hex_key, hex }) => {
+        SubCommand::Get(GetOpt {
+            key,
+            hex_key,
+            hex,
+            version,
+            decrypt,
+        }) => {
             let key = if hex_key {
                 hex::decode(&key).unwrap()
             } else {
                 key.into_bytes()
             };
-            get(client, &key, hex)
+            let value_key = decrypt.then(|| value_key(pem.as_deref(), "--decrypt"));
+            get(client, &key, hex, version, value_key.as_ref())
         }
         SubCommand::Query(QueryOpt { key, hex_key }) => {
             let key = if hex_key {
@@ -382,6 +722,10 @@ fn main() {
             hex_key,
             value,
             stdin,
+            if_version,
+            expires_in,
+            encrypt,
+            chunk_size,
         }) => {
             let key = if hex_key {
                 hex::decode(&key).unwrap()
@@ -395,7 +739,34 @@ fn main() {
             } else {
                 value.expect("Must pass a value").into_bytes()
             };
-            put(client, alt_owner, &key, value)
+            let expires = expires_in.map(|secs| {
+                Timestamp::from_system_time(
+                    std::time::SystemTime::now() + Duration::from_secs(secs),
+                )
+                .unwrap()
+            });
+            let value_key = encrypt.then(|| value_key(pem.as_deref(), "--encrypt"));
+            if let Some(chunk_size) = chunk_size {
+                upload::put_chunked(
+                    client,
+                    alt_owner,
+                    &key,
+                    value,
+                    expires,
+                    chunk_size,
+                    value_key.as_ref(),
+                )
+            } else {
+                put(
+                    client,
+                    alt_owner,
+                    &key,
+                    value,
+                    if_version,
+                    expires,
+                    value_key.as_ref(),
+                )
+            }
         }
         SubCommand::Disable(DisableOpt {
             key,
@@ -410,6 +781,22 @@ fn main() {
             let reason = reason.map(|reason| Reason::new(123456, Some(reason), BTreeMap::new()));
             disable(client, alt_owner, &key, reason)
         }
+        SubCommand::Enable(KeyOpt { key, hex_key }) => {
+            let key = if hex_key {
+                hex::decode(&key).unwrap()
+            } else {
+                key.into_bytes()
+            };
+            key_command(client, "kvstore.enable", alt_owner, &key)
+        }
+        SubCommand::Delete(KeyOpt { key, hex_key }) => {
+            let key = if hex_key {
+                hex::decode(&key).unwrap()
+            } else {
+                key.into_bytes()
+            };
+            key_command(client, "kvstore.delete", alt_owner, &key)
+        }
         SubCommand::Transfer(TransferOpt {
             key,
             hex_key,
@@ -422,6 +809,60 @@ fn main() {
             };
             transfer(client, alt_owner, key, new_owner)
         }
+        SubCommand::Grant(GrantOpt {
+            key,
+            hex_key,
+            address,
+            permissions,
+        }) => {
+            let key = if hex_key {
+                hex::decode(&key).unwrap()
+            } else {
+                key.into_bytes()
+            };
+            if permissions.is_empty() {
+                error!("At least one permission must be granted.");
+                std::process::exit(1);
+            }
+            grant_command(
+                client,
+                "kvstore.grant",
+                alt_owner,
+                &key,
+                address,
+                permissions,
+            )
+        }
+        SubCommand::Revoke(GrantOpt {
+            key,
+            hex_key,
+            address,
+            permissions,
+        }) => {
+            let key = if hex_key {
+                hex::decode(&key).unwrap()
+            } else {
+                key.into_bytes()
+            };
+            grant_command(
+                client,
+                "kvstore.revoke",
+                alt_owner,
+                &key,
+                address,
+                permissions,
+            )
+        }
+        SubCommand::Grants(QueryOpt { key, hex_key }) => {
+            let key = if hex_key {
+                hex::decode(&key).unwrap()
+            } else {
+                key.into_bytes()
+            };
+            grants(client, &key)
+        }
+        SubCommand::Usage(UsageOpt { owner }) => usage(client, owner),
+        SubCommand::List(opts) => list::list(client, opts),
     };
 
     if let Err(err) = result {
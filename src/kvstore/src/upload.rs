@@ -0,0 +1,127 @@
+use crate::encrypt::ValueKey;
+use crate::wait_response;
+use many_client::client::blocking::ManyClient;
+use many_error::ManyError;
+use many_identity::{Address, Identity};
+use many_types::Timestamp;
+use minicbor::bytes::ByteVec;
+use sha3::{Digest, Sha3_256};
+use std::num::NonZeroUsize;
+use tracing::{error, info};
+
+/// Arguments of the `kvstore.beginUpload` endpoint of many-kvstore.
+#[derive(minicbor::Encode)]
+#[cbor(map)]
+struct BeginUploadArgs {
+    #[n(0)]
+    key: ByteVec,
+
+    #[n(1)]
+    size: u64,
+
+    #[n(2)]
+    hash: ByteVec,
+
+    #[n(3)]
+    alternative_owner: Option<Address>,
+
+    #[n(4)]
+    expires: Option<Timestamp>,
+}
+
+#[derive(minicbor::Decode)]
+#[cbor(map)]
+struct BeginUploadReturns {
+    #[n(0)]
+    upload: u64,
+}
+
+#[derive(minicbor::Encode)]
+#[cbor(map)]
+struct PutChunkArgs {
+    #[n(0)]
+    upload: u64,
+
+    #[n(1)]
+    index: u32,
+
+    #[n(2)]
+    data: ByteVec,
+}
+
+/// Arguments of `kvstore.finalizeUpload` and `kvstore.abortUpload`.
+#[derive(minicbor::Encode)]
+#[cbor(map)]
+struct UploadArgs {
+    #[n(0)]
+    upload: u64,
+}
+
+fn send_chunks(
+    client: &ManyClient<impl Identity>,
+    upload: u64,
+    value: &[u8],
+    chunk_size: NonZeroUsize,
+) -> Result<Vec<u8>, ManyError> {
+    let progress = indicatif::ProgressBar::new(value.len() as u64);
+    for (index, chunk) in value.chunks(chunk_size.get()).enumerate() {
+        let arguments = PutChunkArgs {
+            upload,
+            index: index as u32,
+            data: chunk.to_vec().into(),
+        };
+        let response = client.call("kvstore.putChunk", arguments)?;
+        wait_response(client, response)?;
+        progress.inc(chunk.len() as u64);
+    }
+    progress.finish();
+
+    let response = client.call("kvstore.finalizeUpload", UploadArgs { upload })?;
+    wait_response(client, response)
+}
+
+/// Put a value in chunks of `chunk_size` bytes, for values too large for a
+/// single `kvstore.put`. The upload is aborted if a chunk cannot be sent.
+pub fn put_chunked(
+    client: ManyClient<impl Identity>,
+    alt_owner: Option<Address>,
+    key: &[u8],
+    value: Vec<u8>,
+    expires: Option<Timestamp>,
+    chunk_size: NonZeroUsize,
+    value_key: Option<&ValueKey>,
+) -> Result<(), ManyError> {
+    let value = match value_key {
+        Some(value_key) => value_key.encrypt(key, &value)?,
+        None => value,
+    };
+    let arguments = BeginUploadArgs {
+        key: key.to_vec().into(),
+        size: value.len() as u64,
+        hash: Sha3_256::digest(&value).to_vec().into(),
+        alternative_owner: alt_owner,
+        expires,
+    };
+
+    let response = client.call("kvstore.beginUpload", arguments)?;
+    let payload = wait_response(&client, response)?;
+    let BeginUploadReturns { upload } =
+        minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+    info!("Upload: {upload}");
+
+    match send_chunks(&client, upload, &value, chunk_size) {
+        Ok(payload) => {
+            println!("{}", minicbor::display(&payload));
+            Ok(())
+        }
+        Err(err) => {
+            let aborted = client
+                .call("kvstore.abortUpload", UploadArgs { upload })
+                .and_then(|response| wait_response(&client, response));
+            if let Err(abort_err) = aborted {
+                error!("Could not abort upload {upload}: {abort_err}");
+            }
+            Err(err)
+        }
+    }
+}
@@ -23,6 +23,7 @@ use tracing_subscriber::filter::LevelFilter;
 
 mod multisig;
 mod tokens;
+mod transactions;
 
 #[derive(clap::ArgEnum, Clone, Debug)]
 enum LogStrategy {
@@ -120,6 +121,10 @@ enum SubCommand {
 
     /// Perform a token operation
     Token(tokens::CommandOpt),
+
+    /// List the transactions sent by an account. The server must be a many-abci
+    /// frontend.
+    Transactions(transactions::TransactionsOpt),
 }
 
 #[derive(Parser)]
@@ -431,6 +436,7 @@ fn main() {
         }
         SubCommand::Multisig(opts) => multisig::multisig(client, opts),
         SubCommand::Token(opts) => tokens::tokens(client, opts),
+        SubCommand::Transactions(opts) => transactions::transactions(client, client_address, opts),
     };
 
     if let Err(err) = result {
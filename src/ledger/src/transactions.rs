@@ -0,0 +1,90 @@
+use clap::Parser;
+use many_abci_types::{
+    SearchTransactionsArgs, SearchTransactionsReturns, SEARCH_TRANSACTIONS_ENDPOINT,
+};
+use many_client::client::blocking::ManyClient;
+use many_error::ManyError;
+use many_identity::{Address, Identity};
+use many_types::SortOrder;
+
+#[derive(Parser)]
+pub struct TransactionsOpt {
+    /// The identity to list the transactions of. If omitted it will use the identity of
+    /// the caller.
+    identity: Option<Address>,
+
+    /// Only list the transactions calling this method, e.g. `ledger.send`.
+    #[clap(long)]
+    method: Option<String>,
+
+    /// The maximum number of transactions to list.
+    #[clap(long)]
+    count: Option<u64>,
+
+    /// The page of `--count` transactions to list, starting at 1.
+    #[clap(long)]
+    page: Option<u64>,
+
+    /// Only list failed transactions.
+    #[clap(long, conflicts_with("succeeded"))]
+    failed: bool,
+
+    /// Only list successful transactions.
+    #[clap(long)]
+    succeeded: bool,
+
+    /// List the most recent transactions first.
+    #[clap(long)]
+    descending: bool,
+}
+
+pub fn transactions(
+    client: ManyClient<impl Identity>,
+    client_address: Address,
+    opts: TransactionsOpt,
+) -> Result<(), ManyError> {
+    let TransactionsOpt {
+        identity,
+        method,
+        count,
+        page,
+        failed,
+        succeeded,
+        descending,
+    } = opts;
+
+    let args = SearchTransactionsArgs {
+        count,
+        order: Some(if descending {
+            SortOrder::Descending
+        } else {
+            SortOrder::Ascending
+        }),
+        from: Some(identity.unwrap_or(client_address)),
+        method,
+        success: match (failed, succeeded) {
+            (true, _) => Some(false),
+            (_, true) => Some(true),
+            _ => None,
+        },
+        page,
+        ..Default::default()
+    };
+
+    let payload = client.call_(SEARCH_TRANSACTIONS_ENDPOINT, args)?;
+    let result: SearchTransactionsReturns =
+        minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+
+    for tx in result.transactions {
+        println!(
+            "{:>10} {} {:<24} {}",
+            tx.height,
+            hex::encode(&tx.txn.id.hash),
+            tx.method.unwrap_or_default(),
+            if tx.success { "ok" } else { "error" },
+        );
+    }
+    println!("{} transactions in total", result.total_count);
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Types of the endpoints the MANY frontend of `many-abci` serves on top of the
+//! `many-modules` ones, shared with the clients calling them.
+use many_identity::Address;
+use many_types::blockchain::Transaction;
+use many_types::{CborRange, SortOrder};
+
+pub const SEARCH_TRANSACTIONS_ENDPOINT: &str = "blockchain.searchTransactions";
+
+#[derive(Clone, Debug, Default, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct SearchTransactionsArgs {
+    #[n(0)]
+    pub count: Option<u64>,
+
+    #[n(1)]
+    pub order: Option<SortOrder>,
+
+    /// Only return transactions sent by this address. Transactions whose
+    /// envelope is not signed by their sender are not indexed by sender.
+    #[n(2)]
+    pub from: Option<Address>,
+
+    /// Only return transactions calling this method.
+    #[n(3)]
+    pub method: Option<String>,
+
+    #[n(4)]
+    pub height: Option<CborRange<u64>>,
+
+    /// Only return successful (`true`) or failed (`false`) transactions.
+    #[n(5)]
+    pub success: Option<bool>,
+
+    /// The page of `count` transactions to return, starting at 1.
+    #[n(6)]
+    pub page: Option<u64>,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct SearchedTransaction {
+    #[n(0)]
+    pub height: u64,
+
+    #[n(1)]
+    pub method: Option<String>,
+
+    #[n(2)]
+    pub success: bool,
+
+    /// The response of the transaction is `None` if its result data could not
+    /// be decoded.
+    #[n(3)]
+    pub txn: Transaction,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct SearchTransactionsReturns {
+    #[n(0)]
+    pub transactions: Vec<SearchedTransaction>,
+
+    /// The number of transactions matching the search, on all pages.
+    #[n(1)]
+    pub total_count: u64,
+}
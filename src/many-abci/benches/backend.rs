@@ -0,0 +1,94 @@
+//! Throughput of the ABCI -> MANY application forwarding path.
+//!
+//! Runs a MANY server locally, then sends the same envelopes from several
+//! threads (like the tendermint connections do) with the previous approach
+//! (`block_on(send_envelope)` on every call) and with `BackendClient`.
+//!
+//! Run with `cargo bench -p many-abci`.
+use coset::CoseSign1;
+use many_abci::backend::BackendClient;
+use many_identity::verifiers::AnonymousVerifier;
+use many_identity::{Address, AnonymousIdentity};
+use many_protocol::{encode_cose_sign1_from_request, RequestMessageBuilder};
+use many_server::transport::http::HttpServer;
+use many_server::ManyServer;
+use std::time::{Duration, Instant};
+
+const THREADS: usize = 8;
+const REQUESTS_PER_THREAD: usize = 500;
+
+fn envelope() -> CoseSign1 {
+    let message = RequestMessageBuilder::default()
+        .method("status".to_string())
+        .from(Address::anonymous())
+        .build()
+        .unwrap();
+    encode_cose_sign1_from_request(message, &AnonymousIdentity).unwrap()
+}
+
+fn start_server() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    std::thread::spawn(move || {
+        let server = ManyServer::simple("bench", AnonymousIdentity, AnonymousVerifier, None);
+        let mut http = HttpServer::new(server);
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(http.bind(addr.to_string()))
+            .unwrap();
+    });
+
+    // Leave the server some time to bind.
+    std::thread::sleep(Duration::from_millis(500));
+    format!("http://{addr}/")
+}
+
+fn run(name: &str, send: impl Fn(CoseSign1) + Clone + Send + 'static) {
+    let start = Instant::now();
+    let threads: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let send = send.clone();
+            std::thread::spawn(move || {
+                for _ in 0..REQUESTS_PER_THREAD {
+                    send(envelope());
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    let elapsed = start.elapsed();
+    let total = THREADS * REQUESTS_PER_THREAD;
+    println!(
+        "{name:<24} {total} requests in {:>8.2?} ({:>8.0} req/s)",
+        elapsed,
+        total as f64 / elapsed.as_secs_f64()
+    );
+}
+
+fn main() {
+    let url: reqwest::Url = start_server().parse().unwrap();
+
+    run("block_on(send_envelope)", {
+        let url = url.clone();
+        move |envelope| {
+            many_client::client::blocking::block_on(many_client::client::send_envelope(
+                url.clone(),
+                envelope,
+            ))
+            .unwrap();
+        }
+    });
+
+    let backend = BackendClient::new(url, 4).unwrap();
+    run("BackendClient", move |envelope| {
+        let b = backend.clone();
+        backend
+            .block_on(async move { b.send_envelope(envelope).await })
+            .unwrap();
+    });
+}
@@ -1,49 +1,416 @@
+use crate::normalize::NormalizationPolicy;
+use crate::router::{composite_hash, BackendRouter};
 use coset::{CborSerializable, CoseSign1};
-use many_client::client::blocking::{block_on, ManyClient};
 use many_error::ManyError;
-use many_identity::{Address, AnonymousIdentity};
-use many_modules::abci_backend::{AbciBlock, AbciCommitInfo, AbciInfo};
-use many_protocol::ResponseMessage;
-use reqwest::{IntoUrl, Url};
+use many_identity::verifiers::AnonymousVerifier;
+use many_identity_dsa::CoseKeyVerifier;
+use many_identity_webauthn::WebAuthnVerifier;
+use many_modules::abci_backend::{AbciBlock, AbciInfo};
+use many_modules::ledger;
+use many_protocol::{decode_request_from_cose_sign1, ManyUrl, RequestMessage, ResponseMessage};
+use minicbor::bytes::ByteVec;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use tendermint_abci::Application;
 use tendermint_proto::abci::*;
 use tracing::debug;
 
-lazy_static::lazy_static!(
-    static ref EPOCH: many_types::Timestamp = many_types::Timestamp::new(0).unwrap();
-);
+/// ABCI code returned by `check_tx` and `deliver_tx` when the transaction is
+/// not a COSE envelope.
+pub const INVALID_ENVELOPE_CODE: u32 = 2;
+
+/// ABCI code returned by `check_tx` when the signature of the envelope does not
+/// verify, or its payload is not a MANY request.
+pub const INVALID_REQUEST_CODE: u32 = 4;
+
+/// ABCI code returned by `check_tx` when the method of the request is not a
+/// command of a backend.
+pub const INVALID_METHOD_CODE: u32 = 5;
+
+/// ABCI code returned by `deliver_tx` when the backend answered with a MANY
+/// error. The MANY error code itself is put in the `codespace` field.
+pub const MANY_ERROR_CODE: u32 = 6;
+
+fn event_attribute(key: &str, value: impl ToString) -> EventAttribute {
+    EventAttribute {
+        key: key.to_string().into_bytes().into(),
+        value: value.to_string().into_bytes().into(),
+        index: true,
+    }
+}
+
+/// Build the tendermint events of a transaction, so that indexers can search by
+/// sender, method and (for ledger transactions) symbol. The sender is only
+/// indexed if the envelope was signed by it, so that a forged transaction is not
+/// found under the address it claims.
+fn events_from_request(request: &RequestMessage, verified: bool) -> Vec<Event> {
+    let mut attributes = vec![event_attribute("method", &request.method)];
+    if verified {
+        attributes.push(event_attribute("from", request.from()));
+    }
+    let mut events = vec![Event {
+        r#type: "many".to_string(),
+        attributes,
+    }];
+
+    if request.method == "ledger.send" {
+        if let Ok(args) = minicbor::decode::<ledger::SendArgs>(&request.data) {
+            events.push(Event {
+                r#type: "ledger".to_string(),
+                attributes: vec![
+                    event_attribute("symbol", args.symbol),
+                    event_attribute("to", args.to),
+                ],
+            });
+        }
+    }
+
+    events
+}
+
+/// The endpoint of the backend managing the validator set, if any.
+const VALIDATORS_UPDATE_ENDPOINT: &str = "validators.update";
+
+/// The arguments of `abci.initChain` when the genesis has an application state,
+/// or validators for the backend managing the validator set.
+#[derive(minicbor::Encode)]
+#[cbor(map)]
+struct BackendInitChainArgs {
+    #[n(0)]
+    app_state: Option<ByteVec>,
+
+    #[n(1)]
+    validators: Option<Vec<BackendValidatorUpdate>>,
+}
+
+/// The return value of `abci.initChain`. Backends which don't support genesis
+/// states return an empty value.
+#[derive(Default, minicbor::Decode)]
+#[cbor(map)]
+struct BackendInitChain {
+    #[n(0)]
+    app_hash: Option<ByteVec>,
+}
+
+/// A validator update returned by the backend in `abci.endBlock`, or a genesis
+/// validator sent in `abci.initChain`. A power of zero removes the validator.
+#[derive(Clone, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+struct BackendValidatorUpdate {
+    #[n(0)]
+    pub_key: ByteVec,
+
+    #[n(1)]
+    power: u64,
+}
+
+/// The return value of `abci.endBlock` of the backend managing the validator
+/// set. The results of the other backends are ignored.
+#[derive(minicbor::Decode)]
+#[cbor(map)]
+struct BackendEndBlock {
+    #[n(0)]
+    validator_updates: Vec<BackendValidatorUpdate>,
+}
+
+impl TryFrom<BackendValidatorUpdate> for ValidatorUpdate {
+    type Error = String;
+
+    fn try_from(update: BackendValidatorUpdate) -> Result<Self, Self::Error> {
+        Ok(ValidatorUpdate {
+            pub_key: Some(tendermint_proto::crypto::PublicKey {
+                sum: Some(tendermint_proto::crypto::public_key::Sum::Ed25519(
+                    update.pub_key.to_vec(),
+                )),
+            }),
+            power: i64::try_from(update.power)
+                .map_err(|_| format!("Invalid validator power: {}.", update.power))?,
+        })
+    }
+}
+
+/// A genesis validator, for the backend managing the validator set. Only
+/// ed25519 validators are supported.
+fn genesis_validator(update: &ValidatorUpdate) -> Option<BackendValidatorUpdate> {
+    match update.pub_key.as_ref()?.sum.as_ref()? {
+        tendermint_proto::crypto::public_key::Sum::Ed25519(key) => Some(BackendValidatorUpdate {
+            pub_key: key.clone().into(),
+            power: u64::try_from(update.power).ok()?,
+        }),
+        _ => None,
+    }
+}
+
+/// Split the genesis application state between the backends. A single backend
+/// receives the whole state. With several backends, the state must be a JSON
+/// object with the state of each backend under its name, e.g.
+/// `{"many-ledger": {...}, "many-kvstore": {...}}`; backends without an entry
+/// have no genesis state.
+fn split_app_state(
+    backends: &BackendRouter,
+    app_state: &[u8],
+) -> Result<Vec<Option<Vec<u8>>>, String> {
+    let count = backends.backends().count();
+    if app_state.is_empty() {
+        return Ok(vec![None; count]);
+    }
+    if count == 1 {
+        return Ok(vec![Some(app_state.to_vec())]);
+    }
+
+    let mut states: BTreeMap<String, serde_json::Value> =
+        serde_json::from_slice(app_state).map_err(|e| e.to_string())?;
+    let split = backends
+        .backends()
+        .map(|(name, _)| {
+            states
+                .remove(name)
+                .map(|state| serde_json::to_vec(&state).map_err(|e| e.to_string()))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match states.keys().next() {
+        Some(name) => Err(format!("There is no backend named '{name}'.")),
+        None => Ok(split),
+    }
+}
+
+/// Tracks the last block committed by the ABCI application, so that the MANY
+/// frontend can query the backends directly between two blocks.
+///
+/// The value is the height shifted left by two, with flags in the lowest bits
+/// while a block is executing or the height is unknown (e.g. after a failed
+/// commit, until the next `info`).
+#[derive(Clone, Debug)]
+pub struct BlockTracker(Arc<AtomicU64>);
+
+const BLOCK_EXECUTING: u64 = 0b01;
+const BLOCK_HEIGHT_UNKNOWN: u64 = 0b10;
+
+impl Default for BlockTracker {
+    fn default() -> Self {
+        Self(Arc::new(AtomicU64::new(BLOCK_HEIGHT_UNKNOWN)))
+    }
+}
+
+impl BlockTracker {
+    fn set_height(&self, height: u64) {
+        self.0.store(height << 2, Ordering::SeqCst);
+    }
+
+    fn begin_block(&self) {
+        self.0.fetch_or(BLOCK_EXECUTING, Ordering::SeqCst);
+    }
+
+    fn commit(&self) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |value| {
+                if value & BLOCK_HEIGHT_UNKNOWN == 0 {
+                    Some(((value >> 2) + 1) << 2)
+                } else {
+                    None
+                }
+            });
+    }
+
+    fn invalidate(&self) {
+        self.0.fetch_or(BLOCK_HEIGHT_UNKNOWN, Ordering::SeqCst);
+    }
+
+    /// The height of the last committed block, or `None` while a block is
+    /// executing.
+    pub fn committed_height(&self) -> Option<u64> {
+        let value = self.0.load(Ordering::SeqCst);
+        if value & (BLOCK_EXECUTING | BLOCK_HEIGHT_UNKNOWN) == 0 {
+            Some(value >> 2)
+        } else {
+            None
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct AbciApp {
-    app_name: String,
-    many_client: ManyClient<AnonymousIdentity>,
-    many_url: Url,
+    backends: BackendRouter,
+    allow_origin: Option<Vec<ManyUrl>>,
+
+    /// Normalization of the backend responses, which are part of the app hash.
+    normalization: NormalizationPolicy,
+
+    blocks: BlockTracker,
 }
 
 impl AbciApp {
     /// Constructor.
-    pub fn create<U>(many_url: U, server_id: Address) -> Result<Self, String>
-    where
-        U: IntoUrl,
-    {
-        let many_url = many_url.into_url().map_err(|e| e.to_string())?;
-
-        // TODO: Get the server ID from the many server.
-        // let server_id = if server_id.is_anonymous() {
-        //     server_id
-        // } else {
-        //     server_id
-        // };
-
-        let many_client = ManyClient::new(many_url.clone(), server_id, AnonymousIdentity)?;
-        let status = many_client.status().map_err(|x| x.to_string())?;
-        let app_name = status.name;
-
-        Ok(Self {
-            app_name,
-            many_url,
-            many_client,
-        })
+    pub fn new(backends: BackendRouter, allow_origin: Option<Vec<ManyUrl>>) -> Self {
+        Self {
+            backends,
+            allow_origin,
+            normalization: NormalizationPolicy::default(),
+            blocks: BlockTracker::default(),
+        }
+    }
+
+    /// The tracker of the blocks committed by this application.
+    pub fn block_tracker(&self) -> BlockTracker {
+        self.blocks.clone()
+    }
+
+    /// Verify the signature of an envelope and decode its request.
+    fn verify(&self, cose: &CoseSign1) -> Result<RequestMessage, ManyError> {
+        decode_request_from_cose_sign1(
+            cose,
+            &(
+                AnonymousVerifier,
+                CoseKeyVerifier,
+                WebAuthnVerifier::new(self.allow_origin.clone()),
+            ),
+        )
+    }
+
+    /// Validate a transaction before it enters the mempool. The envelope must be
+    /// properly signed and target a command endpoint of the backend.
+    fn validate_tx(&self, tx: &[u8]) -> Result<(), (u32, String)> {
+        let cose =
+            CoseSign1::from_slice(tx).map_err(|err| (INVALID_ENVELOPE_CODE, err.to_string()))?;
+        let message = self
+            .verify(&cose)
+            .map_err(|err| (INVALID_REQUEST_CODE, err.to_string()))?;
+
+        match self.backends.endpoint(&message.method) {
+            Some(info) if info.is_command => Ok(()),
+            Some(_) => Err((
+                INVALID_METHOD_CODE,
+                format!("Method '{}' is not a command.", message.method),
+            )),
+            None => Err((
+                INVALID_METHOD_CODE,
+                ManyError::invalid_method_name(message.method).to_string(),
+            )),
+        }
+    }
+
+    /// Begin the block on every backend. A backend which fails to begin or end
+    /// a block would execute its transactions with a different state than the
+    /// other nodes and diverge from them, so this fails the block instead.
+    fn begin_backend_block(&self, time: Option<u64>) {
+        self.blocks.begin_block();
+        let block = AbciBlock { time };
+        let results = self.backends.call_all_sync("abci.beginBlock", block);
+        for ((name, _), result) in self.backends.backends().zip(results) {
+            if let Err(err) = result {
+                panic!("Could not begin the block of '{name}': {err}");
+            }
+        }
+    }
+
+    /// Returns the validator updates of the block, from the backend managing
+    /// the validator set. Like `begin_backend_block`, this fails the block if
+    /// a backend fails to end it.
+    fn end_backend_block(&self) -> Vec<ValidatorUpdate> {
+        let validators_backend = self.backends.backend_index(VALIDATORS_UPDATE_ENDPOINT);
+        let results = self.backends.call_all_sync("abci.endBlock", ());
+
+        let mut updates = Vec::new();
+        for (index, ((name, _), result)) in self.backends.backends().zip(results).enumerate() {
+            let payload =
+                result.unwrap_or_else(|err| panic!("Could not end the block of '{name}': {err}"));
+            if Some(index) != validators_backend {
+                continue;
+            }
+
+            let end_block: BackendEndBlock = minicbor::decode(&payload)
+                .unwrap_or_else(|err| panic!("Invalid abci.endBlock result of '{name}': {err}"));
+            for update in end_block.validator_updates {
+                updates.push(
+                    ValidatorUpdate::try_from(update).unwrap_or_else(|err| {
+                        panic!("Invalid validator update of '{name}': {err}")
+                    }),
+                );
+            }
+        }
+        updates
+    }
+
+    /// Execute a transaction on the backend and build its result.
+    fn execute_tx(&self, tx: &[u8]) -> ResponseDeliverTx {
+        let cose = match CoseSign1::from_slice(tx) {
+            Ok(x) => x,
+            Err(err) => {
+                return ResponseDeliverTx {
+                    code: INVALID_ENVELOPE_CODE,
+                    log: err.to_string(),
+                    ..Default::default()
+                }
+            }
+        };
+        let request = cose
+            .payload
+            .as_deref()
+            .and_then(|payload| RequestMessage::from_bytes(payload).ok());
+        let verified = self.verify(&cose).is_ok();
+        let mut events = request
+            .as_ref()
+            .map(|request| events_from_request(request, verified))
+            .unwrap_or_default();
+
+        let backend = self
+            .backends
+            .route(request.as_ref().map_or("", |r| r.method.as_str()))
+            .clone();
+        match self
+            .backends
+            .block_on(async move { backend.send_envelope(cose).await })
+        {
+            Ok(cose_sign) => {
+                let payload = cose_sign.payload.unwrap_or_default();
+                let response = self
+                    .normalization
+                    .normalize(ResponseMessage::from_bytes(&payload).unwrap_or_default());
+
+                if let Some(event) = events.iter_mut().find(|e| e.r#type == "many") {
+                    event
+                        .attributes
+                        .push(event_attribute("success", response.data.is_ok()));
+                }
+
+                // Errors from the backend are still part of the block, but are
+                // flagged so that indexers and clients can filter them.
+                let (code, codespace, log) = match &response.data {
+                    Ok(_) => (0, String::new(), String::new()),
+                    Err(err) => (
+                        MANY_ERROR_CODE,
+                        i64::from(err.code()).to_string(),
+                        err.to_string(),
+                    ),
+                };
+
+                if let Ok(data) = response.to_bytes() {
+                    ResponseDeliverTx {
+                        code,
+                        data: data.into(),
+                        log,
+                        events,
+                        codespace,
+                        ..Default::default()
+                    }
+                } else {
+                    ResponseDeliverTx {
+                        code: 3,
+                        ..Default::default()
+                    }
+                }
+            }
+            Err(err) => ResponseDeliverTx {
+                code: 1,
+                data: vec![].into(),
+                log: err.to_string(),
+                ..Default::default()
+            },
+        }
     }
 }
 
@@ -54,29 +421,84 @@ impl Application for AbciApp {
             request.version, request.block_version, request.p2p_version
         );
 
+        let backends = self.backends.clone();
         let AbciInfo { height, hash } =
-            match self.many_client.call_("abci.info", ()).and_then(|payload| {
-                minicbor::decode(&payload).map_err(ManyError::deserialization_error)
-            }) {
+            match self.backends.block_on(async move { backends.info().await }) {
                 Ok(x) => x,
                 Err(err) => {
+                    self.blocks.invalidate();
                     return ResponseInfo {
                         data: format!("An error occurred during call to abci.info:\n{err}"),
                         ..Default::default()
-                    }
+                    };
                 }
             };
 
+        self.blocks.set_height(height);
         ResponseInfo {
-            data: format!("many-abci-bridge({})", self.app_name),
+            data: format!("many-abci-bridge({})", self.backends.name()),
             version: env!("CARGO_PKG_VERSION").to_string(),
             app_version: 1,
             last_block_height: height as i64,
             last_block_app_hash: hash.to_vec().into(),
         }
     }
-    fn init_chain(&self, _request: RequestInitChain) -> ResponseInitChain {
-        Default::default()
+    fn init_chain(&self, request: RequestInitChain) -> ResponseInitChain {
+        let app_states = split_app_state(&self.backends, &request.app_state_bytes)
+            .unwrap_or_else(|err| panic!("Invalid genesis application state: {err}"));
+
+        // The backend managing the validator set gets the genesis validators, so
+        // it can update or remove them later.
+        let validators_backend = self.backends.backend_index(VALIDATORS_UPDATE_ENDPOINT);
+        let genesis_validators: Vec<BackendValidatorUpdate> = request
+            .validators
+            .iter()
+            .filter_map(genesis_validator)
+            .collect();
+
+        let mut hashes = Vec::new();
+        let backends = self.backends.backends().zip(app_states).enumerate();
+        for (index, ((name, backend), app_state)) in backends {
+            let validators = (Some(index) == validators_backend && !genesis_validators.is_empty())
+                .then(|| genesis_validators.clone());
+            let required = app_state.is_some() || validators.is_some();
+            let result = if required {
+                backend.call_sync(
+                    "abci.initChain",
+                    BackendInitChainArgs {
+                        app_state: app_state.map(Into::into),
+                        validators,
+                    },
+                )
+            } else {
+                backend.call_sync("abci.initChain", ())
+            };
+
+            let init: BackendInitChain = match result {
+                Ok(payload) => minicbor::decode(&payload).unwrap_or_default(),
+                // The chain cannot start without its genesis state.
+                Err(err) if required => {
+                    panic!("Could not initialize '{name}' from the genesis: {err}")
+                }
+                Err(err) => {
+                    tracing::error!("An error occurred during call to abci.initChain: {err}");
+                    Default::default()
+                }
+            };
+            hashes.push(init.app_hash.map(|h| h.to_vec()));
+        }
+
+        // The app hash is only known if every backend knows its own.
+        let app_hash = hashes
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .map(|hashes| composite_hash(&hashes))
+            .unwrap_or_default();
+
+        ResponseInitChain {
+            app_hash: app_hash.into(),
+            ..Default::default()
+        }
     }
     fn query(&self, request: RequestQuery) -> ResponseQuery {
         let cose = match CoseSign1::from_slice(&request.data) {
@@ -89,10 +511,17 @@ impl Application for AbciApp {
                 }
             }
         };
-        let value = match block_on(many_client::client::send_envelope(
-            self.many_url.clone(),
-            cose,
-        )) {
+        let method = cose
+            .payload
+            .as_deref()
+            .and_then(|payload| RequestMessage::from_bytes(payload).ok())
+            .map(|request| request.method)
+            .unwrap_or_default();
+        let backend = self.backends.route(&method).clone();
+        let value = match self
+            .backends
+            .block_on(async move { backend.send_envelope(cose).await })
+        {
             Ok(cose_sign) => cose_sign,
 
             Err(err) => {
@@ -123,62 +552,36 @@ impl Application for AbciApp {
             .header
             .and_then(|x| x.time.map(|x| x.seconds as u64));
 
-        let block = AbciBlock { time };
-        let _ = self.many_client.call_("abci.beginBlock", block);
+        self.begin_backend_block(time);
         ResponseBeginBlock { events: vec![] }
     }
 
-    fn deliver_tx(&self, request: RequestDeliverTx) -> ResponseDeliverTx {
-        let cose = match CoseSign1::from_slice(&request.tx) {
-            Ok(x) => x,
-            Err(err) => {
-                return ResponseDeliverTx {
-                    code: 2,
-                    log: err.to_string(),
+    fn check_tx(&self, request: RequestCheckTx) -> ResponseCheckTx {
+        match self.validate_tx(&request.tx) {
+            Ok(()) => ResponseCheckTx {
+                code: 0,
+                ..Default::default()
+            },
+            Err((code, log)) => {
+                debug!("Rejected transaction in check_tx: {log}");
+                ResponseCheckTx {
+                    code,
+                    log,
                     ..Default::default()
                 }
             }
-        };
-        match block_on(many_client::client::send_envelope(
-            self.many_url.clone(),
-            cose,
-        )) {
-            Ok(cose_sign) => {
-                let payload = cose_sign.payload.unwrap_or_default();
-                let mut response = ResponseMessage::from_bytes(&payload).unwrap_or_default();
-
-                // Consensus will sign the result, so the `from` field is unnecessary.
-                response.from = Address::anonymous();
-                // The version is ignored and removed.
-                response.version = None;
-                // The timestamp MIGHT differ between two nodes so we just force it to be 0.
-                response.timestamp = Some(*EPOCH);
-
-                if let Ok(data) = response.to_bytes() {
-                    ResponseDeliverTx {
-                        code: 0,
-                        data: data.into(),
-                        ..Default::default()
-                    }
-                } else {
-                    ResponseDeliverTx {
-                        code: 3,
-                        ..Default::default()
-                    }
-                }
-            }
-            Err(err) => ResponseDeliverTx {
-                code: 1,
-                data: vec![].into(),
-                log: err.to_string(),
-                ..Default::default()
-            },
         }
     }
 
+    fn deliver_tx(&self, request: RequestDeliverTx) -> ResponseDeliverTx {
+        self.execute_tx(&request.tx)
+    }
+
     fn end_block(&self, _request: RequestEndBlock) -> ResponseEndBlock {
-        let _ = self.many_client.call_("abci.endBlock", ());
-        Default::default()
+        ResponseEndBlock {
+            validator_updates: self.end_backend_block(),
+            ..Default::default()
+        }
     }
 
     fn flush(&self) -> ResponseFlush {
@@ -186,13 +589,16 @@ impl Application for AbciApp {
     }
 
     fn commit(&self) -> ResponseCommit {
-        self.many_client.call_("abci.commit", ()).map_or_else(
-            |err| ResponseCommit {
-                data: err.to_string().into_bytes().into(),
-                retain_height: 0,
+        self.backends.commit().map_or_else(
+            |err| {
+                self.blocks.invalidate();
+                ResponseCommit {
+                    data: err.to_string().into_bytes().into(),
+                    retain_height: 0,
+                }
             },
-            |msg| {
-                let info: AbciCommitInfo = minicbor::decode(&msg).unwrap();
+            |info| {
+                self.blocks.commit();
                 ResponseCommit {
                     data: info.hash.to_vec().into(),
                     retain_height: info.retain_height as i64,
@@ -0,0 +1,162 @@
+use coset::{CborSerializable, CoseSign1};
+use many_error::ManyError;
+use many_identity::verifiers::AnonymousVerifier;
+use many_identity::{Address, AnonymousIdentity};
+use many_identity_dsa::CoseKeyVerifier;
+use many_protocol::{
+    decode_response_from_cose_sign1, encode_cose_sign1_from_request, RequestMessageBuilder,
+};
+use many_server::transport::LowLevelManyRequestHandler;
+use reqwest::{IntoUrl, Url};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Number of idle connections kept open to the backend MANY app.
+const MAXIMUM_IDLE_CONNECTIONS: usize = 32;
+
+#[derive(Clone)]
+enum Transport {
+    /// The backend is a separate MANY server, e.g. `many-ledger --abci`.
+    Http { http: reqwest::Client, url: Url },
+
+    /// The backend runs in this process, e.g. a `ManyServer` holding an
+    /// `AbciModule`.
+    Local(Arc<dyn LowLevelManyRequestHandler + Send + Sync>),
+}
+
+/// A client for the backend MANY app, used by the ABCI application.
+///
+/// Requests go through a single connection-pooled HTTP client (or directly to
+/// an in-process backend) and run on a dedicated worker runtime, so ABCI
+/// handlers (which are synchronous) never create a runtime or a connection per
+/// call.
+#[derive(Clone)]
+pub struct BackendClient {
+    transport: Transport,
+    runtime: Arc<tokio::runtime::Runtime>,
+}
+
+impl std::fmt::Debug for BackendClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.transport {
+            Transport::Http { url, .. } => f.debug_tuple("BackendClient").field(url).finish(),
+            Transport::Local(_) => f.write_str("BackendClient(local)"),
+        }
+    }
+}
+
+fn worker_runtime(worker_threads: usize) -> Result<tokio::runtime::Runtime, String> {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(worker_threads.max(1))
+        .thread_name("many-abci-worker")
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+impl BackendClient {
+    /// Create a client to a backend listening at `url`, with `worker_threads`
+    /// threads in its worker pool.
+    pub fn new<U: IntoUrl>(url: U, worker_threads: usize) -> Result<Self, String> {
+        let url = url.into_url().map_err(|e| e.to_string())?;
+        let http = reqwest::Client::builder()
+            .pool_max_idle_per_host(MAXIMUM_IDLE_CONNECTIONS)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .tcp_nodelay(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            transport: Transport::Http { http, url },
+            runtime: Arc::new(worker_runtime(worker_threads)?),
+        })
+    }
+
+    /// Create a client to a backend running in this process. The backend must
+    /// serve the `abci.*` endpoints.
+    pub fn local(
+        backend: impl LowLevelManyRequestHandler + Send + Sync + 'static,
+        worker_threads: usize,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            transport: Transport::Local(Arc::new(backend)),
+            runtime: Arc::new(worker_runtime(worker_threads)?),
+        })
+    }
+
+    /// Send an envelope to the backend and return its response envelope.
+    pub async fn send_envelope(&self, envelope: CoseSign1) -> Result<CoseSign1, ManyError> {
+        match &self.transport {
+            Transport::Http { http, url } => {
+                let bytes = envelope.to_vec().map_err(ManyError::serialization_error)?;
+                let response = http
+                    .post(url.clone())
+                    .body(bytes)
+                    .send()
+                    .await
+                    .map_err(ManyError::unknown)?
+                    .bytes()
+                    .await
+                    .map_err(ManyError::unknown)?;
+
+                CoseSign1::from_slice(&response).map_err(ManyError::deserialization_error)
+            }
+            Transport::Local(backend) => backend
+                .execute(envelope)
+                .await
+                .map_err(ManyError::unexpected_transport_error),
+        }
+    }
+
+    /// Call a method of the backend anonymously, returning the CBOR payload of
+    /// the response.
+    pub async fn call(&self, method: &str, data: Vec<u8>) -> Result<Vec<u8>, ManyError> {
+        let message = RequestMessageBuilder::default()
+            .method(method.to_string())
+            .from(Address::anonymous())
+            .data(data)
+            .build()
+            .map_err(|_| ManyError::internal_server_error())?;
+        let envelope = encode_cose_sign1_from_request(message, &AnonymousIdentity)
+            .map_err(ManyError::unknown)?;
+
+        let response = self.send_envelope(envelope).await?;
+        decode_response_from_cose_sign1(&response, None, &(AnonymousVerifier, CoseKeyVerifier))
+            .map_err(ManyError::unknown)?
+            .data
+    }
+
+    /// Same as `call`, encoding the argument and waiting for the result.
+    pub fn call_sync<T: minicbor::Encode<()>>(
+        &self,
+        method: &str,
+        argument: T,
+    ) -> Result<Vec<u8>, ManyError> {
+        let data = minicbor::to_vec(argument).map_err(ManyError::serialization_error)?;
+        let this = self.clone();
+        let method = method.to_string();
+        self.block_on(async move { this.call(&method, data).await })
+    }
+
+    /// Run a future on the worker pool and wait for its result. This is meant to
+    /// be called from the (synchronous) ABCI handlers.
+    pub fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+        self.runtime.spawn(async move {
+            let _ = tx.send(future.await);
+        });
+
+        // Do not stall another runtime's executor while waiting.
+        if tokio::runtime::Handle::try_current().is_ok() {
+            tokio::task::block_in_place(|| rx.recv())
+        } else {
+            rx.recv()
+        }
+        .expect("The worker pool stopped while processing a request.")
+    }
+}
@@ -0,0 +1,413 @@
+use crate::abci_app::AbciApp;
+use crate::backend::BackendClient;
+use crate::many_app::{AbciModuleMany, DirectQueries};
+use crate::module::{AbciAsyncModule, AbciBlockchainModule, AbciBlockchainModuleImpl};
+use crate::router::BackendRouter;
+use many_error::ManyError;
+use many_identity::verifiers::AnonymousVerifier;
+use many_identity::{Address, Identity};
+use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
+use many_identity_webauthn::WebAuthnVerifier;
+use many_modules::abci_backend::AbciInfo;
+use many_modules::base;
+use many_protocol::ManyUrl;
+use many_server::transport::http::HttpServer;
+use many_server::ManyServer;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tendermint_abci::ServerBuilder;
+use tendermint_rpc::Client;
+use tracing::{debug, error, info, trace};
+
+/// How long to wait for the backend to commit the block tendermint is
+/// executing, or for tendermint to produce the block holding the hash of the
+/// backend, before refusing to start.
+const BLOCK_LAG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options of the ABCI and MANY frontends, independent of where the backend
+/// MANY app runs.
+#[derive(Clone)]
+pub struct FrontendOptions {
+    /// Address and port to bind the ABCI server to.
+    pub abci: String,
+
+    /// URL for the tendermint server.
+    pub tendermint: String,
+
+    /// Address and port to bind the MANY server to.
+    pub many: String,
+
+    /// The identity of the MANY frontend.
+    pub identity: CoseKeyIdentity,
+
+    /// The default server read buffer size, in bytes, for each incoming client connection.
+    pub abci_read_buf_size: usize,
+
+    /// Application absolute URLs allowed to communicate with this server.
+    pub allow_origin: Option<Vec<ManyUrl>>,
+
+    /// Only addresses from this set will be able to execute commands.
+    pub allow_addrs: Option<BTreeSet<Address>>,
+
+    /// Serve the queries directly from the backends, between blocks, instead
+    /// of going through tendermint.
+    pub direct_queries: bool,
+}
+
+async fn backend_status(backend: &BackendClient) -> Result<base::Status, ManyError> {
+    let payload = backend
+        .call(
+            "status",
+            minicbor::to_vec(()).map_err(ManyError::serialization_error)?,
+        )
+        .await?;
+    minicbor::decode(&payload).map_err(ManyError::deserialization_error)
+}
+
+/// The state of the backend compared to the last block of tendermint.
+#[derive(Debug, PartialEq, Eq)]
+enum BlockCheck {
+    /// The backend is consistent with tendermint.
+    Consistent,
+
+    /// The backend hash matches the header of the last block, which the backend
+    /// has not executed yet.
+    Verified,
+
+    /// The backend is at the height of the last block, but its hash differs
+    /// from the header, so it can only be checked against the next header.
+    Pending,
+}
+
+/// Compare the state of the backend with the last block of tendermint, whose
+/// header holds the app hash of the previous height. `verified` is whether a
+/// previous check returned `BlockCheck::Verified`.
+///
+/// A backend at that height must have the same hash, and may only stay there
+/// while it executes the last block. A backend already at the height of the
+/// last block is consistent if its hash is the same (the block did not change
+/// its state), or else must be checked against the next header. Any other
+/// backend lost (or diverged on) a committed block, e.g. after crashing during
+/// a commit, and running it would fork the chain.
+fn check_block(
+    info: &AbciInfo,
+    height: u64,
+    app_hash: &[u8],
+    verified: bool,
+) -> Result<BlockCheck, String> {
+    // A new chain, which gets its state from `initChain`.
+    if height == 0 {
+        return Ok(BlockCheck::Consistent);
+    }
+    if info.height > height {
+        return Err(format!(
+            "The backend is at height {}, ahead of tendermint (height {height}). \
+             Restore the tendermint data from a backup, or restore the backend storage \
+             from a backup at a height lower than {height}.",
+            info.height,
+        ));
+    }
+    if info.height + 1 < height {
+        return Err(format!(
+            "The backend is at height {}, {} blocks behind tendermint (height {height}). \
+             Restore the backend storage from a backup, or reset tendermint and resync the chain.",
+            info.height,
+            height - info.height,
+        ));
+    }
+    if info.height == height {
+        // The backend executed the block it was checked against, or the last
+        // block left its state unchanged.
+        return Ok(if verified || info.hash.as_slice() == app_hash {
+            BlockCheck::Consistent
+        } else {
+            BlockCheck::Pending
+        });
+    }
+
+    // Without an app hash in the genesis, the first header has none.
+    let genesis = info.height == 0 && app_hash.is_empty();
+    if !genesis && info.hash.as_slice() != app_hash {
+        return Err(format!(
+            "The backend hash at height {} is {}, but the block {height} of tendermint expects {}. \
+             The backend storage diverged from the chain; restore it from a backup at a height lower than {}.",
+            info.height,
+            hex::encode(info.hash.as_slice()),
+            hex::encode(app_hash),
+            info.height,
+        ));
+    }
+    Ok(BlockCheck::Verified)
+}
+
+/// Wait until the backend is consistent with the last block of tendermint (see
+/// `check_block`), or fail if it cannot be.
+async fn check_backend_consistency(
+    backends: &BackendRouter,
+    client: &tendermint_rpc::HttpClient,
+) -> Result<(), String> {
+    let start = std::time::SystemTime::now();
+    let mut verified = false;
+    loop {
+        let status = client
+            .status()
+            .await
+            .map_err(|e| format!("Could not get the tendermint status: {e}"))?;
+        let info = backends
+            .info()
+            .await
+            .map_err(|e| format!("Could not get the backend info: {e}"))?;
+
+        let height = status.sync_info.latest_block_height.value();
+        let app_hash = status.sync_info.latest_app_hash.value();
+
+        match check_block(&info, height, &app_hash, verified)? {
+            BlockCheck::Consistent => return Ok(()),
+            BlockCheck::Verified => verified = true,
+            BlockCheck::Pending => {}
+        }
+        if start.elapsed().unwrap() > BLOCK_LAG_TIMEOUT {
+            return Err(if verified {
+                format!(
+                    "The backend is at height {}, one block behind tendermint (height {height}); the \
+                     last block was never committed by the backend. Restart tendermint to replay it, \
+                     or restore the backend storage from a backup at a height lower than {height}.",
+                    info.height,
+                )
+            } else {
+                format!(
+                    "The backend is at height {height}, but tendermint produced no block to check \
+                     its hash against. Make sure the chain produces blocks (e.g. with \
+                     `consensus.create-empty-blocks-interval`), then restart.",
+                )
+            });
+        }
+
+        // The backend may still be executing the last block, or tendermint
+        // producing the next one.
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Run the ABCI application and the MANY frontend until the MANY server is
+/// shut down. Each backend can be remote (`BackendClient::new`) or in this
+/// process (`BackendClient::local`). With several backends, methods are routed
+/// to the backend serving them (see `BackendRouter`).
+pub async fn run(backends: Vec<BackendClient>, options: FrontendOptions) {
+    let FrontendOptions {
+        abci,
+        tendermint,
+        many,
+        identity: key,
+        abci_read_buf_size,
+        allow_origin,
+        allow_addrs,
+        direct_queries,
+    } = options;
+
+    // Try to get the status of the backend MANY apps.
+    let start = std::time::SystemTime::now();
+    trace!("Connecting to the backend apps...");
+
+    let mut statuses = Vec::with_capacity(backends.len());
+    for backend in &backends {
+        let status = loop {
+            match backend_status(backend).await {
+                Err(e) => {
+                    if start.elapsed().unwrap().as_secs() > 60 {
+                        error!(
+                            "\nCould not connect to the ABCI server in 60 seconds... Terminating."
+                        );
+                        error!(error = e.to_string().as_str());
+                        std::process::exit(1);
+                    }
+                    debug!(error = e.to_string().as_str());
+                }
+                Ok(s) => {
+                    trace!(" Connected.");
+                    break s;
+                }
+            }
+
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        };
+        statuses.push(status);
+    }
+
+    let backends = match tokio::task::spawn_blocking(move || BackendRouter::create(backends))
+        .await
+        .unwrap()
+    {
+        Ok(backends) => backends,
+        Err(e) => {
+            error!("\nCould not route the backend endpoints... Terminating.");
+            error!(error = e.as_str());
+            std::process::exit(1);
+        }
+    };
+
+    // The status of the frontend is the status of the first backend, named
+    // after all of them.
+    let mut status = statuses.swap_remove(0);
+    status.name = backends.name();
+
+    let abci_app = AbciApp::new(backends.clone(), allow_origin.clone());
+    let blocks = abci_app.block_tracker();
+
+    let abci_server = ServerBuilder::new(abci_read_buf_size)
+        .bind(abci, abci_app)
+        .unwrap();
+    let _j_abci = tokio::task::spawn_blocking(move || abci_server.listen().unwrap());
+
+    let abci_client = tendermint_rpc::HttpClient::new(tendermint.as_str()).unwrap();
+
+    // Wait for 60 seconds until we can contact the ABCI server.
+    let start = std::time::SystemTime::now();
+    loop {
+        let info = abci_client.abci_info().await;
+        if info.is_ok() {
+            break;
+        }
+        if start.elapsed().unwrap().as_secs() > 300 {
+            error!("\nCould not connect to the ABCI server in 300 seconds... Terminating.");
+            std::process::exit(1);
+        }
+
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    }
+
+    // Refuse to run a backend which is not at the state of the chain.
+    if let Err(e) = check_backend_consistency(&backends, &abci_client).await {
+        error!("\nThe backend is not consistent with the chain... Terminating.");
+        error!(error = e.as_str());
+        std::process::exit(1);
+    }
+
+    info!(many_address = key.address().to_string().as_str());
+    let server = ManyServer::new(
+        format!("AbciModule({})", &status.name),
+        key.clone(),
+        (
+            AnonymousVerifier,
+            CoseKeyVerifier,
+            WebAuthnVerifier::new(allow_origin.clone()),
+        ),
+        key.public_key(),
+    );
+    let mut backend = AbciModuleMany::new(
+        abci_client.clone(),
+        status,
+        key,
+        backends.endpoints(),
+        allow_addrs,
+        allow_origin,
+    );
+    if direct_queries {
+        info!("Serving queries directly from the backends.");
+        backend = backend.with_direct_queries(DirectQueries { backends, blocks });
+    }
+    let blockchain_impl = Arc::new(AbciBlockchainModuleImpl::new(
+        abci_client,
+        tendermint.parse().unwrap(),
+    ));
+
+    {
+        let mut s = server.lock().unwrap();
+        s.add_module(base::BaseModule::new(server.clone()));
+        s.add_module(AbciBlockchainModule::new(blockchain_impl.clone()));
+        s.add_module(AbciAsyncModule::new(blockchain_impl));
+        s.set_fallback_module(backend);
+    }
+
+    let mut many_server = HttpServer::new(server);
+
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, many_server.term_signal())
+        .expect("Could not register signal handler");
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, many_server.term_signal())
+        .expect("Could not register signal handler");
+    signal_hook::flag::register(signal_hook::consts::SIGINT, many_server.term_signal())
+        .expect("Could not register signal handler");
+
+    info!("Starting MANY server on addr {}", many.clone());
+    match many_server.bind(many).await {
+        Ok(_) => {}
+        Err(error) => {
+            error!("{}", error);
+            panic!("Error happened in many: {error:?}");
+        }
+    }
+
+    // It seems that ABCI does not have a graceful way to shutdown. If we make it here
+    // though we already gracefully shutdown the MANY part of the server, so lets just
+    // get on with it, shall we?
+    std::process::exit(0);
+    // j_abci.join().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(height: u64, hash: &[u8]) -> AbciInfo {
+        AbciInfo {
+            height,
+            hash: hash.to_vec().into(),
+        }
+    }
+
+    #[test]
+    fn new_chain() {
+        assert_eq!(
+            check_block(&info(0, b""), 0, b"", false),
+            Ok(BlockCheck::Consistent)
+        );
+    }
+
+    #[test]
+    fn same_height_and_hash() {
+        assert_eq!(
+            check_block(&info(5, b"a"), 5, b"a", false),
+            Ok(BlockCheck::Consistent)
+        );
+    }
+
+    #[test]
+    fn same_height_waits_for_the_next_header() {
+        assert_eq!(
+            check_block(&info(5, b"b"), 5, b"a", false),
+            Ok(BlockCheck::Pending)
+        );
+        assert_eq!(
+            check_block(&info(5, b"b"), 6, b"b", false),
+            Ok(BlockCheck::Verified)
+        );
+        assert_eq!(
+            check_block(&info(6, b"c"), 6, b"b", true),
+            Ok(BlockCheck::Consistent)
+        );
+    }
+
+    #[test]
+    fn one_block_behind() {
+        assert_eq!(
+            check_block(&info(4, b"a"), 5, b"a", false),
+            Ok(BlockCheck::Verified)
+        );
+        assert!(check_block(&info(4, b"b"), 5, b"a", false).is_err());
+    }
+
+    #[test]
+    fn genesis_without_app_hash() {
+        assert_eq!(
+            check_block(&info(0, b"a"), 1, b"", false),
+            Ok(BlockCheck::Verified)
+        );
+    }
+
+    #[test]
+    fn too_far_apart() {
+        assert!(check_block(&info(6, b"a"), 5, b"a", false).is_err());
+        assert!(check_block(&info(3, b"a"), 5, b"a", false).is_err());
+    }
+}
@@ -1,3 +1,7 @@
 pub mod abci_app;
+pub mod backend;
+pub mod frontend;
 pub mod many_app;
 pub mod module;
+pub mod normalize;
+pub mod router;
@@ -1,28 +1,22 @@
 use clap::Parser;
-use many_client::ManyClient;
-use many_identity::verifiers::AnonymousVerifier;
-use many_identity::{Address, AnonymousIdentity, Identity};
-use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
-use many_identity_webauthn::WebAuthnVerifier;
-use many_modules::{base, blockchain, r#async};
+use many_identity::Address;
+use many_identity_dsa::CoseKeyIdentity;
 use many_protocol::ManyUrl;
-use many_server::transport::http::HttpServer;
-use many_server::ManyServer;
 use std::collections::BTreeSet;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use tendermint_abci::ServerBuilder;
-use tendermint_rpc::Client;
-use tracing::{debug, error, info, trace};
+use tracing::{debug, info};
 use tracing_subscriber::filter::LevelFilter;
 
 mod abci_app;
+mod backend;
+mod frontend;
 mod many_app;
 mod module;
+mod normalize;
+mod router;
 
-use abci_app::AbciApp;
-use many_app::AbciModuleMany;
-use module::AbciBlockchainModuleImpl;
+use backend::BackendClient;
+use frontend::FrontendOptions;
 
 #[derive(clap::ArgEnum, Clone, Debug)]
 enum LogStrategy {
@@ -41,8 +35,12 @@ struct Opts {
     tendermint: String,
 
     /// URL (including scheme) that has the MANY application running.
-    #[clap(long)]
-    many_app: String,
+    /// Multiple occurences of this argument can be given to run several
+    /// applications, serving distinct endpoints, behind one chain; every node
+    /// must list them in the same order. The ledger can instead embed this server (see the
+    /// `--embedded-abci` flag of many-ledger), but many-kvstore cannot.
+    #[clap(long, required = true)]
+    many_app: Vec<String>,
 
     /// Address and port to bind the MANY server to.
     #[clap(long)]
@@ -56,6 +54,10 @@ struct Opts {
     #[clap(short, long, default_value = "1048576")]
     abci_read_buf_size: usize,
 
+    /// Number of worker threads used to forward ABCI requests to the MANY application.
+    #[clap(long, default_value = "4")]
+    abci_workers: usize,
+
     /// Increase output logging verbosity to DEBUG level.
     #[clap(short, long, parse(from_occurrences))]
     verbose: i8,
@@ -79,6 +81,12 @@ struct Opts {
     /// Any addresses will be able to execute queries, e.g., balance, get, ...
     #[clap(long)]
     allow_addrs: Option<PathBuf>,
+
+    /// Serve queries (non-command methods) directly from the MANY applications
+    /// between blocks, instead of going through tendermint. Responses have the
+    /// height of the block they were served at in their `height` header.
+    #[clap(long)]
+    direct_queries: bool,
 }
 
 #[tokio::main]
@@ -90,11 +98,13 @@ async fn main() {
         many,
         many_pem,
         abci_read_buf_size,
+        abci_workers,
         verbose,
         quiet,
         allow_origin,
         logmode,
         allow_addrs,
+        direct_queries,
     } = Opts::parse();
 
     let verbose_level = 2 + verbose - quiet;
@@ -130,115 +140,26 @@ async fn main() {
         git_sha = env!("VERGEN_GIT_SHA")
     );
 
-    // Try to get the status of the backend MANY app.
-    let many_client = ManyClient::new(&many_app, Address::anonymous(), AnonymousIdentity).unwrap();
-
-    let start = std::time::SystemTime::now();
-    trace!("Connecting to the backend app...");
-
-    let status = loop {
-        let many_client = many_client.clone();
-        let result = many_client.status().await;
-
-        match result {
-            Err(e) => {
-                if start.elapsed().unwrap().as_secs() > 60 {
-                    error!("\nCould not connect to the ABCI server in 60 seconds... Terminating.");
-                    error!(error = e.to_string().as_str());
-                    std::process::exit(1);
-                }
-                debug!(error = e.to_string().as_str());
-            }
-            Ok(s) => {
-                trace!(" Connected.");
-                break s;
-            }
-        }
-
-        std::thread::sleep(std::time::Duration::from_secs(1));
-    };
-
-    let abci_app = tokio::task::spawn_blocking(move || {
-        AbciApp::create(many_app, Address::anonymous()).unwrap()
-    })
-    .await
-    .unwrap();
-
-    let abci_server = ServerBuilder::new(abci_read_buf_size)
-        .bind(abci, abci_app)
-        .unwrap();
-    let _j_abci = tokio::task::spawn_blocking(move || abci_server.listen().unwrap());
-
-    let abci_client = tendermint_rpc::HttpClient::new(tendermint.as_str()).unwrap();
-
-    // Wait for 60 seconds until we can contact the ABCI server.
-    let start = std::time::SystemTime::now();
-    loop {
-        let info = abci_client.abci_info().await;
-        if info.is_ok() {
-            break;
-        }
-        if start.elapsed().unwrap().as_secs() > 300 {
-            error!("\nCould not connect to the ABCI server in 300 seconds... Terminating.");
-            std::process::exit(1);
-        }
-
-        std::thread::sleep(std::time::Duration::from_secs(1));
-    }
-
     let key = CoseKeyIdentity::from_pem(std::fs::read_to_string(many_pem).unwrap()).unwrap();
-    info!(many_address = key.address().to_string().as_str());
-    let server = ManyServer::new(
-        format!("AbciModule({})", &status.name),
-        key.clone(),
-        (
-            AnonymousVerifier,
-            CoseKeyVerifier,
-            WebAuthnVerifier::new(allow_origin.clone()),
-        ),
-        key.public_key(),
-    );
-    let allowed_addrs: Option<BTreeSet<Address>> =
+    let allow_addrs: Option<BTreeSet<Address>> =
         allow_addrs.map(|path| json5::from_str(&std::fs::read_to_string(path).unwrap()).unwrap());
-    let backend = AbciModuleMany::new(
-        abci_client.clone(),
-        status,
-        key,
-        allowed_addrs,
-        allow_origin,
+    let backends = many_app
+        .into_iter()
+        .map(|url| BackendClient::new(url, abci_workers).unwrap())
+        .collect();
+
+    frontend::run(
+        backends,
+        FrontendOptions {
+            abci,
+            tendermint,
+            many,
+            identity: key,
+            abci_read_buf_size,
+            allow_origin,
+            allow_addrs,
+            direct_queries,
+        },
     )
     .await;
-    let blockchain_impl = Arc::new(Mutex::new(AbciBlockchainModuleImpl::new(abci_client)));
-
-    {
-        let mut s = server.lock().unwrap();
-        s.add_module(base::BaseModule::new(server.clone()));
-        s.add_module(blockchain::BlockchainModule::new(blockchain_impl.clone()));
-        s.add_module(r#async::AsyncModule::new(blockchain_impl));
-        s.set_fallback_module(backend);
-    }
-
-    let mut many_server = HttpServer::new(server);
-
-    signal_hook::flag::register(signal_hook::consts::SIGTERM, many_server.term_signal())
-        .expect("Could not register signal handler");
-    signal_hook::flag::register(signal_hook::consts::SIGHUP, many_server.term_signal())
-        .expect("Could not register signal handler");
-    signal_hook::flag::register(signal_hook::consts::SIGINT, many_server.term_signal())
-        .expect("Could not register signal handler");
-
-    info!("Starting MANY server on addr {}", many.clone());
-    match many_server.bind(many).await {
-        Ok(_) => {}
-        Err(error) => {
-            error!("{}", error);
-            panic!("Error happened in many: {error:?}");
-        }
-    }
-
-    // It seems that ABCI does not have a graceful way to shutdown. If we make it here
-    // though we already gracefully shutdown the MANY part of the server, so lets just
-    // get on with it, shall we?
-    std::process::exit(0);
-    // j_abci.join().unwrap();
 }
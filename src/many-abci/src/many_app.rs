@@ -1,16 +1,16 @@
+use crate::abci_app::BlockTracker;
+use crate::router::BackendRouter;
 use async_trait::async_trait;
-use coset::{CborSerializable, CoseSign1};
+use coset::{CborSerializable, CoseSign1, Label};
 use many_error::ManyError;
 use many_identity::verifiers::AnonymousVerifier;
 use many_identity::{Address, Identity};
 use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
 use many_identity_webauthn::WebAuthnVerifier;
-use many_modules::abci_backend::{AbciInit, EndpointInfo, ABCI_MODULE_ATTRIBUTE};
+use many_modules::abci_backend::{EndpointInfo, ABCI_MODULE_ATTRIBUTE};
 use many_modules::base;
 use many_protocol::{
-    decode_request_from_cose_sign1, decode_response_from_cose_sign1,
-    encode_cose_sign1_from_request, encode_cose_sign1_from_response, ManyUrl,
-    RequestMessageBuilder, ResponseMessage,
+    decode_request_from_cose_sign1, encode_cose_sign1_from_response, ManyUrl, ResponseMessage,
 };
 use many_server::transport::LowLevelManyRequestHandler;
 use many_types::attributes::Attribute;
@@ -20,6 +20,46 @@ use std::default::Default;
 use std::fmt::{Debug, Formatter};
 use tendermint_rpc::Client;
 
+/// Name of the unprotected COSE header holding the height of the block a
+/// direct query was served at.
+pub const QUERY_HEIGHT_HEADER: &str = "height";
+
+/// Serves the queries (non-command methods) directly from the backends instead
+/// of going through tendermint, when no block is executing.
+#[derive(Clone, Debug)]
+pub struct DirectQueries {
+    pub backends: BackendRouter,
+    pub blocks: BlockTracker,
+}
+
+impl DirectQueries {
+    /// Query the backend serving `method`. Returns `None` if a block was
+    /// executed or committed during the query, as the result might not match
+    /// the last committed block. The response is annotated with its height.
+    async fn query(
+        &self,
+        method: &str,
+        envelope: CoseSign1,
+    ) -> Result<Option<CoseSign1>, ManyError> {
+        let height = match self.blocks.committed_height() {
+            Some(height) => height,
+            None => return Ok(None),
+        };
+
+        let mut response = self.backends.route(method).send_envelope(envelope).await?;
+        if self.blocks.committed_height() != Some(height) {
+            return Ok(None);
+        }
+
+        // The header is unprotected, so the signature of the backend still holds.
+        response.unprotected.rest.push((
+            Label::Text(QUERY_HEIGHT_HEADER.to_string()),
+            ciborium::value::Value::Integer(height.into()),
+        ));
+        Ok(Some(response))
+    }
+}
+
 pub struct AbciModuleMany<C: Client> {
     client: C,
     backend_status: base::Status,
@@ -27,50 +67,35 @@ pub struct AbciModuleMany<C: Client> {
     backend_endpoints: BTreeMap<String, EndpointInfo>,
     allow_addrs: Option<BTreeSet<Address>>,
     allow_origin: Option<Vec<ManyUrl>>,
+    direct_queries: Option<DirectQueries>,
 }
 
 impl<C: Client + Sync> AbciModuleMany<C> {
-    pub async fn new(
+    pub fn new(
         client: C,
         backend_status: base::Status,
         identity: CoseKeyIdentity,
+        backend_endpoints: BTreeMap<String, EndpointInfo>,
         allow_addrs: Option<BTreeSet<Address>>,
         allow_origin: Option<Vec<ManyUrl>>,
     ) -> Self {
-        let init_message = RequestMessageBuilder::default()
-            .from(identity.address())
-            .method("abci.init".to_string())
-            .build()
-            .unwrap();
-        let data = encode_cose_sign1_from_request(init_message, &identity)
-            .unwrap()
-            .to_vec()
-            .unwrap();
-
-        let response = client.abci_query(None, data, None, false).await.unwrap();
-        let response = CoseSign1::from_slice(&response.value).unwrap();
-        let response = decode_response_from_cose_sign1(
-            &response,
-            None,
-            &(
-                AnonymousVerifier,
-                CoseKeyVerifier,
-                WebAuthnVerifier::new(allow_origin.clone()),
-            ),
-        )
-        .unwrap();
-        let init_message: AbciInit = minicbor::decode(&response.data.unwrap()).unwrap();
-
         Self {
             client,
             backend_status,
             identity,
-            backend_endpoints: init_message.endpoints,
+            backend_endpoints,
             allow_addrs,
             allow_origin,
+            direct_queries: None,
         }
     }
 
+    /// Serve the queries directly from the backends when possible.
+    pub fn with_direct_queries(mut self, direct_queries: DirectQueries) -> Self {
+        self.direct_queries = Some(direct_queries);
+        self
+    }
+
     async fn execute_message(&self, envelope: CoseSign1) -> Result<CoseSign1, ManyError> {
         let message = decode_request_from_cose_sign1(
             &envelope,
@@ -82,6 +107,17 @@ impl<C: Client + Sync> AbciModuleMany<C> {
         )?;
         if let Some(info) = self.backend_endpoints.get(&message.method) {
             let is_command = info.is_command;
+            if !is_command {
+                if let Some(direct_queries) = &self.direct_queries {
+                    if let Some(response) = direct_queries
+                        .query(&message.method, envelope.clone())
+                        .await?
+                    {
+                        return Ok(response);
+                    }
+                }
+            }
+
             let data = envelope
                 .to_vec()
                 .map_err(ManyError::unexpected_transport_error)?;
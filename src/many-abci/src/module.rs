@@ -1,45 +1,77 @@
+use crate::normalize::NormalizationPolicy;
 use clap::__macro_refs::once_cell;
 use coset::CborSerializable;
-use itertools::Itertools;
 use many_client::client::blocking::block_on;
 use many_error::ManyError;
 use many_identity::{Address, AnonymousIdentity};
 use many_modules::r#async::{StatusArgs, StatusReturn};
 use many_modules::{abci_frontend, blockchain, r#async};
-use many_protocol::{encode_cose_sign1_from_response, ResponseMessage};
+use many_protocol::{encode_cose_sign1_from_response, RequestMessage, ResponseMessage};
 use many_types::blockchain::{
     Block, BlockIdentifier, SingleBlockQuery, SingleTransactionQuery, Transaction,
     TransactionIdentifier,
 };
-use many_types::{blockchain::RangeBlockQuery, SortOrder, Timestamp};
+use many_types::{blockchain::RangeBlockQuery, CborRange, SortOrder, Timestamp};
 use once_cell::sync::Lazy;
+use reqwest::Url;
+use std::future::Future;
 use std::ops::{Bound, RangeBounds};
 use tendermint::Time;
+use tendermint_rpc::error::ErrorDetail;
 use tendermint_rpc::{query::Query, Client};
 
+mod async_status;
+mod blockchain_ext;
+
+pub use async_status::AbciAsyncModule;
+pub use blockchain_ext::AbciBlockchainModule;
+pub use many_abci_types::{SearchTransactionsArgs, SearchTransactionsReturns, SearchedTransaction};
+
 const MAXIMUM_BLOCK_COUNT: u64 = 100;
+const MAXIMUM_MEMPOOL_TXS_COUNT: u64 = 100;
 static DEFAULT_BLOCK_LIST_QUERY: Lazy<Query> = Lazy::new(|| Query::gte("block.height", 0));
+static DEFAULT_TX_SEARCH_QUERY: Lazy<Query> = Lazy::new(|| Query::gte("tx.height", 0));
 
-fn _many_block_from_tendermint_block(block: tendermint::Block) -> Block {
+/// Convert a tendermint block. If `responses` is given, it must contain the
+/// DeliverTx result data of every transaction of the block, and the request and
+/// response of each transaction will be filled.
+fn _many_block_from_tendermint_block(
+    block: tendermint::Block,
+    responses: Option<Vec<Vec<u8>>>,
+) -> Result<Block, ManyError> {
     let height = block.header.height.value();
     let txs_count = block.data.len() as u64;
+    let mut responses = responses.map(|r| r.into_iter());
     let txs = block
         .data
         .into_iter()
         .map(|b| {
             use sha2::Digest;
             let mut hasher = sha2::Sha256::new();
-            hasher.update(b);
-            Transaction {
-                id: TransactionIdentifier {
-                    hash: hasher.finalize().to_vec(),
-                },
-                request: None,
-                response: None,
-            }
+            hasher.update(&b);
+            let hash = hasher.finalize().to_vec();
+
+            let (request, response) = match responses.as_mut() {
+                Some(responses) => {
+                    let data = responses.next().ok_or_else(|| {
+                        ManyError::unknown("Missing transaction results for block.")
+                    })?;
+                    (
+                        Some(b.into()),
+                        Some(_many_response_from_tx_result_data(&data)?.into()),
+                    )
+                }
+                None => (None, None),
+            };
+
+            Ok(Transaction {
+                id: TransactionIdentifier { hash },
+                request,
+                response,
+            })
         })
-        .collect();
-    Block {
+        .collect::<Result<_, ManyError>>()?;
+    Ok(Block {
         id: BlockIdentifier {
             hash: block.header.hash().into(),
             height,
@@ -50,18 +82,30 @@ fn _many_block_from_tendermint_block(block: tendermint::Block) -> Block {
             BlockIdentifier::new(block.header.last_block_id.unwrap().hash.into(), height - 1)
         },
         app_hash: Some(block.header.app_hash.value()),
-        timestamp: Timestamp::new(
-            block
-                .header
-                .time
-                .duration_since(Time::unix_epoch())
-                .unwrap()
-                .as_secs(),
-        )
-        .unwrap(),
+        timestamp: _many_timestamp_from_tendermint_time(block.header.time).unwrap(),
         txs_count,
         txs,
-    }
+    })
+}
+
+/// Encode the DeliverTx result data of a transaction as a MANY response envelope.
+fn _many_response_from_tx_result_data(data: &[u8]) -> Result<Vec<u8>, ManyError> {
+    let response: ResponseMessage =
+        minicbor::decode(data).map_err(ManyError::deserialization_error)?;
+    // Results are normalized when executed, but the response must not depend on
+    // the node which serves it, even for blocks executed by older versions.
+    let response = NormalizationPolicy::default().normalize(response);
+    encode_cose_sign1_from_response(response, &AnonymousIdentity)?
+        .to_vec()
+        .map_err(ManyError::serialization_error)
+}
+
+fn _many_timestamp_from_tendermint_time(time: Time) -> Result<Timestamp, ManyError> {
+    let secs = time
+        .duration_since(Time::unix_epoch())
+        .map_err(ManyError::unknown)?
+        .as_secs();
+    Timestamp::new(secs)
 }
 
 fn _tm_order_from_many_order(order: SortOrder) -> tendermint_rpc::Order {
@@ -72,25 +116,17 @@ fn _tm_order_from_many_order(order: SortOrder) -> tendermint_rpc::Order {
     }
 }
 
-fn _tm_query_from_many_filter(
-    filter: RangeBlockQuery,
-) -> Result<tendermint_rpc::query::Query, ManyError> {
-    let mut query = tendermint_rpc::query::Query::default();
-    query = match filter {
-        RangeBlockQuery::Height(range) => {
-            query = match range.start_bound() {
-                Bound::Included(x) => query.and_gte("block.height", *x),
-                Bound::Excluded(x) => query.and_gt("block.height", *x),
-                _ => query,
-            };
-            query = match range.end_bound() {
-                Bound::Included(x) => query.and_lte("block.height", *x),
-                Bound::Excluded(x) => query.and_lt("block.height", *x),
-                _ => query,
-            };
-            query
-        }
-        RangeBlockQuery::Time(_range) => return Err(ManyError::unknown("Unimplemented")),
+fn _tm_query_from_height_range(range: CborRange<u64>) -> Query {
+    let mut query = Query::default();
+    query = match range.start_bound() {
+        Bound::Included(x) => query.and_gte("block.height", *x),
+        Bound::Excluded(x) => query.and_gt("block.height", *x),
+        _ => query,
+    };
+    query = match range.end_bound() {
+        Bound::Included(x) => query.and_lte("block.height", *x),
+        Bound::Excluded(x) => query.and_lt("block.height", *x),
+        _ => query,
     };
 
     // The default query returns an error (TM 0.35)
@@ -100,62 +136,335 @@ fn _tm_query_from_many_filter(
         query = DEFAULT_BLOCK_LIST_QUERY.clone();
     }
 
-    Ok(query)
+    query
 }
 
+/// Returns the first height in `[low, high]` whose block time satisfies
+/// `predicate`, or `high + 1` if none does. Block times are monotonic in
+/// tendermint, so the predicate is expected to partition the heights.
+async fn time_partition_point<F, Fut>(
+    mut low: u64,
+    high: u64,
+    block_time: &F,
+    predicate: impl Fn(&Timestamp) -> bool,
+) -> Result<u64, ManyError>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<Timestamp, ManyError>>,
+{
+    let mut high = high + 1;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if predicate(&block_time(mid).await?) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Ok(low)
+}
+
+/// Whether tendermint answered that a transaction is not in its index, as
+/// opposed to failing to answer.
+fn is_tx_not_found(error: &tendermint_rpc::Error) -> bool {
+    match error.detail() {
+        ErrorDetail::Response(e) => e.source.data().map_or(false, |d| d.contains("not found")),
+        _ => false,
+    }
+}
+
+/// Resolve a time range into the range of heights of the blocks produced
+/// within it, by bisecting the heights from `earliest` to `latest`.
+/// `block_time` is only called with heights in that range.
+async fn height_range_from_time_range<F, Fut>(
+    range: CborRange<Timestamp>,
+    earliest: u64,
+    latest: u64,
+    block_time: F,
+) -> Result<CborRange<u64>, ManyError>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<Timestamp, ManyError>>,
+{
+    let first = match range.start_bound() {
+        Bound::Included(start) => {
+            time_partition_point(earliest, latest, &block_time, |t| t >= start).await?
+        }
+        Bound::Excluded(start) => {
+            time_partition_point(earliest, latest, &block_time, |t| t > start).await?
+        }
+        Bound::Unbounded => earliest,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(end) => {
+            time_partition_point(first, latest, &block_time, |t| t > end).await?
+        }
+        Bound::Excluded(end) => {
+            time_partition_point(first, latest, &block_time, |t| t >= end).await?
+        }
+        Bound::Unbounded => latest + 1,
+    };
+
+    Ok(CborRange {
+        start: Bound::Included(first),
+        end: Bound::Excluded(end),
+    })
+}
+
+/// The blockchain and async backend of the MANY frontend. Every method is async
+/// and only needs a shared reference, so it can be served concurrently without
+/// locking (see `AbciBlockchainModule` and `AbciAsyncModule`). The synchronous
+/// `many_modules` traits are also implemented for compatibility.
+#[derive(Clone)]
 pub struct AbciBlockchainModuleImpl<C: Client> {
     client: C,
+    http: reqwest::Client,
+    tendermint_url: Url,
 }
 
 impl<C: Client> AbciBlockchainModuleImpl<C> {
-    pub fn new(client: C) -> Self {
-        Self { client }
+    pub fn new(client: C, tendermint_url: Url) -> Self {
+        Self {
+            client,
+            http: reqwest::Client::new(),
+            tendermint_url,
+        }
     }
 }
 
-impl<C: Client> Drop for AbciBlockchainModuleImpl<C> {
-    fn drop(&mut self) {
-        tracing::info!("ABCI Blockchain Module being dropped.");
+impl<C: Client + Send + Sync> AbciBlockchainModuleImpl<C> {
+    async fn tx(
+        &self,
+        query: SingleTransactionQuery,
+        prove: bool,
+    ) -> Result<tendermint_rpc::endpoint::tx::Response, ManyError> {
+        match query {
+            SingleTransactionQuery::Hash(hash) => {
+                if let Ok(hash) = TryInto::<[u8; 32]>::try_into(hash) {
+                    self.client
+                        .tx(tendermint_rpc::abci::transaction::Hash::new(hash), prove)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("abci transport: {}", e.to_string());
+                            abci_frontend::abci_transport_error(e.to_string())
+                        })
+                } else {
+                    Err(ManyError::unknown("Invalid transaction hash .".to_string()))
+                }
+            }
+        }
     }
-}
 
-impl<C: Client + Send + Sync> r#async::AsyncModuleBackend for AbciBlockchainModuleImpl<C> {
-    fn status(&self, _sender: &Address, args: StatusArgs) -> Result<StatusReturn, ManyError> {
-        let hash = args.token.as_ref();
+    async fn block_time(&self, height: u64) -> Result<Timestamp, ManyError> {
+        let block = self.client.block(height as u32).await.map_err(|e| {
+            tracing::error!("abci transport: {}", e.to_string());
+            abci_frontend::abci_transport_error(e.to_string())
+        })?;
+        _many_timestamp_from_tendermint_time(block.block.header.time)
+    }
 
-        if let Ok(hash) = TryInto::<[u8; 32]>::try_into(hash) {
-            block_on(async {
-                match self
-                    .client
-                    .tx(tendermint_rpc::abci::transaction::Hash::new(hash), false)
-                    .await
-                {
-                    Ok(tx) => {
-                        tracing::warn!("result: {}", hex::encode(tx.tx_result.data.value()));
-                        Ok(StatusReturn::Done {
-                            response: Box::new(
-                                encode_cose_sign1_from_response(
-                                    ResponseMessage::from_bytes(tx.tx_result.data.value())
-                                        .map_err(abci_frontend::abci_transport_error)?,
-                                    &AnonymousIdentity,
-                                )
-                                .map_err(abci_frontend::abci_transport_error)?,
-                            ),
-                        })
-                    }
+    /// Resolve a time range into the range of heights of the blocks produced
+    /// within it, among the blocks this node still has.
+    async fn height_range_from_time_range(
+        &self,
+        range: CborRange<Timestamp>,
+    ) -> Result<CborRange<u64>, ManyError> {
+        let sync_info = self
+            .client
+            .status()
+            .await
+            .map_err(|e| {
+                tracing::error!("abci transport: {}", e.to_string());
+                abci_frontend::abci_transport_error(e.to_string())
+            })?
+            .sync_info;
 
-                    Err(_) => Ok(StatusReturn::Unknown),
+        // A pruned node does not have the blocks below its earliest height.
+        let earliest = sync_info.earliest_block_height.value().max(1);
+        let latest = sync_info.latest_block_height.value();
+        height_range_from_time_range(range, earliest, latest, |height| self.block_time(height))
+            .await
+    }
+
+    /// Returns whether a transaction is waiting in the tendermint mempool. The
+    /// RPC client does not expose `unconfirmed_txs`, so it is called directly,
+    /// one page of `MAXIMUM_MEMPOOL_TXS_COUNT` transactions at a time. The
+    /// mempool may change between pages.
+    async fn is_in_mempool(&self, hash: &[u8]) -> Result<bool, ManyError> {
+        let mut checked = 0;
+        for page in 1.. {
+            let (txs, total) = self.unconfirmed_txs(page).await?;
+            if txs.iter().any(|tx| {
+                use sha2::Digest;
+                sha2::Sha256::digest(tx).as_slice() == hash
+            }) {
+                return Ok(true);
+            }
+
+            checked += txs.len() as u64;
+            if txs.is_empty() || checked >= total {
+                break;
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns a page of the transactions in the mempool, and the number of
+    /// transactions in the mempool.
+    async fn unconfirmed_txs(&self, page: u64) -> Result<(Vec<Vec<u8>>, u64), ManyError> {
+        let mut url = self
+            .tendermint_url
+            .join("unconfirmed_txs")
+            .map_err(ManyError::unknown)?;
+        url.set_query(Some(&format!(
+            "page={page}&per_page={MAXIMUM_MEMPOOL_TXS_COUNT}"
+        )));
+
+        let body = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .map_err(abci_frontend::abci_transport_error)?
+            .text()
+            .await
+            .map_err(abci_frontend::abci_transport_error)?;
+        let json: serde_json::Value =
+            serde_json::from_str(&body).map_err(ManyError::deserialization_error)?;
+
+        let txs: Vec<Vec<u8>> = json["result"]["txs"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|tx| tx.as_str().and_then(|tx| base64::decode(tx).ok()))
+            .collect();
+        // Tendermint encodes integers as strings.
+        let total = json["result"]["total"]
+            .as_str()
+            .and_then(|total| total.parse().ok())
+            .unwrap_or(txs.len() as u64);
+        Ok((txs, total))
+    }
+
+    /// Returns the DeliverTx result data of all transactions of a block.
+    async fn tx_results(
+        &self,
+        height: tendermint::block::Height,
+    ) -> Result<Vec<Vec<u8>>, ManyError> {
+        let results = self.client.block_results(height).await.map_err(|e| {
+            tracing::error!("abci transport: {}", e.to_string());
+            abci_frontend::abci_transport_error(e.to_string())
+        })?;
+
+        Ok(results
+            .txs_results
+            .unwrap_or_default()
+            .into_iter()
+            .map(|result| result.data.value().to_vec())
+            .collect())
+    }
+
+    /// Search the tendermint transaction index, using the events emitted by
+    /// `deliver_tx`.
+    pub async fn search_transactions(
+        &self,
+        args: SearchTransactionsArgs,
+    ) -> Result<SearchTransactionsReturns, ManyError> {
+        let SearchTransactionsArgs {
+            count,
+            order,
+            from,
+            method,
+            height,
+            success,
+            page,
+        } = args;
+
+        let count = count.map_or(MAXIMUM_BLOCK_COUNT, |c| {
+            std::cmp::min(c, MAXIMUM_BLOCK_COUNT)
+        }) as u8;
+        let order = order.map_or(tendermint_rpc::Order::Ascending, _tm_order_from_many_order);
+        let page = match page {
+            None => 1,
+            Some(0) => return Err(ManyError::unknown("Pages start at 1.")),
+            Some(page) => u32::try_from(page).map_err(ManyError::unknown)?,
+        };
+
+        let mut query = DEFAULT_TX_SEARCH_QUERY.clone();
+        if let Some(from) = from {
+            query = query.and_eq("many.from", from.to_string());
+        }
+        if let Some(method) = method {
+            query = query.and_eq("many.method", method);
+        }
+        if let Some(success) = success {
+            query = query.and_eq("many.success", success.to_string());
+        }
+        if let Some(range) = height {
+            query = match range.start_bound() {
+                Bound::Included(x) => query.and_gte("tx.height", *x),
+                Bound::Excluded(x) => query.and_gt("tx.height", *x),
+                _ => query,
+            };
+            query = match range.end_bound() {
+                Bound::Included(x) => query.and_lte("tx.height", *x),
+                Bound::Excluded(x) => query.and_lt("tx.height", *x),
+                _ => query,
+            };
+        }
+
+        let search = self
+            .client
+            .tx_search(query, false, page, count, order)
+            .await
+            .map_err(|e| {
+                tracing::error!("abci transport: {}", e.to_string());
+                abci_frontend::abci_transport_error(e.to_string())
+            })?;
+
+        let transactions = search
+            .txs
+            .into_iter()
+            .map(|tx| {
+                let request = tx.tx.as_bytes().to_vec();
+                let method = coset::CoseSign1::from_slice(&request)
+                    .ok()
+                    .and_then(|cose| cose.payload)
+                    .and_then(|payload| RequestMessage::from_bytes(&payload).ok())
+                    .map(|message| message.method);
+
+                // A transaction with invalid result data is still returned, so
+                // it does not hide the rest of the page.
+                let response = _many_response_from_tx_result_data(tx.tx_result.data.value())
+                    .map_err(|e| {
+                        tracing::warn!("Could not decode the response of {}: {e}", tx.hash);
+                    })
+                    .ok();
+
+                SearchedTransaction {
+                    height: tx.height.value(),
+                    method,
+                    success: tx.tx_result.code.is_ok(),
+                    txn: Transaction {
+                        id: TransactionIdentifier {
+                            hash: tx.hash.as_bytes().to_vec(),
+                        },
+                        request: Some(request.into()),
+                        response: response.map(Into::into),
+                    },
                 }
             })
-        } else {
-            Err(ManyError::unknown("Invalid async token .".to_string()))
-        }
+            .collect();
+
+        Ok(SearchTransactionsReturns {
+            transactions,
+            total_count: search.total_count.into(),
+        })
     }
-}
 
-impl<C: Client + Send + Sync> blockchain::BlockchainModuleBackend for AbciBlockchainModuleImpl<C> {
-    fn info(&self) -> Result<blockchain::InfoReturns, ManyError> {
-        let status = block_on(async { self.client.status().await }).map_err(|e| {
+    pub async fn info(&self) -> Result<blockchain::InfoReturns, ManyError> {
+        let status = self.client.status().await.map_err(|e| {
             tracing::error!("abci transport: {}", e.to_string());
             abci_frontend::abci_transport_error(e.to_string())
         })?;
@@ -170,76 +479,84 @@ impl<C: Client + Send + Sync> blockchain::BlockchainModuleBackend for AbciBlockc
         })
     }
 
-    fn transaction(
+    /// Same as `blockchain.transaction`, but also fills the request and response
+    /// of the transaction if `details` is true.
+    pub async fn transaction(
         &self,
         args: blockchain::TransactionArgs,
+        details: bool,
     ) -> Result<blockchain::TransactionReturns, ManyError> {
-        let block = block_on(async {
-            match args.query {
-                SingleTransactionQuery::Hash(hash) => {
-                    if let Ok(hash) = TryInto::<[u8; 32]>::try_into(hash) {
-                        self.client
-                            .tx(tendermint_rpc::abci::transaction::Hash::new(hash), true)
-                            .await
-                            .map_err(|e| {
-                                tracing::error!("abci transport: {}", e.to_string());
-                                abci_frontend::abci_transport_error(e.to_string())
-                            })
-                    } else {
-                        Err(ManyError::unknown("Invalid transaction hash .".to_string()))
-                    }
-                }
-            }
-        })?;
+        let tx = self.tx(args.query, true).await?;
+
+        let tx_hash = tx.hash.as_bytes().to_vec();
+        let (request, response) = if details {
+            (
+                Some(tx.tx.as_bytes().to_vec().into()),
+                Some(_many_response_from_tx_result_data(tx.tx_result.data.value())?.into()),
+            )
+        } else {
+            (None, None)
+        };
 
-        let tx_hash = block.hash.as_bytes().to_vec();
         Ok(blockchain::TransactionReturns {
             txn: Transaction {
                 id: TransactionIdentifier { hash: tx_hash },
-                request: None,
-                response: None,
+                request,
+                response,
             },
         })
     }
 
-    fn block(&self, args: blockchain::BlockArgs) -> Result<blockchain::BlockReturns, ManyError> {
-        let block = block_on(async {
-            match args.query {
-                SingleBlockQuery::Hash(hash) => {
-                    if let Ok(hash) = TryInto::<[u8; 32]>::try_into(hash) {
-                        self.client
-                            .block_by_hash(tendermint::Hash::Sha256(hash))
-                            .await
-                            .map_err(|e| {
-                                tracing::error!("abci transport: {}", e.to_string());
-                                abci_frontend::abci_transport_error(e.to_string())
-                            })
-                            .map(|search| search.block)
-                    } else {
-                        Err(ManyError::unknown("Invalid hash length.".to_string()))
-                    }
+    /// Same as `blockchain.block`, but also fills the request and response of
+    /// every transaction of the block if `details` is true.
+    pub async fn block(
+        &self,
+        args: blockchain::BlockArgs,
+        details: bool,
+    ) -> Result<blockchain::BlockReturns, ManyError> {
+        let block = match args.query {
+            SingleBlockQuery::Hash(hash) => {
+                if let Ok(hash) = TryInto::<[u8; 32]>::try_into(hash) {
+                    self.client
+                        .block_by_hash(tendermint::Hash::Sha256(hash))
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("abci transport: {}", e.to_string());
+                            abci_frontend::abci_transport_error(e.to_string())
+                        })
+                        .map(|search| search.block)
+                } else {
+                    Err(ManyError::unknown("Invalid hash length.".to_string()))
                 }
-                SingleBlockQuery::Height(height) => self
-                    .client
-                    .block(height as u32)
-                    .await
-                    .map_err(|e| {
-                        tracing::error!("abci transport: {}", e.to_string());
-                        abci_frontend::abci_transport_error(e.to_string())
-                    })
-                    .map(|x| Some(x.block)),
             }
-        })?;
+            SingleBlockQuery::Height(height) => self
+                .client
+                .block(height as u32)
+                .await
+                .map_err(|e| {
+                    tracing::error!("abci transport: {}", e.to_string());
+                    abci_frontend::abci_transport_error(e.to_string())
+                })
+                .map(|x| Some(x.block)),
+        }?;
 
         if let Some(block) = block {
-            let block = _many_block_from_tendermint_block(block);
+            let responses = if details {
+                Some(self.tx_results(block.header.height).await?)
+            } else {
+                None
+            };
+            let block = _many_block_from_tendermint_block(block, responses)?;
             Ok(blockchain::BlockReturns { block })
         } else {
             Err(blockchain::unknown_block())
         }
     }
 
-    fn list(&self, args: blockchain::ListArgs) -> Result<blockchain::ListReturns, ManyError> {
+    pub async fn list(
+        &self,
+        args: blockchain::ListArgs,
+    ) -> Result<blockchain::ListReturns, ManyError> {
         let blockchain::ListArgs {
             count,
             order,
@@ -265,23 +582,23 @@ impl<C: Client + Send + Sync> blockchain::BlockchainModuleBackend for AbciBlockc
 
         let order = order.map_or(tendermint_rpc::Order::Ascending, _tm_order_from_many_order);
 
-        let query = filter.map_or(
-            Ok(DEFAULT_BLOCK_LIST_QUERY.clone()),
-            _tm_query_from_many_filter,
-        )?;
+        let query = match filter {
+            None => DEFAULT_BLOCK_LIST_QUERY.clone(),
+            Some(RangeBlockQuery::Height(range)) => _tm_query_from_height_range(range),
+            Some(RangeBlockQuery::Time(range)) => {
+                _tm_query_from_height_range(self.height_range_from_time_range(range).await?)
+            }
+        };
 
-        let (status, block) = block_on(async move {
-            let status = self.client.status().await;
-            let block = self.client.block_search(query, pages, count, order).await;
-            (status, block)
-        });
+        let status = self.client.status().await;
+        let block = self.client.block_search(query, pages, count, order).await;
 
         let blocks = block
             .map_err(ManyError::unknown)?
             .blocks
             .into_iter()
-            .map(|x| _many_block_from_tendermint_block(x.block))
-            .collect_vec();
+            .map(|x| _many_block_from_tendermint_block(x.block, None))
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(blockchain::ListReturns {
             height: status
@@ -293,27 +610,11 @@ impl<C: Client + Send + Sync> blockchain::BlockchainModuleBackend for AbciBlockc
         })
     }
 
-    fn request(
+    pub async fn request(
         &self,
         args: blockchain::RequestArgs,
     ) -> Result<blockchain::RequestReturns, ManyError> {
-        let tx = block_on(async {
-            match args.query {
-                SingleTransactionQuery::Hash(hash) => {
-                    if let Ok(hash) = TryInto::<[u8; 32]>::try_into(hash) {
-                        self.client
-                            .tx(tendermint_rpc::abci::transaction::Hash::new(hash), true)
-                            .await
-                            .map_err(|e| {
-                                tracing::error!("abci transport: {}", e.to_string());
-                                abci_frontend::abci_transport_error(e.to_string())
-                            })
-                    } else {
-                        Err(ManyError::unknown("Invalid transaction hash .".to_string()))
-                    }
-                }
-            }
-        })?;
+        let tx = self.tx(args.query, true).await?;
 
         tracing::debug!("blockchain.request: {}", hex::encode(tx.tx.as_bytes()));
 
@@ -322,38 +623,228 @@ impl<C: Client + Send + Sync> blockchain::BlockchainModuleBackend for AbciBlockc
         })
     }
 
-    fn response(
+    pub async fn response(
         &self,
         args: blockchain::ResponseArgs,
     ) -> Result<blockchain::ResponseReturns, ManyError> {
-        let tx = block_on(async {
-            match args.query {
-                SingleTransactionQuery::Hash(hash) => {
-                    if let Ok(hash) = TryInto::<[u8; 32]>::try_into(hash) {
-                        self.client
-                            .tx(tendermint_rpc::abci::transaction::Hash::new(hash), true)
-                            .await
-                            .map_err(|e| {
-                                tracing::error!("abci transport: {}", e.to_string());
-                                abci_frontend::abci_transport_error(e.to_string())
-                            })
-                    } else {
-                        Err(ManyError::unknown("Invalid transaction hash .".to_string()))
-                    }
-                }
-            }
-        })?;
+        let tx = self.tx(args.query, true).await?;
 
         tracing::debug!(
             "blockchain.response: {}",
             hex::encode(tx.tx_result.data.value())
         );
-        let response: ResponseMessage = minicbor::decode(tx.tx_result.data.value())
-            .map_err(ManyError::deserialization_error)?;
         Ok(blockchain::ResponseReturns {
-            response: encode_cose_sign1_from_response(response, &AnonymousIdentity)?
-                .to_vec()
-                .map_err(ManyError::serialization_error)?,
+            response: _many_response_from_tx_result_data(tx.tx_result.data.value())?,
         })
     }
+
+    /// The status of a transaction, by hash. Tendermint does not expose
+    /// transactions being executed, so a transaction is either `Queued` in the
+    /// mempool, `Done` once indexed, or `Unknown`; `Processing` is not returned.
+    pub async fn status(&self, args: StatusArgs) -> Result<StatusReturn, ManyError> {
+        let hash = args.token.as_ref();
+
+        if let Ok(hash) = TryInto::<[u8; 32]>::try_into(hash) {
+            match self
+                .client
+                .tx(tendermint_rpc::abci::transaction::Hash::new(hash), false)
+                .await
+            {
+                Ok(tx) => {
+                    tracing::debug!("result: {}", hex::encode(tx.tx_result.data.value()));
+                    let response = match ResponseMessage::from_bytes(tx.tx_result.data.value()) {
+                        Ok(response) => response,
+                        // The transaction failed before reaching the backend, and
+                        // there is no response to decode. Use the log instead.
+                        Err(_) if tx.tx_result.code.is_err() => ResponseMessage::error(
+                            Address::anonymous(),
+                            None,
+                            ManyError::unknown(tx.tx_result.log.to_string()),
+                        ),
+                        Err(e) => return Err(abci_frontend::abci_transport_error(e)),
+                    };
+
+                    Ok(StatusReturn::Done {
+                        response: Box::new(
+                            encode_cose_sign1_from_response(response, &AnonymousIdentity)
+                                .map_err(abci_frontend::abci_transport_error)?,
+                        ),
+                    })
+                }
+
+                // Not indexed yet; it might still be waiting in the mempool.
+                Err(e) if is_tx_not_found(&e) => {
+                    if self.is_in_mempool(&hash).await? {
+                        Ok(StatusReturn::Queued)
+                    } else {
+                        Ok(StatusReturn::Unknown)
+                    }
+                }
+                Err(e) => Err(abci_frontend::abci_transport_error(e.to_string())),
+            }
+        } else {
+            Err(ManyError::unknown("Invalid async token .".to_string()))
+        }
+    }
+}
+
+impl<C: Client> Drop for AbciBlockchainModuleImpl<C> {
+    fn drop(&mut self) {
+        tracing::info!("ABCI Blockchain Module being dropped.");
+    }
+}
+
+impl<C: Client + Send + Sync> r#async::AsyncModuleBackend for AbciBlockchainModuleImpl<C> {
+    fn status(&self, _sender: &Address, args: StatusArgs) -> Result<StatusReturn, ManyError> {
+        block_on(AbciBlockchainModuleImpl::status(self, args))
+    }
+}
+
+impl<C: Client + Send + Sync> blockchain::BlockchainModuleBackend for AbciBlockchainModuleImpl<C> {
+    fn info(&self) -> Result<blockchain::InfoReturns, ManyError> {
+        block_on(AbciBlockchainModuleImpl::info(self))
+    }
+
+    fn transaction(
+        &self,
+        args: blockchain::TransactionArgs,
+    ) -> Result<blockchain::TransactionReturns, ManyError> {
+        block_on(AbciBlockchainModuleImpl::transaction(self, args, false))
+    }
+
+    fn block(&self, args: blockchain::BlockArgs) -> Result<blockchain::BlockReturns, ManyError> {
+        block_on(AbciBlockchainModuleImpl::block(self, args, false))
+    }
+
+    fn list(&self, args: blockchain::ListArgs) -> Result<blockchain::ListReturns, ManyError> {
+        block_on(AbciBlockchainModuleImpl::list(self, args))
+    }
+
+    fn request(
+        &self,
+        args: blockchain::RequestArgs,
+    ) -> Result<blockchain::RequestReturns, ManyError> {
+        block_on(AbciBlockchainModuleImpl::request(self, args))
+    }
+
+    fn response(
+        &self,
+        args: blockchain::ResponseArgs,
+    ) -> Result<blockchain::ResponseReturns, ManyError> {
+        block_on(AbciBlockchainModuleImpl::response(self, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A chain pruned below height 5, up to height 20, with a block every 10
+    /// seconds.
+    const EARLIEST: u64 = 5;
+    const LATEST: u64 = 20;
+
+    fn time(secs: u64) -> Timestamp {
+        Timestamp::new(secs).unwrap()
+    }
+
+    async fn block_time(height: u64) -> Result<Timestamp, ManyError> {
+        assert!(
+            (EARLIEST..=LATEST).contains(&height),
+            "Block {height} is not available."
+        );
+        Ok(time(height * 10))
+    }
+
+    fn heights(start: Bound<u64>, end: Bound<u64>) -> (Bound<u64>, Bound<u64>) {
+        let range = CborRange {
+            start: start.map(time),
+            end: end.map(time),
+        };
+        let range = block_on(height_range_from_time_range(
+            range, EARLIEST, LATEST, block_time,
+        ))
+        .unwrap();
+        (range.start, range.end)
+    }
+
+    #[test]
+    fn partition_point() {
+        let point = |secs| {
+            block_on(time_partition_point(
+                1,
+                10,
+                &|h| async move { Ok(time(h)) },
+                |t| *t >= time(secs),
+            ))
+            .unwrap()
+        };
+        assert_eq!(point(0), 1);
+        assert_eq!(point(1), 1);
+        assert_eq!(point(7), 7);
+        assert_eq!(point(10), 10);
+        assert_eq!(point(11), 11);
+    }
+
+    #[test]
+    fn time_range_bounds() {
+        use Bound::*;
+        assert_eq!(
+            heights(Included(100), Included(150)),
+            (Included(10), Excluded(16))
+        );
+        assert_eq!(
+            heights(Excluded(100), Excluded(150)),
+            (Included(11), Excluded(15))
+        );
+        // Between two blocks.
+        assert_eq!(
+            heights(Included(95), Included(155)),
+            (Included(10), Excluded(16))
+        );
+        assert_eq!(
+            heights(Unbounded, Excluded(100)),
+            (Included(EARLIEST), Excluded(10))
+        );
+        assert_eq!(
+            heights(Included(150), Unbounded),
+            (Included(15), Excluded(LATEST + 1))
+        );
+    }
+
+    #[test]
+    fn time_range_of_pruned_blocks() {
+        use Bound::*;
+        // The blocks before the earliest one are not searched.
+        assert_eq!(
+            heights(Included(0), Included(60)),
+            (Included(EARLIEST), Excluded(7))
+        );
+        assert_eq!(
+            heights(Included(0), Excluded(10)),
+            (Included(EARLIEST), Excluded(EARLIEST))
+        );
+    }
+
+    #[test]
+    fn time_range_after_the_latest_block() {
+        use Bound::*;
+        assert_eq!(
+            heights(Included(1000), Unbounded),
+            (Included(LATEST + 1), Excluded(LATEST + 1))
+        );
+    }
+
+    #[test]
+    fn tx_not_found() {
+        use tendermint_rpc::{Code, ResponseError};
+        let not_found = tendermint_rpc::Error::response(ResponseError::new(
+            Code::InternalError,
+            Some("tx (ABCD) not found".to_string()),
+        ));
+        assert!(is_tx_not_found(&not_found));
+
+        let transport = tendermint_rpc::Error::client_internal("connection refused".to_string());
+        assert!(!is_tx_not_found(&transport));
+    }
 }
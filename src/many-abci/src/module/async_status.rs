@@ -0,0 +1,57 @@
+use super::AbciBlockchainModuleImpl;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_modules::{r#async, ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use tendermint_rpc::Client;
+
+/// The async module of the MANY frontend, executing `async.status` directly on
+/// the (async) backend.
+pub struct AbciAsyncModule<C: Client + Clone + Send + Sync + 'static> {
+    inner: r#async::AsyncModule<AbciBlockchainModuleImpl<C>>,
+    backend: Arc<AbciBlockchainModuleImpl<C>>,
+}
+
+impl<C: Client + Clone + Send + Sync + 'static> AbciAsyncModule<C> {
+    pub fn new(backend: Arc<AbciBlockchainModuleImpl<C>>) -> Self {
+        Self {
+            inner: r#async::AsyncModule::new(Arc::new(Mutex::new(backend.as_ref().clone()))),
+            backend,
+        }
+    }
+}
+
+impl<C: Client + Clone + Send + Sync + 'static> Debug for AbciAsyncModule<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AbciAsyncModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl<C: Client + Clone + Send + Sync + 'static> ManyModule for AbciAsyncModule<C> {
+    fn info(&self) -> &ManyModuleInfo {
+        self.inner.info()
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        self.inner.validate(message, envelope)
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        if message.method != "async.status" {
+            return self.inner.execute(message).await;
+        }
+
+        let args: r#async::StatusArgs =
+            minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+        let data = self
+            .backend
+            .status(args)
+            .await
+            .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error));
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
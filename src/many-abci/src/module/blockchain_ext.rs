@@ -0,0 +1,129 @@
+use super::AbciBlockchainModuleImpl;
+use coset::CoseSign1;
+use many_abci_types::{SearchTransactionsArgs, SEARCH_TRANSACTIONS_ENDPOINT};
+use many_error::ManyError;
+use many_modules::{blockchain, ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use many_types::blockchain::{SingleBlockQuery, SingleTransactionQuery};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use tendermint_rpc::Client;
+
+/// Arguments of `blockchain.block`, with an additional flag to include the
+/// request and response of every transaction.
+#[derive(minicbor::Decode)]
+#[cbor(map)]
+struct BlockDetailsArgs {
+    #[n(0)]
+    query: SingleBlockQuery,
+
+    #[n(1)]
+    details: Option<bool>,
+}
+
+/// Arguments of `blockchain.transaction`, with an additional flag to include the
+/// request and response of the transaction.
+#[derive(minicbor::Decode)]
+#[cbor(map)]
+struct TransactionDetailsArgs {
+    #[n(0)]
+    query: SingleTransactionQuery,
+
+    #[n(1)]
+    details: Option<bool>,
+}
+
+fn decode<'a, T: minicbor::Decode<'a, ()>>(data: &'a [u8]) -> Result<T, ManyError> {
+    minicbor::decode(data).map_err(ManyError::deserialization_error)
+}
+
+fn encode<T: minicbor::Encode<()>>(value: Result<T, ManyError>) -> Result<Vec<u8>, ManyError> {
+    value.and_then(|v| minicbor::to_vec(v).map_err(ManyError::serialization_error))
+}
+
+/// The blockchain module of the MANY frontend. On top of the regular
+/// blockchain endpoints, it supports returning transaction requests and
+/// responses inline and searching the tendermint transaction index.
+///
+/// Endpoints are executed directly on the (async) backend, so concurrent
+/// requests don't wait on each other.
+pub struct AbciBlockchainModule<C: Client + Clone + Send + Sync + 'static> {
+    info: ManyModuleInfo,
+    inner: blockchain::BlockchainModule<AbciBlockchainModuleImpl<C>>,
+    backend: Arc<AbciBlockchainModuleImpl<C>>,
+}
+
+impl<C: Client + Clone + Send + Sync + 'static> AbciBlockchainModule<C> {
+    pub fn new(backend: Arc<AbciBlockchainModuleImpl<C>>) -> Self {
+        // The generated module is only used for its info and validation, so it
+        // gets its own copy of the backend.
+        let inner =
+            blockchain::BlockchainModule::new(Arc::new(Mutex::new(backend.as_ref().clone())));
+        let mut info = inner.info().clone();
+        info.endpoints
+            .push(SEARCH_TRANSACTIONS_ENDPOINT.to_string());
+
+        Self {
+            info,
+            inner,
+            backend,
+        }
+    }
+}
+
+impl<C: Client + Clone + Send + Sync + 'static> Debug for AbciBlockchainModule<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AbciBlockchainModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl<C: Client + Clone + Send + Sync + 'static> ManyModule for AbciBlockchainModule<C> {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        if message.method == SEARCH_TRANSACTIONS_ENDPOINT {
+            decode::<SearchTransactionsArgs>(&message.data)?;
+            Ok(())
+        } else {
+            self.inner.validate(message, envelope)
+        }
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let backend = &self.backend;
+        let data = match message.method.as_str() {
+            "blockchain.info" => encode(backend.info().await),
+            "blockchain.block" => {
+                let BlockDetailsArgs { query, details } = decode(&message.data)?;
+                encode(
+                    backend
+                        .block(blockchain::BlockArgs { query }, details.unwrap_or(false))
+                        .await,
+                )
+            }
+            "blockchain.transaction" => {
+                let TransactionDetailsArgs { query, details } = decode(&message.data)?;
+                encode(
+                    backend
+                        .transaction(
+                            blockchain::TransactionArgs { query },
+                            details.unwrap_or(false),
+                        )
+                        .await,
+                )
+            }
+            "blockchain.list" => encode(backend.list(decode(&message.data)?).await),
+            "blockchain.request" => encode(backend.request(decode(&message.data)?).await),
+            "blockchain.response" => encode(backend.response(decode(&message.data)?).await),
+            SEARCH_TRANSACTIONS_ENDPOINT => {
+                encode(backend.search_transactions(decode(&message.data)?).await)
+            }
+            _ => return self.inner.execute(message).await,
+        };
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
@@ -0,0 +1,73 @@
+//! Deterministic normalization of the backend responses.
+//!
+//! Every node of the network executes the same transactions on its own
+//! backend, and the encoded responses end up in the block results, hence in the
+//! app hash. Any field which depends on the node (its identity, its clock, its
+//! version) must be normalized before the response is returned to tendermint,
+//! or the nodes would fork.
+use many_identity::Address;
+use many_protocol::ResponseMessage;
+use many_types::Timestamp;
+
+lazy_static::lazy_static!(
+    static ref EPOCH: Timestamp = Timestamp::new(0).unwrap();
+);
+
+/// What to do with a field of the response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FieldPolicy<T> {
+    /// Keep the value sent by the backend. Only valid for fields which are
+    /// the same on every node.
+    Keep,
+
+    /// Replace the value sent by the backend.
+    Set(T),
+}
+
+impl<T: Clone> FieldPolicy<T> {
+    fn apply(&self, value: &mut T) {
+        if let FieldPolicy::Set(v) = self {
+            *value = v.clone();
+        }
+    }
+}
+
+/// The normalization applied to a response, field by field. Fields which are
+/// not listed here (`to`, `id`, `data` and `attributes`) are derived from the
+/// request and the state, and are always kept.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NormalizationPolicy {
+    /// The identity of the backend. Consensus will sign the result, so it is
+    /// unnecessary.
+    pub from: FieldPolicy<Address>,
+
+    /// The protocol version of the backend.
+    pub version: FieldPolicy<Option<u8>>,
+
+    /// The clock of the backend, which might differ between two nodes.
+    pub timestamp: FieldPolicy<Option<Timestamp>>,
+}
+
+impl Default for NormalizationPolicy {
+    /// The policy of the blockchain: anonymous `from`, no version and a
+    /// timestamp at the epoch. Changing it changes the app hash of every block
+    /// with a transaction.
+    fn default() -> Self {
+        Self {
+            from: FieldPolicy::Set(Address::anonymous()),
+            version: FieldPolicy::Set(None),
+            timestamp: FieldPolicy::Set(Some(*EPOCH)),
+        }
+    }
+}
+
+impl NormalizationPolicy {
+    /// Normalize a response. Normalizing a response twice is the same as
+    /// normalizing it once.
+    pub fn normalize(&self, mut response: ResponseMessage) -> ResponseMessage {
+        self.from.apply(&mut response.from);
+        self.version.apply(&mut response.version);
+        self.timestamp.apply(&mut response.timestamp);
+        response
+    }
+}
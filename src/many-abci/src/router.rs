@@ -0,0 +1,219 @@
+use crate::backend::BackendClient;
+use many_error::ManyError;
+use many_modules::abci_backend::{AbciCommitInfo, AbciInfo, AbciInit, EndpointInfo};
+use many_modules::base;
+use sha2::Digest;
+use std::collections::BTreeMap;
+use std::future::Future;
+
+/// A backend MANY app, with the name it reports in its status.
+#[derive(Clone, Debug)]
+struct Backend {
+    name: String,
+    client: BackendClient,
+}
+
+/// Routes the MANY methods to the backends serving them, when several backends
+/// with distinct endpoints run behind one chain.
+///
+/// Every backend executes every block (`abci.beginBlock`, `abci.endBlock` and
+/// `abci.commit` go to all of them), and the app hash of the chain is a
+/// composite of their hashes. The order of the backends is part of the
+/// consensus: all nodes must list them in the same order.
+#[derive(Clone, Debug)]
+pub struct BackendRouter {
+    backends: Vec<Backend>,
+
+    /// The endpoints of all backends, and the index of the backend serving
+    /// each of them.
+    endpoints: BTreeMap<String, (usize, EndpointInfo)>,
+}
+
+/// Combine the app hashes of the backends, in order, into the app hash of the
+/// chain. With a single backend, this is the hash of that backend, so chains
+/// running one backend keep the same hashes.
+pub fn composite_hash(hashes: &[Vec<u8>]) -> Vec<u8> {
+    match hashes {
+        [hash] => hash.clone(),
+        hashes => {
+            let mut hasher = sha2::Sha256::new();
+            for hash in hashes {
+                hasher.update((hash.len() as u32).to_be_bytes());
+                hasher.update(hash);
+            }
+            hasher.finalize().to_vec()
+        }
+    }
+}
+
+/// Assign every endpoint to the backend serving it, by index. Each endpoint
+/// must be served by a single backend: one served by several (e.g.
+/// `events.list` or `account.info`, which both the ledger and the kvstore
+/// serve) could not reach all of them through the router, so this fails.
+pub fn route_endpoints(
+    inits: Vec<(String, AbciInit)>,
+) -> Result<BTreeMap<String, (usize, EndpointInfo)>, String> {
+    let mut endpoints: BTreeMap<String, (usize, EndpointInfo)> = BTreeMap::new();
+    for (index, (name, init)) in inits.iter().enumerate() {
+        for (method, info) in &init.endpoints {
+            if let Some((other, _)) = endpoints.get(method) {
+                let other = &inits[*other].0;
+                return Err(format!(
+                    "Endpoint '{method}' is served by both '{other}' and '{name}'."
+                ));
+            }
+            endpoints.insert(method.clone(), (index, info.clone()));
+        }
+    }
+    Ok(endpoints)
+}
+
+impl BackendRouter {
+    /// Fetch the status and the endpoints of every backend. Fails if several
+    /// backends serve the same endpoint (see `route_endpoints`).
+    pub fn create(clients: Vec<BackendClient>) -> Result<Self, String> {
+        if clients.is_empty() {
+            return Err("At least one backend is required.".to_string());
+        }
+
+        let mut backends: Vec<Backend> = Vec::with_capacity(clients.len());
+        let mut inits = Vec::with_capacity(clients.len());
+        for client in clients {
+            let status: base::Status = client
+                .call_sync("status", ())
+                .and_then(|payload| {
+                    minicbor::decode(&payload).map_err(ManyError::deserialization_error)
+                })
+                .map_err(|x| x.to_string())?;
+
+            let init: AbciInit = client
+                .call_sync("abci.init", ())
+                .and_then(|payload| {
+                    minicbor::decode(&payload).map_err(ManyError::deserialization_error)
+                })
+                .map_err(|x| x.to_string())?;
+
+            inits.push((status.name.clone(), init));
+            backends.push(Backend {
+                name: status.name,
+                client,
+            });
+        }
+
+        Ok(Self {
+            backends,
+            endpoints: route_endpoints(inits)?,
+        })
+    }
+
+    /// The name of the application, from the names of the backends.
+    pub fn name(&self) -> String {
+        self.backends
+            .iter()
+            .map(|b| b.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The names and clients of the backends, in order.
+    pub fn backends(&self) -> impl Iterator<Item = (&str, &BackendClient)> {
+        self.backends.iter().map(|b| (b.name.as_str(), &b.client))
+    }
+
+    /// The endpoints of all backends.
+    pub fn endpoints(&self) -> BTreeMap<String, EndpointInfo> {
+        self.endpoints
+            .iter()
+            .map(|(method, (_, info))| (method.clone(), info.clone()))
+            .collect()
+    }
+
+    pub fn endpoint(&self, method: &str) -> Option<&EndpointInfo> {
+        self.endpoints.get(method).map(|(_, info)| info)
+    }
+
+    /// The index of the backend serving a method, in the order of
+    /// `backends()`.
+    pub fn backend_index(&self, method: &str) -> Option<usize> {
+        self.endpoints.get(method).map(|(index, _)| *index)
+    }
+
+    /// The backend serving a method. Methods which are not ABCI endpoints
+    /// (e.g. `status`) go to the first backend.
+    pub fn route(&self, method: &str) -> &BackendClient {
+        let index = self.endpoints.get(method).map_or(0, |(index, _)| *index);
+        &self.backends[index].client
+    }
+
+    /// Run a future on the worker pool of the first backend and wait for its
+    /// result.
+    pub fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.backends[0].client.block_on(future)
+    }
+
+    /// Call a method on every backend, in order.
+    pub fn call_all_sync<T: minicbor::Encode<()> + Clone>(
+        &self,
+        method: &str,
+        argument: T,
+    ) -> Vec<Result<Vec<u8>, ManyError>> {
+        self.backends
+            .iter()
+            .map(|b| b.client.call_sync(method, argument.clone()))
+            .collect()
+    }
+
+    /// The height and composite hash of the backends. All backends must be at
+    /// the same height.
+    pub async fn info(&self) -> Result<AbciInfo, ManyError> {
+        let mut infos = Vec::with_capacity(self.backends.len());
+        for backend in &self.backends {
+            let payload = backend
+                .client
+                .call(
+                    "abci.info",
+                    minicbor::to_vec(()).map_err(ManyError::serialization_error)?,
+                )
+                .await?;
+            let info: AbciInfo =
+                minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+            infos.push((backend.name.as_str(), info));
+        }
+
+        let height = infos[0].1.height;
+        if let Some((name, info)) = infos.iter().find(|(_, info)| info.height != height) {
+            return Err(ManyError::unknown(format!(
+                "Backends are at different heights: '{}' is at {height}, '{name}' is at {}.",
+                infos[0].0, info.height
+            )));
+        }
+
+        let hashes: Vec<Vec<u8>> = infos.into_iter().map(|(_, i)| i.hash.to_vec()).collect();
+        Ok(AbciInfo {
+            height,
+            hash: composite_hash(&hashes).into(),
+        })
+    }
+
+    /// Commit every backend, returning the composite hash and the lowest
+    /// retain height.
+    pub fn commit(&self) -> Result<AbciCommitInfo, ManyError> {
+        let mut hashes = Vec::with_capacity(self.backends.len());
+        let mut retain_height = u64::MAX;
+        for payload in self.call_all_sync("abci.commit", ()) {
+            let info: AbciCommitInfo =
+                minicbor::decode(&payload?).map_err(ManyError::deserialization_error)?;
+            hashes.push(info.hash.to_vec());
+            retain_height = retain_height.min(info.retain_height);
+        }
+
+        Ok(AbciCommitInfo {
+            retain_height,
+            hash: composite_hash(&hashes).into(),
+        })
+    }
+}
@@ -0,0 +1,157 @@
+use coset::{CborSerializable, CoseSign1};
+use many_abci::abci_app::{
+    AbciApp, INVALID_ENVELOPE_CODE, INVALID_METHOD_CODE, INVALID_REQUEST_CODE,
+};
+use many_abci::backend::BackendClient;
+use many_abci::router::BackendRouter;
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::verifiers::AnonymousVerifier;
+use many_identity::{Address, AnonymousIdentity};
+use many_modules::abci_backend::{AbciInit, EndpointInfo};
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{
+    encode_cose_sign1_from_request, RequestMessage, RequestMessageBuilder, ResponseMessage,
+};
+use many_server::ManyServer;
+use std::collections::BTreeMap;
+use tendermint_abci::Application;
+use tendermint_proto::abci::{RequestCheckTx, RequestDeliverTx};
+
+/// A backend serving a command and a query.
+#[derive(Debug)]
+struct Backend {
+    info: ManyModuleInfo,
+}
+
+#[async_trait::async_trait]
+impl ManyModule for Backend {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, _message: &RequestMessage, _envelope: &CoseSign1) -> Result<(), ManyError> {
+        Ok(())
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let init = AbciInit {
+            endpoints: BTreeMap::from([
+                ("ledger.send".to_string(), EndpointInfo { is_command: true }),
+                (
+                    "ledger.balance".to_string(),
+                    EndpointInfo { is_command: false },
+                ),
+            ]),
+        };
+        let data = minicbor::to_vec(init).map_err(ManyError::serialization_error);
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
+
+fn app() -> AbciApp {
+    let many = ManyServer::simple("backend", AnonymousIdentity, AnonymousVerifier, None);
+    many.lock().unwrap().add_module(Backend {
+        info: ManyModuleInfo {
+            name: "Backend".to_string(),
+            attribute: None,
+            endpoints: vec!["abci.init".to_string()],
+        },
+    });
+    let client = BackendClient::local(many, 1).unwrap();
+    AbciApp::new(BackendRouter::create(vec![client]).unwrap(), None)
+}
+
+fn envelope(method: &str, from: Address) -> CoseSign1 {
+    let message = RequestMessageBuilder::default()
+        .method(method.to_string())
+        .from(from)
+        .build()
+        .unwrap();
+    encode_cose_sign1_from_request(message, &AnonymousIdentity).unwrap()
+}
+
+fn check_tx(app: &AbciApp, tx: Vec<u8>) -> u32 {
+    app.check_tx(RequestCheckTx {
+        tx: tx.into(),
+        ..Default::default()
+    })
+    .code
+}
+
+#[test]
+fn check_tx_accepts_commands() {
+    let app = app();
+    let tx = envelope("ledger.send", Address::anonymous());
+    assert_eq!(check_tx(&app, tx.to_vec().unwrap()), 0);
+}
+
+#[test]
+fn check_tx_rejects_invalid_envelopes() {
+    let app = app();
+    assert_eq!(
+        check_tx(&app, b"not an envelope".to_vec()),
+        INVALID_ENVELOPE_CODE
+    );
+}
+
+#[test]
+fn check_tx_rejects_invalid_requests() {
+    let app = app();
+    let mut tx = envelope("ledger.send", Address::anonymous());
+    tx.payload = Some(b"not a request".to_vec());
+    assert_eq!(check_tx(&app, tx.to_vec().unwrap()), INVALID_REQUEST_CODE);
+}
+
+#[test]
+fn check_tx_rejects_unverified_senders() {
+    let app = app();
+    // An unsigned envelope claiming to be from another address.
+    let tx = envelope("ledger.send", identity(1));
+    assert_eq!(check_tx(&app, tx.to_vec().unwrap()), INVALID_REQUEST_CODE);
+}
+
+#[test]
+fn check_tx_rejects_queries_and_unknown_methods() {
+    let app = app();
+    let query = envelope("ledger.balance", Address::anonymous());
+    assert_eq!(check_tx(&app, query.to_vec().unwrap()), INVALID_METHOD_CODE);
+
+    let unknown = envelope("kvstore.put", Address::anonymous());
+    assert_eq!(
+        check_tx(&app, unknown.to_vec().unwrap()),
+        INVALID_METHOD_CODE
+    );
+}
+
+/// The keys of the attributes of the `many` event of a transaction.
+fn many_attributes(app: &AbciApp, tx: CoseSign1) -> Vec<String> {
+    let response = app.deliver_tx(RequestDeliverTx {
+        tx: tx.to_vec().unwrap().into(),
+    });
+    let event = response
+        .events
+        .into_iter()
+        .find(|e| e.r#type == "many")
+        .unwrap();
+    event
+        .attributes
+        .into_iter()
+        .map(|a| String::from_utf8(a.key.to_vec()).unwrap())
+        .collect()
+}
+
+#[test]
+fn deliver_tx_indexes_verified_senders() {
+    let app = app();
+    let attributes = many_attributes(&app, envelope("ledger.send", Address::anonymous()));
+    assert!(attributes.contains(&"from".to_string()));
+}
+
+#[test]
+fn deliver_tx_does_not_index_forged_senders() {
+    let app = app();
+    let attributes = many_attributes(&app, envelope("ledger.send", identity(1)));
+    assert!(attributes.contains(&"method".to_string()));
+    assert!(!attributes.contains(&"from".to_string()));
+}
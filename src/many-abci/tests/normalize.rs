@@ -0,0 +1,130 @@
+use many_abci::normalize::{FieldPolicy, NormalizationPolicy};
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_protocol::{RequestMessage, RequestMessageBuilder, ResponseMessage};
+use many_types::Timestamp;
+use proptest::prelude::*;
+
+/// A backend of one node, with its own identity, clock and version.
+struct Backend {
+    identity: Address,
+    clock: u64,
+    version: Option<u8>,
+}
+
+impl Backend {
+    /// The bytes of the response of this backend to a request, as received by
+    /// `deliver_tx`.
+    fn respond(&self, request: &RequestMessage, data: Result<Vec<u8>, ManyError>) -> Vec<u8> {
+        let mut response = ResponseMessage::from_request(request, &self.identity, data);
+        response.timestamp = Some(Timestamp::new(self.clock).unwrap());
+        response.version = self.version;
+        response.to_bytes().unwrap()
+    }
+}
+
+fn backend() -> impl Strategy<Value = Backend> {
+    (any::<u32>(), any::<u32>(), any::<Option<u8>>()).prop_map(|(seed, clock, version)| Backend {
+        identity: identity(seed),
+        clock: clock as u64,
+        version,
+    })
+}
+
+fn request(sender: u32, id: u64) -> RequestMessage {
+    let mut request = RequestMessageBuilder::default()
+        .method("ledger.send".to_string())
+        .from(identity(sender))
+        .build()
+        .unwrap();
+    request.id = Some(id);
+    request
+}
+
+fn normalize(policy: &NormalizationPolicy, bytes: &[u8]) -> Vec<u8> {
+    policy
+        .normalize(ResponseMessage::from_bytes(bytes).unwrap())
+        .to_bytes()
+        .unwrap()
+}
+
+proptest! {
+    #[test]
+    fn identical_across_backends(
+        a in backend(),
+        b in backend(),
+        sender in any::<u32>(),
+        id in any::<u64>(),
+        data in proptest::collection::vec(any::<u8>(), 0..64),
+    ) {
+        let request = request(sender, id);
+        let policy = NormalizationPolicy::default();
+
+        let a = normalize(&policy, &a.respond(&request, Ok(data.clone())));
+        let b = normalize(&policy, &b.respond(&request, Ok(data)));
+        prop_assert_eq!(a, b);
+    }
+
+    #[test]
+    fn identical_errors_across_backends(
+        a in backend(),
+        b in backend(),
+        sender in any::<u32>(),
+        id in any::<u64>(),
+        message in "[a-z ]{0,32}",
+    ) {
+        let request = request(sender, id);
+        let policy = NormalizationPolicy::default();
+
+        let a = normalize(&policy, &a.respond(&request, Err(ManyError::unknown(message.clone()))));
+        let b = normalize(&policy, &b.respond(&request, Err(ManyError::unknown(message))));
+        prop_assert_eq!(a, b);
+    }
+
+    #[test]
+    fn idempotent(
+        a in backend(),
+        sender in any::<u32>(),
+        id in any::<u64>(),
+        data in proptest::collection::vec(any::<u8>(), 0..64),
+    ) {
+        let policy = NormalizationPolicy::default();
+        let once = normalize(&policy, &a.respond(&request(sender, id), Ok(data)));
+        prop_assert_eq!(normalize(&policy, &once), once);
+    }
+
+    #[test]
+    fn keeps_result(
+        a in backend(),
+        sender in any::<u32>(),
+        id in any::<u64>(),
+        data in proptest::collection::vec(any::<u8>(), 0..64),
+    ) {
+        let request = request(sender, id);
+        let bytes = normalize(&NormalizationPolicy::default(), &a.respond(&request, Ok(data.clone())));
+        let response = ResponseMessage::from_bytes(&bytes).unwrap();
+
+        prop_assert_eq!(response.data, Ok(data));
+        prop_assert_eq!(response.to, Some(identity(sender)));
+        prop_assert_eq!(response.id, Some(id));
+        prop_assert_eq!(response.from, Address::anonymous());
+    }
+}
+
+#[test]
+fn keep_policy() {
+    let backend = Backend {
+        identity: identity(1),
+        clock: 1_000_000,
+        version: Some(1),
+    };
+    let policy = NormalizationPolicy {
+        from: FieldPolicy::Keep,
+        version: FieldPolicy::Keep,
+        timestamp: FieldPolicy::Keep,
+    };
+
+    let bytes = backend.respond(&request(2, 3), Ok(vec![]));
+    assert_eq!(normalize(&policy, &bytes), bytes);
+}
@@ -0,0 +1,85 @@
+use many_abci::router::{composite_hash, route_endpoints};
+use many_modules::abci_backend::{AbciInit, EndpointInfo};
+
+#[test]
+fn single_backend_hash_is_unchanged() {
+    let hash = vec![1, 2, 3, 4];
+    assert_eq!(composite_hash(&[hash.clone()]), hash);
+}
+
+#[test]
+fn composite_hash_depends_on_order() {
+    let a = vec![1, 2, 3];
+    let b = vec![4, 5, 6];
+    assert_ne!(
+        composite_hash(&[a.clone(), b.clone()]),
+        composite_hash(&[b, a])
+    );
+}
+
+#[test]
+fn composite_hash_is_unambiguous() {
+    // The same bytes split differently between the backends.
+    assert_ne!(
+        composite_hash(&[vec![1, 2], vec![3]]),
+        composite_hash(&[vec![1], vec![2, 3]])
+    );
+}
+
+fn init(endpoints: &[(&str, bool)]) -> AbciInit {
+    AbciInit {
+        endpoints: endpoints
+            .iter()
+            .map(|(method, is_command)| {
+                (
+                    method.to_string(),
+                    EndpointInfo {
+                        is_command: *is_command,
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn endpoints_go_to_their_backend() {
+    let ledger = init(&[("ledger.send", true), ("ledger.balance", false)]);
+    let kvstore = init(&[("kvstore.put", true), ("kvstore.get", false)]);
+
+    let endpoints = route_endpoints(vec![
+        ("ledger".to_string(), ledger),
+        ("kvstore".to_string(), kvstore),
+    ])
+    .unwrap();
+    let routes: Vec<(&str, usize)> = endpoints
+        .iter()
+        .map(|(method, (index, _))| (method.as_str(), *index))
+        .collect();
+    assert_eq!(
+        routes,
+        vec![
+            ("kvstore.get", 1),
+            ("kvstore.put", 1),
+            ("ledger.balance", 0),
+            ("ledger.send", 0),
+        ]
+    );
+    assert!(endpoints["ledger.send"].1.is_command);
+    assert!(!endpoints["kvstore.get"].1.is_command);
+}
+
+#[test]
+fn shared_endpoints_are_rejected() {
+    let ledger = init(&[("ledger.send", true), ("events.list", false)]);
+    let kvstore = init(&[("kvstore.put", true), ("events.list", false)]);
+
+    let result = route_endpoints(vec![
+        ("ledger".to_string(), ledger),
+        ("kvstore".to_string(), kvstore),
+    ]);
+    assert_eq!(
+        result.unwrap_err(),
+        "Endpoint 'events.list' is served by both 'ledger' and 'kvstore'."
+    );
+}
@@ -10,6 +10,26 @@ define_attribute_many_error!(
         5: pub fn subres_alt_unsupported() => "Subresource alternative owner unsupported.",
         6: pub fn key_not_found() => "The key was not found.",
         7: pub fn cannot_disable_empty_key() => "Unable to disable an empty key.",
+        8: pub fn genesis_state_conflict()
+            => "The kvstore was initialized from --state or an existing store, but the genesis also has an application state.",
+        9: pub fn version_not_found(version)
+            => "Version {version} of the key was not found. Only the last versions of a key are kept.",
+        10: pub fn version_mismatch(expected, actual)
+            => "The key is at version {actual}, expected version {expected}.",
+        11: pub fn too_many_operations(max)
+            => "Too many operations in the batch, the maximum is {max}.",
+        12: pub fn duplicate_key(key) => "The key '{key}' appears more than once in the batch.",
+        13: pub fn value_mismatch(key) => "The value of the key '{key}' is not the expected value.",
+        14: pub fn invalid_expiry() => "The expiry of the key must be in the future.",
+        15: pub fn upload_not_found(id) => "Upload {id} was not found.",
+        16: pub fn chunk_out_of_order(expected) => "Expected chunk {expected} of the upload.",
+        17: pub fn upload_size_mismatch(expected, actual)
+            => "The upload is {expected} bytes, but {actual} bytes were sent.",
+        18: pub fn invalid_hash() => "The hash must be a 32 bytes SHA3-256 hash.",
+        19: pub fn hash_mismatch() => "The uploaded value does not match its hash.",
+        20: pub fn value_too_large(max) => "The value is too large, the maximum is {max} bytes.",
+        21: pub fn quota_exceeded(quota)
+            => "The owner of the key would go over its quota of {quota} bytes.",
     }
 );
 
@@ -1,10 +1,11 @@
+use crate::module::abci::KvStoreAbciModule;
 use crate::module::account::AccountFeatureModule;
 use clap::Parser;
 use many_identity::verifiers::AnonymousVerifier;
 use many_identity::Address;
 use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
 use many_modules::account::features::Feature;
-use many_modules::{abci_backend, account, events, kvstore};
+use many_modules::{account, events, kvstore, ManyModule};
 use many_server::transport::http::HttpServer;
 use many_server::ManyServer;
 use std::collections::BTreeSet;
@@ -18,8 +19,26 @@ mod error;
 mod module;
 mod storage;
 
+use crate::storage::usage::KvStoreLimits;
 use module::*;
 
+/// Add a module of commands, which only the addresses in `allow_addrs` can
+/// execute if it is set.
+fn add_commands_module<M: ManyModule + 'static>(
+    server: &mut ManyServer,
+    module: M,
+    allow_addrs: &Option<BTreeSet<Address>>,
+) {
+    if let Some(allow_addrs) = allow_addrs {
+        server.add_module(allow_addrs::AllowAddrsModule {
+            inner: module,
+            allow_addrs: allow_addrs.clone(),
+        });
+    } else {
+        server.add_module(module);
+    }
+}
+
 #[derive(clap::ArgEnum, Clone, Debug)]
 enum LogStrategy {
     Terminal,
@@ -49,6 +68,7 @@ struct Opts {
     abci: bool,
 
     /// Path of a state file (that will be used for the initial setup).
+    /// With `--abci`, the initial state can instead be given in the genesis of the chain.
     #[clap(long)]
     state: Option<PathBuf>,
 
@@ -70,6 +90,24 @@ struct Opts {
     /// Any addresses will be able to execute queries, e.g., balance, get, ...
     #[clap(long)]
     allow_addrs: Option<PathBuf>,
+
+    /// Log the SHA3-256 hash of values in put events instead of the values,
+    /// so they cannot be read from the event log. All the nodes of a chain
+    /// must use the same setting, so it is only read when the persistent
+    /// store is created, and kept in it.
+    #[clap(long)]
+    hash_event_values: bool,
+
+    /// The maximum size of a value, in bytes. Like `--hash-event-values`, it
+    /// is kept in the persistent store when it is created.
+    #[clap(long)]
+    max_value_size: Option<u64>,
+
+    /// The maximum number of bytes the values of an owner can use. Like
+    /// `--hash-event-values`, it is kept in the persistent store when it is
+    /// created.
+    #[clap(long)]
+    owner_quota: Option<u64>,
 }
 
 fn main() {
@@ -84,6 +122,9 @@ fn main() {
         clean,
         logmode,
         allow_addrs,
+        hash_event_values,
+        max_value_size,
+        owner_quota,
     } = Opts::parse();
 
     let verbose_level = 2 + verbose - quiet;
@@ -134,7 +175,12 @@ fn main() {
         json5::from_str(&content).unwrap()
     });
 
-    let module = if persistent.exists() {
+    let limits = KvStoreLimits {
+        max_value_size,
+        owner_quota,
+    };
+    let created = !persistent.exists();
+    let mut module = if persistent.exists() {
         if state.is_some() {
             tracing::warn!(
                 r#"
@@ -148,10 +194,27 @@ fn main() {
         KvStoreModuleImpl::load(persistent, abci).unwrap()
     } else if let Some(state) = state {
         KvStoreModuleImpl::new(state, persistent, abci).unwrap()
+    } else if abci {
+        info!("No staging file given. The initial state will be read from the genesis.");
+        KvStoreModuleImpl::new_for_genesis(persistent).unwrap()
     } else {
         panic!("Persistent store or staging file not found.")
     };
 
+    if created {
+        module.set_hash_event_values(hash_event_values).unwrap();
+        module.set_limits(limits).unwrap();
+    } else if (hash_event_values && !module.hash_event_values())
+        || (max_value_size.is_some() && max_value_size != module.limits().max_value_size)
+        || (owner_quota.is_some() && owner_quota != module.limits().owner_quota)
+    {
+        tracing::warn!(
+            "Ignoring --hash-event-values, --max-value-size and --owner-quota, the persistent store \
+             keeps the settings it was created with: {}, {:?}.",
+            module.hash_event_values(),
+            module.limits(),
+        );
+    }
     let module = Arc::new(Mutex::new(module));
 
     let many = ManyServer::simple(
@@ -163,18 +226,42 @@ fn main() {
 
     {
         let mut s = many.lock().unwrap();
-        s.add_module(kvstore::KvStoreModule::new(module.clone()));
-        let kvstore_command_module = kvstore::KvStoreCommandsModule::new(module.clone());
-        if let Some(path) = allow_addrs {
-            let allow_addrs: BTreeSet<Address> =
-                json5::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
-            s.add_module(allow_addrs::AllowAddrsModule {
-                inner: kvstore_command_module,
-                allow_addrs,
-            });
-        } else {
-            s.add_module(kvstore_command_module);
-        }
+        s.add_module(version::VersionedKvStoreModule::new(module.clone()));
+        s.add_module(list::KvStoreListModule::new(module.clone()));
+        s.add_module(usage::KvStoreUsageModule::new(module.clone()));
+        let allow_addrs: Option<BTreeSet<Address>> = allow_addrs
+            .map(|path| json5::from_str(&std::fs::read_to_string(path).unwrap()).unwrap());
+        add_commands_module(
+            &mut s,
+            version::VersionedKvStoreCommandsModule::new(module.clone()),
+            &allow_addrs,
+        );
+        add_commands_module(
+            &mut s,
+            batch::KvStoreBatchModule::new(module.clone()),
+            &allow_addrs,
+        );
+        add_commands_module(
+            &mut s,
+            delete::KvStoreDeleteModule::new(module.clone()),
+            &allow_addrs,
+        );
+        add_commands_module(
+            &mut s,
+            enable::KvStoreEnableModule::new(module.clone()),
+            &allow_addrs,
+        );
+        add_commands_module(
+            &mut s,
+            grant::KvStoreGrantModule::new(module.clone()),
+            &allow_addrs,
+        );
+        s.add_module(grant::KvStoreGrantsModule::new(module.clone()));
+        add_commands_module(
+            &mut s,
+            upload::KvStoreUploadModule::new(module.clone()),
+            &allow_addrs,
+        );
         s.add_module(kvstore::KvStoreTransferModule::new(module.clone()));
         s.add_module(events::EventsModule::new(module.clone()));
 
@@ -184,7 +271,7 @@ fn main() {
         ));
         if abci {
             s.set_timeout(u64::MAX);
-            s.add_module(abci_backend::AbciModule::new(module));
+            s.add_module(KvStoreAbciModule::new(module));
         }
     }
     let mut many_server = HttpServer::new(many);
@@ -1,6 +1,7 @@
 use crate::{
     error,
-    storage::{AclMap, KvStoreStorage},
+    module::grant::{Grants, KeyPermission},
+    storage::{usage::KvStoreLimits, AclMap, KvStoreStorage},
 };
 use many_error::{ManyError, Reason};
 use many_identity::Address;
@@ -20,9 +21,18 @@ use std::fmt::Debug;
 use std::path::Path;
 use tracing::info;
 
+pub mod abci;
 pub mod account;
 pub mod allow_addrs;
+pub mod batch;
+pub mod delete;
+pub mod enable;
 mod event;
+pub mod grant;
+pub mod list;
+pub mod upload;
+pub mod usage;
+pub mod version;
 
 // The initial state schema, loaded from JSON.
 #[derive(serde::Deserialize, Debug, Default)]
@@ -38,9 +48,9 @@ pub struct KvStoreModuleImpl {
     storage: KvStoreStorage,
 }
 
-/// The KvStoreMetadata mimics the QueryReturns structure but adds serde capabilities
+/// The KvStoreMetadata extends the QueryReturns structure (which it can be
+/// decoded as) with serde capabilities and the expiry of the key.
 #[derive(Clone, Debug, minicbor::Encode, minicbor::Decode, serde::Deserialize)]
-#[serde(remote = "QueryReturns")]
 #[cbor(map)]
 pub struct KvStoreMetadata {
     #[n(0)]
@@ -49,16 +59,23 @@ pub struct KvStoreMetadata {
     #[n(1)]
     #[serde(skip_deserializing)]
     pub disabled: Option<Either<bool, Reason<u64>>>,
+
+    /// When the key expires. Expired keys are treated as absent, and removed
+    /// at the end of the block.
+    #[n(2)]
+    #[serde(skip_deserializing)]
+    pub expires: Option<Timestamp>,
+
+    /// The permissions of other addresses on the key.
+    #[n(3)]
+    #[serde(skip_deserializing)]
+    pub grants: Option<Grants>,
 }
 
 #[derive(Debug, serde::Deserialize, minicbor::Encode, minicbor::Decode)]
 #[serde(transparent)]
 #[cbor(transparent)]
-pub struct KvStoreMetadataWrapper(
-    #[serde(with = "KvStoreMetadata")]
-    #[n(0)]
-    QueryReturns,
-);
+pub struct KvStoreMetadataWrapper(#[n(0)] KvStoreMetadata);
 
 impl KvStoreModuleImpl {
     pub fn load<P: AsRef<Path>>(
@@ -76,28 +93,75 @@ impl KvStoreModuleImpl {
         persistence_store_path: P,
         blockchain: bool,
     ) -> Result<Self, ManyError> {
-        let storage = KvStoreStorage::new(
-            initial_state.acl,
-            initial_state.identity,
-            persistence_store_path,
-            blockchain,
-        )
-        .map_err(ManyError::unknown)?;
+        let mut module_impl = Self {
+            storage: KvStoreStorage::open(persistence_store_path, blockchain)
+                .map_err(ManyError::unknown)?,
+        };
+        module_impl.init_storage(initial_state)?;
+        Ok(module_impl)
+    }
+
+    /// Set the limits of a new storage. They are kept in the storage, as all
+    /// the nodes of a chain must use the same limits.
+    pub fn set_limits(&mut self, limits: KvStoreLimits) -> Result<(), ManyError> {
+        self.storage.set_limits(limits)
+    }
+
+    pub fn limits(&self) -> KvStoreLimits {
+        self.storage.limits()
+    }
+
+    /// Log the hash of put values in events, instead of the values. Like the
+    /// limits, this is set once for a new storage.
+    pub fn set_hash_event_values(&mut self, hash_event_values: bool) -> Result<(), ManyError> {
+        self.storage.set_hash_event_values(hash_event_values)
+    }
+
+    pub fn hash_event_values(&self) -> bool {
+        self.storage.hash_event_values()
+    }
+
+    /// Create a kv-store with an empty storage. Its initial state is given by
+    /// the genesis of the chain, in `abci.initChain`.
+    pub fn new_for_genesis<P: AsRef<Path>>(persistence_store_path: P) -> Result<Self, ManyError> {
+        let storage =
+            KvStoreStorage::open(persistence_store_path, true).map_err(ManyError::unknown)?;
+        Ok(Self { storage })
+    }
+
+    /// Initialize the storage from the genesis state of the chain and return
+    /// the resulting hash.
+    pub fn init_chain_with_state(
+        &mut self,
+        initial_state: InitialStateJson,
+    ) -> Result<Vec<u8>, ManyError> {
+        if self.storage.is_initialized() {
+            return Err(error::genesis_state_conflict());
+        }
+
+        self.init_storage(initial_state)?;
+        Ok(self.storage.hash())
+    }
+
+    fn init_storage(&mut self, initial_state: InitialStateJson) -> Result<(), ManyError> {
+        self.storage
+            .init(initial_state.acl, initial_state.identity)
+            .map_err(ManyError::unknown)?;
 
         if let Some(h) = initial_state.hash {
             // Verify the hash.
-            let actual = hex::encode(storage.hash());
+            let actual = hex::encode(self.storage.hash());
             if actual != h {
                 return Err(error::invalid_initial_hash(h, actual));
             }
         }
 
         info!(
-            height = storage.get_height(),
-            hash = hex::encode(storage.hash()).as_str()
+            height = self.storage.get_height(),
+            hash = hex::encode(self.storage.hash()).as_str()
         );
 
-        Ok(Self { storage })
+        Ok(())
     }
 }
 
@@ -111,8 +175,20 @@ impl ManyAbciModuleBackend for KvStoreModuleImpl {
                 ("kvstore.info".to_string(), EndpointInfo { is_command: false }),
                 ("kvstore.get".to_string(), EndpointInfo { is_command: false }),
                 ("kvstore.query".to_string(), EndpointInfo { is_command: false }),
+                ("kvstore.list".to_string(), EndpointInfo { is_command: false }),
                 ("kvstore.put".to_string(), EndpointInfo { is_command: true }),
                 ("kvstore.disable".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.batch".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.delete".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.enable".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.grant".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.revoke".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.grants".to_string(), EndpointInfo { is_command: false }),
+                ("kvstore.beginUpload".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.putChunk".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.finalizeUpload".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.abortUpload".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.usage".to_string(), EndpointInfo { is_command: false }),
 
                 // Accounts
                 ("account.create".to_string(), EndpointInfo { is_command: true }),
@@ -195,37 +271,33 @@ impl KvStoreModuleBackend for KvStoreModuleImpl {
     }
 
     fn query(&self, _sender: &Address, args: QueryArgs) -> Result<QueryReturns, ManyError> {
-        minicbor::decode(
-            &self
-                .storage
-                .get_metadata(&args.key)?
-                .ok_or_else(error::key_not_found)?,
-        )
-        .map_err(|e| ManyError::deserialization_error(e.to_string()))
+        let meta = self
+            .storage
+            .get_decoded_metadata(&args.key)?
+            .ok_or_else(error::key_not_found)?;
+        if self.storage.is_expired(&meta)? {
+            return Err(error::key_not_found());
+        }
+
+        Ok(QueryReturns {
+            owner: meta.owner,
+            disabled: meta.disabled,
+        })
     }
 }
 
 impl KvStoreCommandsModuleBackend for KvStoreModuleImpl {
     fn put(&mut self, sender: &Address, args: PutArgs) -> Result<PutReturn, ManyError> {
-        let key: Vec<u8> = args.key.into();
-        let owner = if let Some(alternative_owner) = args.alternative_owner {
-            self.validate_alternative_owner(
-                sender,
-                &alternative_owner,
-                [Role::CanKvStorePut, Role::Owner],
-            )?;
-            alternative_owner
-        } else {
-            *sender
-        };
-
-        self.verify_acl(&owner, key.clone())?;
-
-        let meta = KvStoreMetadata {
-            owner,
-            disabled: Some(Either::Left(false)),
-        };
-        self.storage.put(&meta, &key, args.value.into())?;
+        self.put_versioned(
+            sender,
+            version::PutArgs {
+                key: args.key,
+                value: args.value,
+                alternative_owner: args.alternative_owner,
+                if_version: None,
+                expires: None,
+            },
+        )?;
         Ok(PutReturn {})
     }
 
@@ -245,7 +317,7 @@ impl KvStoreCommandsModuleBackend for KvStoreModuleImpl {
             sender
         };
 
-        self.verify_acl(owner, key.clone())?;
+        self.verify_acl(owner, key.clone(), Some(KeyPermission::Disable))?;
 
         let maybe_reason = if let Some(reason) = args.reason {
             Either::Right(reason)
@@ -253,9 +325,13 @@ impl KvStoreCommandsModuleBackend for KvStoreModuleImpl {
             Either::Left(true)
         };
 
+        let meta = self
+            .storage
+            .get_decoded_metadata(&key)?
+            .ok_or_else(error::key_not_found)?;
         let meta = KvStoreMetadata {
-            owner: *owner,
             disabled: Some(maybe_reason),
+            ..meta
         };
 
         self.storage.disable(&meta, &key)?;
@@ -296,14 +372,17 @@ impl KvStoreTransferModuleBackend for KvStoreModuleImpl {
             sender
         };
 
-        self.verify_acl(owner, key.clone())?;
+        self.verify_acl(owner, key.clone(), Some(KeyPermission::Transfer))?;
 
-        // We allow transferring a disabled key, and keep the same reason.
+        // We allow transferring a disabled key, and keep the same reason and
+        // expiry. The grants of the previous owner do not carry over.
         let meta = KvStoreMetadata {
             owner: args.new_owner,
             disabled: metadata.disabled,
+            expires: metadata.expires,
+            grants: None,
         };
-        self.storage.transfer(&key, *owner, meta)?;
+        self.storage.transfer(&key, metadata.owner, meta)?;
 
         Ok(TransferReturn {})
     }
@@ -0,0 +1,89 @@
+use crate::module::{InitialStateJson, KvStoreModuleImpl};
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_modules::{abci_backend, ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use minicbor::bytes::ByteVec;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+/// The arguments of `abci.initChain`, sent by the ABCI frontend when the
+/// genesis of the chain has an application state.
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct InitChainArgs {
+    /// The `app_state` of the genesis, an `InitialStateJson`.
+    #[n(0)]
+    pub app_state: ByteVec,
+}
+
+#[derive(Clone, Debug, Default, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct InitChainReturns {
+    #[n(0)]
+    pub app_hash: Option<ByteVec>,
+}
+
+/// The ABCI backend module of the kvstore. `abci.initChain` also accepts the
+/// genesis state.
+pub struct KvStoreAbciModule {
+    inner: abci_backend::AbciModule<KvStoreModuleImpl>,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl KvStoreAbciModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            inner: abci_backend::AbciModule::new(module_impl.clone()),
+            module_impl,
+        }
+    }
+
+    fn init_chain(&self, args: InitChainArgs) -> Result<Vec<u8>, ManyError> {
+        let content =
+            std::str::from_utf8(&args.app_state).map_err(ManyError::deserialization_error)?;
+        let state: InitialStateJson =
+            json5::from_str(content).map_err(ManyError::deserialization_error)?;
+        let hash = self
+            .module_impl
+            .lock()
+            .unwrap()
+            .init_chain_with_state(state)?;
+
+        minicbor::to_vec(InitChainReturns {
+            app_hash: Some(hash.into()),
+        })
+        .map_err(ManyError::serialization_error)
+    }
+}
+
+impl Debug for KvStoreAbciModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KvStoreAbciModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for KvStoreAbciModule {
+    fn info(&self) -> &ManyModuleInfo {
+        self.inner.info()
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        self.inner.validate(message, envelope)
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        match message.method.as_str() {
+            "abci.initChain" => match minicbor::decode::<InitChainArgs>(&message.data) {
+                Ok(args) => {
+                    let data = self.init_chain(args);
+                    Ok(ResponseMessage::from_request(&message, &message.to, data))
+                }
+                // Without a genesis state, this is the regular `init_chain`.
+                Err(_) => self.inner.execute(message).await,
+            },
+            _ => self.inner.execute(message).await,
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use super::{error, KvStoreMetadata, KvStoreModuleImpl};
+use super::grant::KeyPermission;
+use super::{error, KvStoreModuleImpl};
 use coset::CoseSign1;
 use many_error::{ManyError, ManyErrorCode};
 use many_identity::Address;
@@ -299,18 +300,30 @@ impl KvStoreModuleImpl {
         }
     }
 
-    /// Verify if user is permitted to access the value at the given key
-    pub(crate) fn verify_acl(&self, sender: &Address, key: Vec<u8>) -> Result<(), ManyError> {
+    /// Verify if user is permitted to access the value at the given key, as
+    /// its owner or, if `permission` is given, with this permission granted.
+    pub(crate) fn verify_acl(
+        &self,
+        sender: &Address,
+        key: Vec<u8>,
+        permission: Option<KeyPermission>,
+    ) -> Result<(), ManyError> {
         // Get ACL, if it exists
-        if let Some(meta_cbor) = self.storage.get_metadata(&key)? {
-            // Decode ACL
-            let meta: KvStoreMetadata = minicbor::decode(&meta_cbor)
-                .map_err(|e| ManyError::deserialization_error(e.to_string()))?;
-
-            if &meta.owner == sender {
+        if let Some(meta) = self.storage.get_decoded_metadata(&key)? {
+            // An expired key is free, even before it is removed.
+            if &meta.owner == sender || self.storage.is_expired(&meta)? {
                 return Ok(());
             }
 
+            if let (Some(permission), Some(grants)) = (permission, &meta.grants) {
+                if grants
+                    .get(sender)
+                    .map_or(false, |granted| granted.contains(&permission))
+                {
+                    return Ok(());
+                }
+            }
+
             return Err(error::permission_denied());
         }
         Ok(())
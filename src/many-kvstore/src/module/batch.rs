@@ -0,0 +1,192 @@
+use crate::error;
+use crate::module::grant::KeyPermission;
+use crate::module::KvStoreModuleImpl;
+use crate::storage::batch::KeyOperation;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::account::Role;
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use many_types::Timestamp;
+use minicbor::bytes::ByteVec;
+use std::collections::BTreeSet;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+pub const BATCH_ENDPOINT: &str = "kvstore.batch";
+
+/// The maximum number of operations in a single `kvstore.batch` call.
+pub const MAXIMUM_BATCH_OPERATIONS: usize = 100;
+
+/// An operation of a batch, with its preconditions.
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct BatchOperation {
+    #[n(0)]
+    pub key: ByteVec,
+
+    /// The value to put, or none to delete the key.
+    #[n(1)]
+    pub value: Option<ByteVec>,
+
+    /// Only apply the batch if the key is at this version. Version 0 means the
+    /// key was never put.
+    #[n(2)]
+    pub if_version: Option<u64>,
+
+    /// Only apply the batch if the current value of the key is this value.
+    #[n(3)]
+    pub if_value: Option<ByteVec>,
+
+    /// When the key expires, if it should. Only for puts.
+    #[n(4)]
+    pub expires: Option<Timestamp>,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct BatchArgs {
+    /// The operations to apply, each on a distinct key.
+    #[n(0)]
+    pub operations: Vec<BatchOperation>,
+
+    #[n(1)]
+    pub alternative_owner: Option<Address>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct BatchReturns {
+    /// The version of each key after its operation, in order. A deleted key
+    /// is at version 0.
+    #[n(0)]
+    pub versions: Vec<u64>,
+}
+
+impl KvStoreModuleImpl {
+    /// Put and delete several keys atomically. Either all operations are
+    /// applied, or none of them if any check or precondition fails.
+    pub fn batch(&mut self, sender: &Address, args: BatchArgs) -> Result<BatchReturns, ManyError> {
+        if sender.is_anonymous() {
+            return Err(ManyError::invalid_identity());
+        }
+        if args.operations.len() > MAXIMUM_BATCH_OPERATIONS {
+            return Err(error::too_many_operations(MAXIMUM_BATCH_OPERATIONS));
+        }
+
+        let owner = if let Some(alternative_owner) = args.alternative_owner {
+            if args.operations.iter().any(|op| op.value.is_some()) {
+                self.validate_alternative_owner(
+                    sender,
+                    &alternative_owner,
+                    [Role::CanKvStorePut, Role::Owner],
+                )?;
+            }
+            if args.operations.iter().any(|op| op.value.is_none()) {
+                self.validate_alternative_owner(
+                    sender,
+                    &alternative_owner,
+                    [Role::CanKvStoreDisable, Role::Owner],
+                )?;
+            }
+            alternative_owner
+        } else {
+            *sender
+        };
+
+        let mut keys = BTreeSet::new();
+        let mut operations = Vec::with_capacity(args.operations.len());
+        for op in args.operations {
+            let key: Vec<u8> = op.key.into();
+            if !keys.insert(key.clone()) {
+                return Err(error::duplicate_key(hex::encode(&key)));
+            }
+
+            let permission = op.value.as_ref().map(|_| KeyPermission::Put);
+            self.verify_acl(&owner, key.clone(), permission)?;
+
+            if let Some(expires) = &op.expires {
+                self.storage.check_expiry(expires)?;
+            }
+            if let Some(expected) = op.if_version {
+                let actual = self.storage.get_version(&key)?;
+                if expected != actual {
+                    return Err(error::version_mismatch(expected, actual));
+                }
+            }
+            if let Some(expected) = op.if_value {
+                if self.storage.get(&key)?.as_deref() != Some(expected.as_slice()) {
+                    return Err(error::value_mismatch(hex::encode(&key)));
+                }
+            }
+
+            let operation = match op.value {
+                Some(value) => KeyOperation::Put {
+                    meta: self.metadata_for_put(owner, &key, op.expires)?,
+                    value: value.into(),
+                },
+                None => {
+                    if self.storage.get_metadata(&key)?.is_none() {
+                        return Err(error::key_not_found());
+                    }
+                    KeyOperation::Delete
+                }
+            };
+            operations.push((key, operation));
+        }
+
+        let versions = self.storage.apply_operations(operations)?;
+        Ok(BatchReturns { versions })
+    }
+}
+
+/// The `kvstore.batch` endpoint.
+pub struct KvStoreBatchModule {
+    info: ManyModuleInfo,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl KvStoreBatchModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            info: ManyModuleInfo {
+                name: "KvStoreBatchModule".to_string(),
+                attribute: None,
+                endpoints: vec![BATCH_ENDPOINT.to_string()],
+            },
+            module_impl,
+        }
+    }
+}
+
+impl Debug for KvStoreBatchModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KvStoreBatchModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for KvStoreBatchModule {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, _envelope: &CoseSign1) -> Result<(), ManyError> {
+        minicbor::decode::<BatchArgs>(&message.data).map_err(ManyError::deserialization_error)?;
+        Ok(())
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let args: BatchArgs =
+            minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+        let data = self
+            .module_impl
+            .lock()
+            .unwrap()
+            .batch(&message.from(), args)
+            .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error));
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
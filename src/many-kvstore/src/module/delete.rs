@@ -0,0 +1,108 @@
+use crate::error;
+use crate::module::KvStoreModuleImpl;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::account::Role;
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use minicbor::bytes::ByteVec;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+pub const DELETE_ENDPOINT: &str = "kvstore.delete";
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct DeleteArgs {
+    #[n(0)]
+    pub key: ByteVec,
+
+    #[n(1)]
+    pub alternative_owner: Option<Address>,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct DeleteReturns {}
+
+impl KvStoreModuleImpl {
+    /// Remove a key and its value from the store. Only its owner can delete
+    /// it, even if it was disabled.
+    pub fn delete(
+        &mut self,
+        sender: &Address,
+        args: DeleteArgs,
+    ) -> Result<DeleteReturns, ManyError> {
+        let key: Vec<u8> = args.key.into();
+        let meta = self
+            .storage
+            .get_decoded_metadata(&key)?
+            .ok_or_else(error::key_not_found)?;
+        if self.storage.is_expired(&meta)? {
+            return Err(error::key_not_found());
+        }
+
+        let owner = if let Some(ref alternative_owner) = args.alternative_owner {
+            self.validate_alternative_owner(sender, alternative_owner, [Role::Owner])?;
+            alternative_owner
+        } else {
+            sender
+        };
+
+        self.verify_acl(owner, key.clone(), None)?;
+
+        self.storage.delete(&key)?;
+        Ok(DeleteReturns {})
+    }
+}
+
+/// The `kvstore.delete` endpoint.
+pub struct KvStoreDeleteModule {
+    info: ManyModuleInfo,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl KvStoreDeleteModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            info: ManyModuleInfo {
+                name: "KvStoreDeleteModule".to_string(),
+                attribute: None,
+                endpoints: vec![DELETE_ENDPOINT.to_string()],
+            },
+            module_impl,
+        }
+    }
+}
+
+impl Debug for KvStoreDeleteModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KvStoreDeleteModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for KvStoreDeleteModule {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, _envelope: &CoseSign1) -> Result<(), ManyError> {
+        minicbor::decode::<DeleteArgs>(&message.data).map_err(ManyError::deserialization_error)?;
+        Ok(())
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let args: DeleteArgs =
+            minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+        let data = self
+            .module_impl
+            .lock()
+            .unwrap()
+            .delete(&message.from(), args)
+            .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error));
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
@@ -0,0 +1,117 @@
+use crate::error;
+use crate::module::grant::KeyPermission;
+use crate::module::{KvStoreMetadata, KvStoreModuleImpl};
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::account::Role;
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use many_types::Either;
+use minicbor::bytes::ByteVec;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+pub const ENABLE_ENDPOINT: &str = "kvstore.enable";
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct EnableArgs {
+    #[n(0)]
+    pub key: ByteVec,
+
+    #[n(1)]
+    pub alternative_owner: Option<Address>,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct EnableReturns {}
+
+impl KvStoreModuleImpl {
+    /// Enable a key which was disabled, keeping its value.
+    pub fn enable(
+        &mut self,
+        sender: &Address,
+        args: EnableArgs,
+    ) -> Result<EnableReturns, ManyError> {
+        let key: Vec<u8> = args.key.into();
+        let meta = self
+            .storage
+            .get_decoded_metadata(&key)?
+            .ok_or_else(error::key_not_found)?;
+        if self.storage.is_expired(&meta)? {
+            return Err(error::key_not_found());
+        }
+
+        let owner = if let Some(ref alternative_owner) = args.alternative_owner {
+            self.validate_alternative_owner(
+                sender,
+                alternative_owner,
+                [Role::CanKvStoreDisable, Role::Owner],
+            )?;
+            alternative_owner
+        } else {
+            sender
+        };
+
+        self.verify_acl(owner, key.clone(), Some(KeyPermission::Disable))?;
+
+        let meta = KvStoreMetadata {
+            disabled: Some(Either::Left(false)),
+            ..meta
+        };
+        self.storage.update_metadata(&meta, &key)?;
+        Ok(EnableReturns {})
+    }
+}
+
+/// The `kvstore.enable` endpoint.
+pub struct KvStoreEnableModule {
+    info: ManyModuleInfo,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl KvStoreEnableModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            info: ManyModuleInfo {
+                name: "KvStoreEnableModule".to_string(),
+                attribute: None,
+                endpoints: vec![ENABLE_ENDPOINT.to_string()],
+            },
+            module_impl,
+        }
+    }
+}
+
+impl Debug for KvStoreEnableModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KvStoreEnableModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for KvStoreEnableModule {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, _envelope: &CoseSign1) -> Result<(), ManyError> {
+        minicbor::decode::<EnableArgs>(&message.data).map_err(ManyError::deserialization_error)?;
+        Ok(())
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let args: EnableArgs =
+            minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+        let data = self
+            .module_impl
+            .lock()
+            .unwrap()
+            .enable(&message.from(), args)
+            .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error));
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
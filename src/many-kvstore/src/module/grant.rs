@@ -0,0 +1,308 @@
+use crate::error;
+use crate::module::{KvStoreMetadata, KvStoreModuleImpl};
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::account::Role;
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use minicbor::bytes::ByteVec;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+pub const GRANT_ENDPOINT: &str = "kvstore.grant";
+pub const REVOKE_ENDPOINT: &str = "kvstore.revoke";
+pub const GRANTS_ENDPOINT: &str = "kvstore.grants";
+
+/// What an address other than the owner of a key can do with it. Values are
+/// public, so there is no permission to read them.
+#[derive(
+    Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, minicbor::Encode, minicbor::Decode,
+)]
+#[cbor(index_only)]
+pub enum KeyPermission {
+    #[n(0)]
+    Put,
+
+    #[n(1)]
+    Disable,
+
+    #[n(2)]
+    Transfer,
+}
+
+pub type Grants = BTreeMap<Address, BTreeSet<KeyPermission>>;
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct GrantArgs {
+    #[n(0)]
+    pub key: ByteVec,
+
+    /// The address to grant the permissions to.
+    #[n(1)]
+    pub address: Address,
+
+    #[n(2)]
+    pub permissions: BTreeSet<KeyPermission>,
+
+    #[n(3)]
+    pub alternative_owner: Option<Address>,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct GrantReturns {}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct RevokeArgs {
+    #[n(0)]
+    pub key: ByteVec,
+
+    /// The address to revoke the permissions of.
+    #[n(1)]
+    pub address: Address,
+
+    /// The permissions to revoke, or all of them.
+    #[n(2)]
+    pub permissions: Option<BTreeSet<KeyPermission>>,
+
+    #[n(3)]
+    pub alternative_owner: Option<Address>,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct RevokeReturns {}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct GrantsArgs {
+    #[n(0)]
+    pub key: ByteVec,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct GrantsReturns {
+    #[n(0)]
+    pub grants: Grants,
+}
+
+impl KvStoreModuleImpl {
+    /// The metadata of a key which exists and did not expire.
+    fn live_metadata(&self, key: &[u8]) -> Result<KvStoreMetadata, ManyError> {
+        let meta = self
+            .storage
+            .get_decoded_metadata(key)?
+            .ok_or_else(error::key_not_found)?;
+        if self.storage.is_expired(&meta)? {
+            return Err(error::key_not_found());
+        }
+        Ok(meta)
+    }
+
+    /// Change the grants of a key, as its owner.
+    fn update_grants(
+        &mut self,
+        sender: &Address,
+        key: Vec<u8>,
+        alternative_owner: Option<Address>,
+        f: impl FnOnce(&mut Grants),
+    ) -> Result<(), ManyError> {
+        let meta = self.live_metadata(&key)?;
+        let owner = if let Some(ref alternative_owner) = alternative_owner {
+            self.validate_alternative_owner(sender, alternative_owner, [Role::Owner])?;
+            alternative_owner
+        } else {
+            sender
+        };
+
+        self.verify_acl(owner, key.clone(), None)?;
+
+        let mut grants = meta.grants.clone().unwrap_or_default();
+        f(&mut grants);
+        grants.retain(|_, permissions| !permissions.is_empty());
+
+        let meta = KvStoreMetadata {
+            grants: if grants.is_empty() {
+                None
+            } else {
+                Some(grants)
+            },
+            ..meta
+        };
+        self.storage.update_metadata(&meta, &key)
+    }
+
+    /// Allow an address to modify a key.
+    pub fn grant(&mut self, sender: &Address, args: GrantArgs) -> Result<GrantReturns, ManyError> {
+        if args.address.is_anonymous() {
+            return Err(error::anon_alt_denied());
+        }
+
+        let GrantArgs {
+            key,
+            address,
+            permissions,
+            alternative_owner,
+        } = args;
+        self.update_grants(sender, key.into(), alternative_owner, |grants| {
+            grants.entry(address).or_default().extend(permissions);
+        })?;
+        Ok(GrantReturns {})
+    }
+
+    /// Revoke permissions given with `grant`.
+    pub fn revoke(
+        &mut self,
+        sender: &Address,
+        args: RevokeArgs,
+    ) -> Result<RevokeReturns, ManyError> {
+        let RevokeArgs {
+            key,
+            address,
+            permissions,
+            alternative_owner,
+        } = args;
+        self.update_grants(sender, key.into(), alternative_owner, |grants| {
+            if let Some(permissions) = permissions {
+                if let Some(granted) = grants.get_mut(&address) {
+                    granted.retain(|p| !permissions.contains(p));
+                }
+            } else {
+                grants.remove(&address);
+            }
+        })?;
+        Ok(RevokeReturns {})
+    }
+
+    /// The grants of a key.
+    pub fn grants(&self, args: GrantsArgs) -> Result<GrantsReturns, ManyError> {
+        let meta = self.live_metadata(&args.key)?;
+        Ok(GrantsReturns {
+            grants: meta.grants.unwrap_or_default(),
+        })
+    }
+}
+
+/// The `kvstore.grant` and `kvstore.revoke` endpoints.
+pub struct KvStoreGrantModule {
+    info: ManyModuleInfo,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl KvStoreGrantModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            info: ManyModuleInfo {
+                name: "KvStoreGrantModule".to_string(),
+                attribute: None,
+                endpoints: vec![GRANT_ENDPOINT.to_string(), REVOKE_ENDPOINT.to_string()],
+            },
+            module_impl,
+        }
+    }
+}
+
+impl Debug for KvStoreGrantModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KvStoreGrantModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for KvStoreGrantModule {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, _envelope: &CoseSign1) -> Result<(), ManyError> {
+        let data = message.data.as_slice();
+        match message.method.as_str() {
+            GRANT_ENDPOINT => minicbor::decode::<GrantArgs>(data).map(|_| ()),
+            REVOKE_ENDPOINT => minicbor::decode::<RevokeArgs>(data).map(|_| ()),
+            _ => return Err(ManyError::invalid_method_name(message.method.clone())),
+        }
+        .map_err(ManyError::deserialization_error)
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let from = message.from();
+        let data = match message.method.as_str() {
+            GRANT_ENDPOINT => {
+                let args: GrantArgs =
+                    minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+                self.module_impl
+                    .lock()
+                    .unwrap()
+                    .grant(&from, args)
+                    .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error))
+            }
+            REVOKE_ENDPOINT => {
+                let args: RevokeArgs =
+                    minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+                self.module_impl
+                    .lock()
+                    .unwrap()
+                    .revoke(&from, args)
+                    .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error))
+            }
+            _ => return Err(ManyError::invalid_method_name(message.method.clone())),
+        };
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
+
+/// The `kvstore.grants` endpoint.
+pub struct KvStoreGrantsModule {
+    info: ManyModuleInfo,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl KvStoreGrantsModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            info: ManyModuleInfo {
+                name: "KvStoreGrantsModule".to_string(),
+                attribute: None,
+                endpoints: vec![GRANTS_ENDPOINT.to_string()],
+            },
+            module_impl,
+        }
+    }
+}
+
+impl Debug for KvStoreGrantsModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KvStoreGrantsModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for KvStoreGrantsModule {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, _envelope: &CoseSign1) -> Result<(), ManyError> {
+        minicbor::decode::<GrantsArgs>(&message.data).map_err(ManyError::deserialization_error)?;
+        Ok(())
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let args: GrantsArgs =
+            minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+        let data = self
+            .module_impl
+            .lock()
+            .unwrap()
+            .grants(args)
+            .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error));
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
@@ -0,0 +1,153 @@
+use crate::module::KvStoreModuleImpl;
+use crate::storage::list::KeyBounds;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use many_types::{CborRange, SortOrder};
+use minicbor::bytes::ByteVec;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+pub const LIST_ENDPOINT: &str = "kvstore.list";
+
+/// The maximum number of keys returned by a single `kvstore.list` call.
+const MAXIMUM_KEY_COUNT: u64 = 100;
+
+#[derive(Clone, Debug, Default, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct ListArgs {
+    /// Only list the keys starting with this prefix.
+    #[n(0)]
+    pub prefix: Option<ByteVec>,
+
+    /// Only list the keys within this range.
+    #[n(1)]
+    pub range: Option<CborRange<ByteVec>>,
+
+    #[n(2)]
+    pub order: Option<SortOrder>,
+
+    /// The maximum number of keys to return, at most 100.
+    #[n(3)]
+    pub count: Option<u64>,
+
+    /// Continue a previous listing, after this key. This is the `next` key
+    /// returned by the previous call, with the same arguments.
+    #[n(4)]
+    pub after: Option<ByteVec>,
+
+    /// Only list the keys owned by this address.
+    #[n(5)]
+    pub owner: Option<Address>,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct ListReturns {
+    #[n(0)]
+    pub keys: Vec<ByteVec>,
+
+    /// If there are more keys to list, the key to pass as `after` to continue.
+    #[n(1)]
+    pub next: Option<ByteVec>,
+}
+
+impl KvStoreModuleImpl {
+    /// List the keys of the store, skipping disabled and expired keys.
+    pub fn list_keys(&self, args: ListArgs) -> Result<ListReturns, ManyError> {
+        let order = args.order.unwrap_or(SortOrder::Indeterminate);
+        let count = args
+            .count
+            .map_or(MAXIMUM_KEY_COUNT, |c| c.min(MAXIMUM_KEY_COUNT)) as usize;
+
+        let mut bounds = KeyBounds::default();
+        if let Some(prefix) = &args.prefix {
+            bounds = bounds.with_prefix(prefix.as_slice());
+        }
+        if let Some(range) = &args.range {
+            bounds = bounds.with_range(range);
+        }
+        if let Some(after) = &args.after {
+            bounds = bounds.after(after.as_slice(), order);
+        }
+
+        let mut keys: Vec<ByteVec> = Vec::new();
+        let mut next = None;
+        for item in self.storage.list_keys(bounds, order) {
+            let item = item?;
+            if item.is_disabled() {
+                continue;
+            }
+            if let Some(meta) = &item.metadata {
+                if self.storage.is_expired(meta)? {
+                    continue;
+                }
+            }
+            if let Some(owner) = &args.owner {
+                if item.metadata.as_ref().map(|m| &m.owner) != Some(owner) {
+                    continue;
+                }
+            }
+
+            if keys.len() == count {
+                next = keys.last().cloned();
+                break;
+            }
+            keys.push(item.key.into());
+        }
+
+        Ok(ListReturns { keys, next })
+    }
+}
+
+/// The `kvstore.list` endpoint.
+pub struct KvStoreListModule {
+    info: ManyModuleInfo,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl KvStoreListModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            info: ManyModuleInfo {
+                name: "KvStoreListModule".to_string(),
+                attribute: None,
+                endpoints: vec![LIST_ENDPOINT.to_string()],
+            },
+            module_impl,
+        }
+    }
+}
+
+impl Debug for KvStoreListModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KvStoreListModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for KvStoreListModule {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, _envelope: &CoseSign1) -> Result<(), ManyError> {
+        minicbor::decode::<ListArgs>(&message.data).map_err(ManyError::deserialization_error)?;
+        Ok(())
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let args: ListArgs =
+            minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+        let data = self
+            .module_impl
+            .lock()
+            .unwrap()
+            .list_keys(args)
+            .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error));
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
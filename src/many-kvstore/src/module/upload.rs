@@ -0,0 +1,300 @@
+use crate::error;
+use crate::module::grant::KeyPermission;
+use crate::module::KvStoreModuleImpl;
+use crate::storage::chunk::Upload;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::account::Role;
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use many_types::Timestamp;
+use minicbor::bytes::ByteVec;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+pub const BEGIN_UPLOAD_ENDPOINT: &str = "kvstore.beginUpload";
+pub const PUT_CHUNK_ENDPOINT: &str = "kvstore.putChunk";
+pub const FINALIZE_UPLOAD_ENDPOINT: &str = "kvstore.finalizeUpload";
+pub const ABORT_UPLOAD_ENDPOINT: &str = "kvstore.abortUpload";
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct BeginUploadArgs {
+    #[n(0)]
+    pub key: ByteVec,
+
+    /// The size of the whole value.
+    #[n(1)]
+    pub size: u64,
+
+    /// The SHA3-256 hash of the whole value.
+    #[n(2)]
+    pub hash: ByteVec,
+
+    #[n(3)]
+    pub alternative_owner: Option<Address>,
+
+    #[n(4)]
+    pub expires: Option<Timestamp>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct BeginUploadReturns {
+    #[n(0)]
+    pub upload: u64,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct PutChunkArgs {
+    #[n(0)]
+    pub upload: u64,
+
+    /// The index of the chunk. Chunks must be sent in order.
+    #[n(1)]
+    pub index: u32,
+
+    #[n(2)]
+    pub data: ByteVec,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct PutChunkReturns {
+    /// The SHA3-256 hash of the chunk.
+    #[n(0)]
+    pub hash: ByteVec,
+}
+
+/// The arguments of `kvstore.finalizeUpload` and `kvstore.abortUpload`.
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct UploadArgs {
+    #[n(0)]
+    pub upload: u64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct FinalizeUploadReturns {
+    /// The version of the key.
+    #[n(0)]
+    pub version: u64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct AbortUploadReturns {}
+
+impl KvStoreModuleImpl {
+    /// Start uploading a value in chunks, for values too large for a single
+    /// `kvstore.put`. The size of the value counts against the usage of the
+    /// owner until the upload is finalized or aborted, or until it is
+    /// abandoned after `UPLOAD_TIMEOUT` seconds.
+    pub fn begin_upload(
+        &mut self,
+        sender: &Address,
+        args: BeginUploadArgs,
+    ) -> Result<BeginUploadReturns, ManyError> {
+        if sender.is_anonymous() {
+            return Err(ManyError::invalid_identity());
+        }
+        let key: Vec<u8> = args.key.into();
+        let owner = if let Some(alternative_owner) = args.alternative_owner {
+            self.validate_alternative_owner(
+                sender,
+                &alternative_owner,
+                [Role::CanKvStorePut, Role::Owner],
+            )?;
+            alternative_owner
+        } else {
+            *sender
+        };
+
+        self.verify_acl(&owner, key.clone(), Some(KeyPermission::Put))?;
+
+        if args.hash.len() != 32 {
+            return Err(error::invalid_hash());
+        }
+        self.storage.check_value_size(args.size)?;
+        if let Some(expires) = &args.expires {
+            self.storage.check_expiry(expires)?;
+        }
+
+        let upload = self.storage.begin_upload(&Upload {
+            key: key.into(),
+            sender: *sender,
+            owner,
+            expires: args.expires,
+            size: args.size,
+            hash: args.hash,
+            chunks: vec![],
+            received: 0,
+            started: self.storage.now(),
+        })?;
+        Ok(BeginUploadReturns { upload })
+    }
+
+    /// An upload started by the sender.
+    fn sender_upload(&self, sender: &Address, id: u64) -> Result<Upload, ManyError> {
+        let upload = self
+            .storage
+            .get_upload(id)?
+            .ok_or_else(|| error::upload_not_found(id))?;
+        if &upload.sender != sender {
+            return Err(error::permission_denied());
+        }
+        Ok(upload)
+    }
+
+    pub fn put_chunk(
+        &mut self,
+        sender: &Address,
+        args: PutChunkArgs,
+    ) -> Result<PutChunkReturns, ManyError> {
+        let upload = self.sender_upload(sender, args.upload)?;
+        if args.index as usize != upload.chunks.len() {
+            return Err(error::chunk_out_of_order(upload.chunks.len()));
+        }
+        let received = upload.received + args.data.len() as u64;
+        if received > upload.size {
+            return Err(error::upload_size_mismatch(upload.size, received));
+        }
+
+        let hash = self
+            .storage
+            .put_chunk(args.upload, upload, args.data.into())?;
+        Ok(PutChunkReturns { hash })
+    }
+
+    /// Put the uploaded value at its key, once all the chunks were sent.
+    pub fn finalize_upload(
+        &mut self,
+        sender: &Address,
+        args: UploadArgs,
+    ) -> Result<FinalizeUploadReturns, ManyError> {
+        let upload = self.sender_upload(sender, args.upload)?;
+        if upload.received != upload.size {
+            return Err(error::upload_size_mismatch(upload.size, upload.received));
+        }
+
+        // The key may have changed since the upload started.
+        self.verify_acl(&upload.owner, upload.key.to_vec(), Some(KeyPermission::Put))?;
+        if let Some(expires) = &upload.expires {
+            self.storage.check_expiry(expires)?;
+        }
+
+        let meta = self.metadata_for_put(upload.owner, &upload.key, upload.expires)?;
+        let version = self.storage.finalize_upload(args.upload, upload, meta)?;
+        Ok(FinalizeUploadReturns { version })
+    }
+
+    /// Drop an upload and the chunks sent.
+    pub fn abort_upload(
+        &mut self,
+        sender: &Address,
+        args: UploadArgs,
+    ) -> Result<AbortUploadReturns, ManyError> {
+        let upload = self.sender_upload(sender, args.upload)?;
+        self.storage.abort_upload(args.upload, &upload)?;
+        Ok(AbortUploadReturns {})
+    }
+}
+
+/// The `kvstore.beginUpload`, `kvstore.putChunk`, `kvstore.finalizeUpload`
+/// and `kvstore.abortUpload` endpoints.
+pub struct KvStoreUploadModule {
+    info: ManyModuleInfo,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl KvStoreUploadModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            info: ManyModuleInfo {
+                name: "KvStoreUploadModule".to_string(),
+                attribute: None,
+                endpoints: vec![
+                    BEGIN_UPLOAD_ENDPOINT.to_string(),
+                    PUT_CHUNK_ENDPOINT.to_string(),
+                    FINALIZE_UPLOAD_ENDPOINT.to_string(),
+                    ABORT_UPLOAD_ENDPOINT.to_string(),
+                ],
+            },
+            module_impl,
+        }
+    }
+}
+
+impl Debug for KvStoreUploadModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KvStoreUploadModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for KvStoreUploadModule {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, _envelope: &CoseSign1) -> Result<(), ManyError> {
+        let data = message.data.as_slice();
+        match message.method.as_str() {
+            BEGIN_UPLOAD_ENDPOINT => minicbor::decode::<BeginUploadArgs>(data).map(|_| ()),
+            PUT_CHUNK_ENDPOINT => minicbor::decode::<PutChunkArgs>(data).map(|_| ()),
+            FINALIZE_UPLOAD_ENDPOINT | ABORT_UPLOAD_ENDPOINT => {
+                minicbor::decode::<UploadArgs>(data).map(|_| ())
+            }
+            _ => return Err(ManyError::invalid_method_name(message.method.clone())),
+        }
+        .map_err(ManyError::deserialization_error)
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let from = message.from();
+        let data = match message.method.as_str() {
+            BEGIN_UPLOAD_ENDPOINT => {
+                let args: BeginUploadArgs =
+                    minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+                self.module_impl
+                    .lock()
+                    .unwrap()
+                    .begin_upload(&from, args)
+                    .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error))
+            }
+            PUT_CHUNK_ENDPOINT => {
+                let args: PutChunkArgs =
+                    minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+                self.module_impl
+                    .lock()
+                    .unwrap()
+                    .put_chunk(&from, args)
+                    .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error))
+            }
+            FINALIZE_UPLOAD_ENDPOINT => {
+                let args: UploadArgs =
+                    minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+                self.module_impl
+                    .lock()
+                    .unwrap()
+                    .finalize_upload(&from, args)
+                    .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error))
+            }
+            ABORT_UPLOAD_ENDPOINT => {
+                let args: UploadArgs =
+                    minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+                self.module_impl
+                    .lock()
+                    .unwrap()
+                    .abort_upload(&from, args)
+                    .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error))
+            }
+            _ => return Err(ManyError::invalid_method_name(message.method.clone())),
+        };
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
@@ -0,0 +1,97 @@
+use crate::module::KvStoreModuleImpl;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+pub const USAGE_ENDPOINT: &str = "kvstore.usage";
+
+#[derive(Clone, Debug, Default, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct UsageArgs {
+    /// The owner to get the usage of, or the sender.
+    #[n(0)]
+    pub owner: Option<Address>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct UsageReturns {
+    /// The number of bytes used by the values of the keys of the owner.
+    #[n(0)]
+    pub usage: u64,
+
+    /// The maximum number of bytes the owner can use, if there is one.
+    #[n(1)]
+    pub quota: Option<u64>,
+
+    /// The maximum size of a value, if there is one.
+    #[n(2)]
+    pub max_value_size: Option<u64>,
+}
+
+impl KvStoreModuleImpl {
+    /// The storage used by an owner, and the limits of the store.
+    pub fn usage(&self, sender: &Address, args: UsageArgs) -> Result<UsageReturns, ManyError> {
+        let owner = args.owner.unwrap_or(*sender);
+        let limits = self.storage.limits();
+        Ok(UsageReturns {
+            usage: self.storage.get_usage(&owner)?,
+            quota: limits.owner_quota,
+            max_value_size: limits.max_value_size,
+        })
+    }
+}
+
+/// The `kvstore.usage` endpoint.
+pub struct KvStoreUsageModule {
+    info: ManyModuleInfo,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl KvStoreUsageModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            info: ManyModuleInfo {
+                name: "KvStoreUsageModule".to_string(),
+                attribute: None,
+                endpoints: vec![USAGE_ENDPOINT.to_string()],
+            },
+            module_impl,
+        }
+    }
+}
+
+impl Debug for KvStoreUsageModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KvStoreUsageModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for KvStoreUsageModule {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, _envelope: &CoseSign1) -> Result<(), ManyError> {
+        minicbor::decode::<UsageArgs>(&message.data).map_err(ManyError::deserialization_error)?;
+        Ok(())
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let args: UsageArgs =
+            minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+        let data = self
+            .module_impl
+            .lock()
+            .unwrap()
+            .usage(&message.from(), args)
+            .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error));
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
@@ -0,0 +1,265 @@
+use crate::error;
+use crate::module::grant::KeyPermission;
+use crate::module::{KvStoreMetadata, KvStoreModuleImpl};
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::account::Role;
+use many_modules::{kvstore, ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use many_types::{Either, Timestamp};
+use minicbor::bytes::ByteVec;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+/// The arguments of `kvstore.get`, which can read a previous version of the
+/// key.
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct GetArgs {
+    #[n(0)]
+    pub key: ByteVec,
+
+    /// The version to read, instead of the current value.
+    #[n(1)]
+    pub version: Option<u64>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct GetReturns {
+    #[n(0)]
+    pub value: Option<ByteVec>,
+
+    /// The version of the value, 0 if the key has no version.
+    #[n(1)]
+    pub version: u64,
+}
+
+/// The arguments of `kvstore.put`, which can be conditional on the current
+/// version of the key.
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct PutArgs {
+    #[n(0)]
+    pub key: ByteVec,
+
+    #[n(1)]
+    pub value: ByteVec,
+
+    #[n(2)]
+    pub alternative_owner: Option<Address>,
+
+    /// Only put the value if the key is at this version. Version 0 means the
+    /// key must not exist yet.
+    #[n(3)]
+    pub if_version: Option<u64>,
+
+    /// When the key expires, if it should.
+    #[n(4)]
+    pub expires: Option<Timestamp>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct PutReturns {
+    /// The version of the value that was put.
+    #[n(0)]
+    pub version: u64,
+}
+
+impl KvStoreModuleImpl {
+    /// Get the current value of a key, or one of its previous versions.
+    pub fn get_versioned(&self, args: GetArgs) -> Result<GetReturns, ManyError> {
+        match args.version {
+            None => {
+                let value = self.storage.get(&args.key)?;
+                let version = self.storage.get_version(&args.key)?;
+                Ok(GetReturns {
+                    value: value.map(|x| x.into()),
+                    version,
+                })
+            }
+            Some(version) => {
+                let value = self
+                    .storage
+                    .get_at_version(&args.key, version)?
+                    .ok_or_else(|| error::version_not_found(version))?;
+                Ok(GetReturns {
+                    value: Some(value.into()),
+                    version,
+                })
+            }
+        }
+    }
+
+    /// The metadata of a key after a put by `sender`. A key which exists keeps
+    /// its owner and grants, so a grantee does not take it over.
+    pub(crate) fn metadata_for_put(
+        &self,
+        sender: Address,
+        key: &[u8],
+        expires: Option<Timestamp>,
+    ) -> Result<KvStoreMetadata, ManyError> {
+        let (owner, grants) = match self.storage.get_decoded_metadata(key)? {
+            Some(meta) if !self.storage.is_expired(&meta)? => (meta.owner, meta.grants),
+            _ => (sender, None),
+        };
+        Ok(KvStoreMetadata {
+            owner,
+            disabled: Some(Either::Left(false)),
+            expires,
+            grants,
+        })
+    }
+
+    /// Put a value, returning its version. Fails if `if_version` is given and
+    /// the key is at another version.
+    pub fn put_versioned(
+        &mut self,
+        sender: &Address,
+        args: PutArgs,
+    ) -> Result<PutReturns, ManyError> {
+        let key: Vec<u8> = args.key.into();
+        let owner = if let Some(alternative_owner) = args.alternative_owner {
+            self.validate_alternative_owner(
+                sender,
+                &alternative_owner,
+                [Role::CanKvStorePut, Role::Owner],
+            )?;
+            alternative_owner
+        } else {
+            *sender
+        };
+
+        self.verify_acl(&owner, key.clone(), Some(KeyPermission::Put))?;
+
+        if let Some(expires) = &args.expires {
+            self.storage.check_expiry(expires)?;
+        }
+        if let Some(expected) = args.if_version {
+            let actual = self.storage.get_version(&key)?;
+            if expected != actual {
+                return Err(error::version_mismatch(expected, actual));
+            }
+        }
+
+        let meta = self.metadata_for_put(owner, &key, args.expires)?;
+        let version = self.storage.put(&meta, &key, args.value.into())?;
+        Ok(PutReturns { version })
+    }
+}
+
+/// The `kvstore.info`, `kvstore.get` and `kvstore.query` endpoints, where
+/// `kvstore.get` also returns the version of the value and can read previous
+/// versions.
+pub struct VersionedKvStoreModule {
+    inner: kvstore::KvStoreModule<KvStoreModuleImpl>,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl VersionedKvStoreModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            inner: kvstore::KvStoreModule::new(module_impl.clone()),
+            module_impl,
+        }
+    }
+}
+
+impl Debug for VersionedKvStoreModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("VersionedKvStoreModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for VersionedKvStoreModule {
+    fn info(&self) -> &ManyModuleInfo {
+        self.inner.info()
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        match message.method.as_str() {
+            "kvstore.get" => {
+                minicbor::decode::<GetArgs>(&message.data)
+                    .map_err(ManyError::deserialization_error)?;
+                Ok(())
+            }
+            _ => self.inner.validate(message, envelope),
+        }
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        match message.method.as_str() {
+            "kvstore.get" => {
+                let args: GetArgs =
+                    minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+                let data = self
+                    .module_impl
+                    .lock()
+                    .unwrap()
+                    .get_versioned(args)
+                    .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error));
+                Ok(ResponseMessage::from_request(&message, &message.to, data))
+            }
+            _ => self.inner.execute(message).await,
+        }
+    }
+}
+
+/// The `kvstore.put` and `kvstore.disable` endpoints, where `kvstore.put`
+/// returns the version of the value and can be conditional on the current
+/// version.
+pub struct VersionedKvStoreCommandsModule {
+    inner: kvstore::KvStoreCommandsModule<KvStoreModuleImpl>,
+    module_impl: Arc<Mutex<KvStoreModuleImpl>>,
+}
+
+impl VersionedKvStoreCommandsModule {
+    pub fn new(module_impl: Arc<Mutex<KvStoreModuleImpl>>) -> Self {
+        Self {
+            inner: kvstore::KvStoreCommandsModule::new(module_impl.clone()),
+            module_impl,
+        }
+    }
+}
+
+impl Debug for VersionedKvStoreCommandsModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("VersionedKvStoreCommandsModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for VersionedKvStoreCommandsModule {
+    fn info(&self) -> &ManyModuleInfo {
+        self.inner.info()
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        // The generated validation ignores the additional arguments.
+        self.inner.validate(message, envelope)?;
+        if message.method == "kvstore.put" {
+            minicbor::decode::<PutArgs>(&message.data).map_err(ManyError::deserialization_error)?;
+        }
+        Ok(())
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        match message.method.as_str() {
+            "kvstore.put" => {
+                let args: PutArgs =
+                    minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+                let data = self
+                    .module_impl
+                    .lock()
+                    .unwrap()
+                    .put_versioned(&message.from(), args)
+                    .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error));
+                Ok(ResponseMessage::from_request(&message, &message.to, data))
+            }
+            _ => self.inner.execute(message).await,
+        }
+    }
+}
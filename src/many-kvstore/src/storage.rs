@@ -10,14 +10,38 @@ use std::collections::BTreeMap;
 use std::path::Path;
 
 mod account;
+pub mod batch;
+pub mod chunk;
 mod event;
+pub mod expiry;
+pub mod list;
+pub mod usage;
+pub mod version;
 
 use crate::error;
+use batch::KeyOperation;
 use event::EventId;
+use usage::{KvStoreLimits, KVSTORE_LIMITS_KEY};
 
 const KVSTORE_ROOT: &[u8] = b"s";
 const KVSTORE_ACL_ROOT: &[u8] = b"a";
 
+/// Whether put events hold the hash of values, set when the store is created.
+const HASH_EVENT_VALUES_KEY: &[u8] = b"/config/hash_event_values";
+
+/// Decode a number stored as a big-endian `u64`, e.g. a height or a version.
+/// `name` describes it in the error.
+fn decode_u64(name: &str, value: &[u8]) -> Result<u64, ManyError> {
+    <[u8; 8]>::try_from(value)
+        .map(u64::from_be_bytes)
+        .map_err(|_| error::storage_get_failed(format!("Invalid {name}: {}", hex::encode(value))))
+}
+
+/// Decode the height, stored as a big-endian `u64`.
+fn decode_height(value: &[u8]) -> Result<u64, ManyError> {
+    decode_u64("height", value)
+}
+
 #[derive(Serialize, Deserialize, Debug, Eq, Ord, PartialEq, PartialOrd)]
 #[serde(transparent)]
 pub struct Key {
@@ -35,6 +59,13 @@ pub struct KvStoreStorage {
     /// persistent store.
     blockchain: bool,
 
+    /// When this is true, `KvStorePut` events contain the SHA3-256 hash of the
+    /// value instead of the value itself. Events are part of the state, so all
+    /// nodes of a chain must agree on it.
+    hash_event_values: bool,
+
+    limits: KvStoreLimits,
+
     latest_event_id: EventId,
     current_time: Option<Timestamp>,
     current_hash: Option<Vec<u8>>,
@@ -53,6 +84,25 @@ impl KvStoreStorage {
     pub fn set_time(&mut self, time: Timestamp) {
         self.current_time = Some(time);
     }
+    /// Set whether put events hold the hash of values and commit it. Like
+    /// `set_limits`, this is only done when the storage is created.
+    pub fn set_hash_event_values(&mut self, hash_event_values: bool) -> Result<(), ManyError> {
+        self.persistent_store
+            .apply(&[(
+                HASH_EVENT_VALUES_KEY.to_vec(),
+                Op::Put(vec![hash_event_values as u8]),
+            )])
+            .map_err(error::storage_apply_failed)?;
+        self.persistent_store
+            .commit(&[])
+            .map_err(error::storage_apply_failed)?;
+        self.hash_event_values = hash_event_values;
+        Ok(())
+    }
+    #[inline]
+    pub fn hash_event_values(&self) -> bool {
+        self.hash_event_values
+    }
     #[inline]
     pub fn now(&self) -> Timestamp {
         self.current_time.unwrap_or_else(Timestamp::now)
@@ -71,37 +121,53 @@ impl KvStoreStorage {
         self.root_identity.with_subresource_id(current_id)
     }
 
+    /// Load an existing storage. A block is committed in a single merk commit,
+    /// so the storage is always at the end of a block; a block lost in a crash
+    /// is detected by the ABCI frontend, which compares the height and hash
+    /// with tendermint.
     pub fn load<P: AsRef<Path>>(persistent_path: P, blockchain: bool) -> Result<Self, String> {
         let persistent_store = merk::Merk::open(persistent_path).map_err(|e| e.to_string())?;
+        let get = |key: &[u8]| {
+            persistent_store.get(key).map_err(|e| {
+                let key = String::from_utf8_lossy(key);
+                format!("Could not read '{key}' from storage: {e}")
+            })
+        };
 
-        let next_subresource = persistent_store
-            .get(b"/config/subresource_id")
-            .unwrap()
-            .map_or(0, |x| {
-                let mut bytes = [0u8; 4];
-                bytes.copy_from_slice(x.as_slice());
-                u32::from_be_bytes(bytes)
-            });
-
-        let root_identity: Address = Address::from_bytes(
-            &persistent_store
-                .get(b"/config/identity")
-                .expect("Could not open storage.")
-                .expect("Could not find key '/config/identity' in storage."),
+        get(b"/height")?
+            .map_or(Ok(0), |x| decode_height(&x))
+            .map_err(|e| e.to_string())?;
+
+        let next_subresource = get(b"/config/subresource_id")?.map_or(Ok(0), |x| {
+            <[u8; 4]>::try_from(x.as_slice())
+                .map(u32::from_be_bytes)
+                .map_err(|_| "Invalid value for '/config/subresource_id' in storage.".to_string())
+        })?;
+
+        let root_identity = Address::from_bytes(
+            &get(b"/config/identity")?
+                .ok_or("Could not find key '/config/identity' in storage.")?,
         )
         .map_err(|e| e.to_string())?;
 
         let latest_event_id = minicbor::decode(
-            &persistent_store
-                .get(b"/latest_event_id")
-                .expect("Could not open storage.")
-                .expect("Could not find key '/latest_event_id'"),
+            &get(b"/latest_event_id")?
+                .ok_or("Could not find key '/latest_event_id' in storage.")?,
         )
         .map_err(|e| e.to_string())?;
 
+        let hash_event_values = get(HASH_EVENT_VALUES_KEY)?.map_or(false, |x| x == [1]);
+        let limits = get(KVSTORE_LIMITS_KEY)?
+            .map(|x| minicbor::decode(&x))
+            .transpose()
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+
         Ok(Self {
             persistent_store,
             blockchain,
+            hash_event_values,
+            limits,
             current_time: None,
             current_hash: None,
             latest_event_id,
@@ -116,8 +182,35 @@ impl KvStoreStorage {
         persistent_path: P,
         blockchain: bool,
     ) -> Result<Self, String> {
-        let mut persistent_store = merk::Merk::open(persistent_path).map_err(|e| e.to_string())?;
+        let mut storage = Self::open(persistent_path, blockchain)?;
+        storage.init(acl, identity)?;
+        Ok(storage)
+    }
+
+    /// Open an empty storage, which will be initialized later from the genesis
+    /// of the chain.
+    pub fn open<P: AsRef<Path>>(persistent_path: P, blockchain: bool) -> Result<Self, String> {
+        let persistent_store = merk::Merk::open(persistent_path).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            persistent_store,
+            blockchain,
+            hash_event_values: false,
+            limits: KvStoreLimits::default(),
+            current_time: None,
+            current_hash: None,
+            latest_event_id: EventId::from(vec![0]),
+            next_subresource: 0,
+            root_identity: Address::anonymous(),
+        })
+    }
 
+    pub fn is_initialized(&self) -> bool {
+        matches!(self.persistent_store.get(b"/config/identity"), Ok(Some(_)))
+    }
+
+    /// Write the initial state in an empty storage, and commit it.
+    pub fn init(&mut self, acl: AclMap, identity: Address) -> Result<(), String> {
         let mut batch: Vec<BatchEntry> = Vec::new();
 
         batch.push((b"/config/identity".to_vec(), Op::Put(identity.to_vec())));
@@ -130,29 +223,26 @@ impl KvStoreStorage {
             ));
         }
 
-        persistent_store
+        self.persistent_store
             .apply(batch.as_slice())
             .map_err(|e| e.to_string())?;
 
-        let latest_event_id = EventId::from(vec![0]);
-        persistent_store
+        self.latest_event_id = EventId::from(vec![0]);
+        self.persistent_store
             .apply(&[(
                 b"/latest_event_id".to_vec(),
-                Op::Put(minicbor::to_vec(&latest_event_id).expect("Unable to encode event id")),
+                Op::Put(
+                    minicbor::to_vec(&self.latest_event_id).expect("Unable to encode event id"),
+                ),
             )])
             .unwrap();
 
-        persistent_store.commit(&[]).map_err(|e| e.to_string())?;
+        self.persistent_store
+            .commit(&[])
+            .map_err(|e| e.to_string())?;
 
-        Ok(Self {
-            persistent_store,
-            blockchain,
-            current_time: None,
-            current_hash: None,
-            latest_event_id,
-            next_subresource: 0,
-            root_identity: identity,
-        })
+        self.root_identity = identity;
+        Ok(())
     }
 
     fn inc_height(&mut self) -> u64 {
@@ -167,17 +257,18 @@ impl KvStoreStorage {
     }
 
     pub fn get_height(&self) -> u64 {
+        // The height was checked when loading the storage.
         self.persistent_store
             .get(b"/height")
             .unwrap()
-            .map_or(0u64, |x| {
-                let mut bytes = [0u8; 8];
-                bytes.copy_from_slice(x.as_slice());
-                u64::from_be_bytes(bytes)
-            })
+            .map_or(0u64, |x| decode_height(&x).unwrap())
     }
 
     pub fn commit(&mut self) -> AbciCommitInfo {
+        self.remove_expired()
+            .expect("Could not remove the expired keys");
+        self.remove_abandoned_uploads()
+            .expect("Could not remove the abandoned uploads");
         let _ = self.inc_height();
         self.persistent_store
             .apply(&[(
@@ -211,69 +302,60 @@ impl KvStoreStorage {
             .map_err(|e| ManyError::unknown(e.to_string()))
     }
 
-    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ManyError> {
-        if let Some(cbor) = self._get(key, KVSTORE_ACL_ROOT)? {
-            let meta: KvStoreMetadata = minicbor::decode(&cbor)
-                .map_err(|e| ManyError::deserialization_error(e.to_string()))?;
-
-            if let Some(either) = meta.disabled {
+    /// Fail if the key was disabled by its owner, and return whether it is
+    /// still available (it did not expire).
+    fn check_available(&self, key: &[u8]) -> Result<bool, ManyError> {
+        if let Some(meta) = self.get_decoded_metadata(key)? {
+            if let Some(either) = &meta.disabled {
                 match either {
                     Either::Left(false) => {}
                     _ => return Err(error::key_disabled()),
                 }
             }
+            return Ok(!self.is_expired(&meta)?);
+        }
+        Ok(true)
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ManyError> {
+        if !self.check_available(key)? {
+            return Ok(None);
         }
-        self._get(key, KVSTORE_ROOT)
+        self._get(key, KVSTORE_ROOT)?
+            .map(|value| self.reassemble(key, self.get_version(key)?, value))
+            .transpose()
     }
 
     pub fn get_metadata(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ManyError> {
         self._get(key, KVSTORE_ACL_ROOT)
     }
 
+    pub fn get_decoded_metadata(&self, key: &[u8]) -> Result<Option<KvStoreMetadata>, ManyError> {
+        self.get_metadata(key)?
+            .map(|cbor| minicbor::decode(&cbor))
+            .transpose()
+            .map_err(|e| ManyError::deserialization_error(e.to_string()))
+    }
+
+    /// Put a value, returning its version.
     pub fn put(
         &mut self,
         meta: &KvStoreMetadata,
         key: &[u8],
         value: Vec<u8>,
-    ) -> Result<(), ManyError> {
-        self.persistent_store
-            .apply(&[
-                (
-                    vec![KVSTORE_ACL_ROOT.to_vec(), key.to_vec()].concat(),
-                    Op::Put(
-                        minicbor::to_vec(meta)
-                            .map_err(|e| ManyError::serialization_error(e.to_string()))?,
-                    ),
-                ),
-                (
-                    vec![KVSTORE_ROOT.to_vec(), key.to_vec()].concat(),
-                    Op::Put(value.clone()),
-                ),
-            ])
-            .map_err(|e| ManyError::unknown(e.to_string()))?;
-
-        self.log_event(EventInfo::KvStorePut {
-            key: key.to_vec().into(),
-            value: value.into(),
-            owner: meta.owner,
-        });
-
-        if !self.blockchain {
-            self.persistent_store.commit(&[]).unwrap();
-        }
-        Ok(())
+    ) -> Result<u64, ManyError> {
+        let versions = self.apply_operations(vec![(
+            key.to_vec(),
+            KeyOperation::Put {
+                meta: meta.clone(),
+                value,
+            },
+        )])?;
+        Ok(versions[0])
     }
 
     pub fn disable(&mut self, meta: &KvStoreMetadata, key: &[u8]) -> Result<(), ManyError> {
-        self.persistent_store
-            .apply(&[(
-                vec![KVSTORE_ACL_ROOT.to_vec(), key.to_vec()].concat(),
-                Op::Put(
-                    minicbor::to_vec(meta)
-                        .map_err(|e| ManyError::serialization_error(e.to_string()))?,
-                ),
-            )])
-            .map_err(|e| ManyError::unknown(e.to_string()))?;
+        self.put_metadata(meta, key)?;
 
         let reason = if let Some(disabled) = &meta.disabled {
             match disabled {
@@ -295,6 +377,23 @@ impl KvStoreStorage {
         Ok(())
     }
 
+    /// Write the metadata of a key, e.g. when it is enabled again or its grants
+    /// change. This logs no event.
+    pub fn update_metadata(&mut self, meta: &KvStoreMetadata, key: &[u8]) -> Result<(), ManyError> {
+        self.put_metadata(meta, key)?;
+
+        if !self.blockchain {
+            self.persistent_store.commit(&[]).unwrap();
+        }
+        Ok(())
+    }
+
+    /// Remove the value and the metadata of a key.
+    pub fn delete(&mut self, key: &[u8]) -> Result<(), ManyError> {
+        self.apply_operations(vec![(key.to_vec(), KeyOperation::Delete)])?;
+        Ok(())
+    }
+
     pub fn transfer(
         &mut self,
         key: &[u8],
@@ -302,15 +401,7 @@ impl KvStoreStorage {
         meta: KvStoreMetadata,
     ) -> Result<(), ManyError> {
         let new_owner = meta.owner;
-        self.persistent_store
-            .apply(&[(
-                vec![KVSTORE_ACL_ROOT.to_vec(), key.to_vec()].concat(),
-                Op::Put(
-                    minicbor::to_vec(meta)
-                        .map_err(|e| ManyError::serialization_error(e.to_string()))?,
-                ),
-            )])
-            .map_err(|e| ManyError::unknown(e.to_string()))?;
+        self.put_metadata(&meta, key)?;
 
         self.log_event(EventInfo::KvStoreTransfer {
             key: key.to_vec().into(),
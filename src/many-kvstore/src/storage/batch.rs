@@ -0,0 +1,134 @@
+use super::chunk::{key_for_manifest, key_for_upload, Manifest};
+use super::usage::{add_delta, counted, UsageDeltas};
+use super::{KvStoreStorage, KVSTORE_ACL_ROOT, KVSTORE_ROOT};
+use crate::module::KvStoreMetadata;
+use many_error::ManyError;
+use many_modules::events::EventInfo;
+use merk::{BatchEntry, Op};
+use sha3::{Digest, Sha3_256};
+
+/// An operation on a key of the store.
+#[derive(Clone, Debug)]
+pub enum KeyOperation {
+    /// Put a value with its metadata, as the next version of the key.
+    Put {
+        meta: KvStoreMetadata,
+        value: Vec<u8>,
+    },
+
+    /// Remove the key entirely: its value, metadata, expiry, and its history
+    /// with the chunks of uploaded versions. A key put again after being
+    /// deleted starts over at version 1.
+    Delete,
+
+    /// Remove an expired key, like `Delete`.
+    Expire,
+
+    /// Put the manifest of a finished upload, like `Put`. Reading the key
+    /// returns the chunks of the upload reassembled.
+    PutManifest {
+        meta: KvStoreMetadata,
+        value: Vec<u8>,
+        upload: u64,
+    },
+}
+
+impl KvStoreStorage {
+    /// Apply operations on distinct keys in a single batch, so either all or
+    /// none of them are applied, and log an event for each put (deleted and
+    /// expired keys log none). Returns the version of each key after its
+    /// operation.
+    pub fn apply_operations(
+        &mut self,
+        operations: Vec<(Vec<u8>, KeyOperation)>,
+    ) -> Result<Vec<u64>, ManyError> {
+        let mut batch: Vec<BatchEntry> = Vec::new();
+        let mut versions = Vec::with_capacity(operations.len());
+        let mut deltas = UsageDeltas::new();
+        for (key, operation) in &operations {
+            let previous = self.get_decoded_metadata(key)?;
+            let counted_before = match &previous {
+                Some(previous) => counted(previous, self.stored_size(key)?),
+                None => None,
+            };
+            match operation {
+                KeyOperation::Put { meta, value }
+                | KeyOperation::PutManifest { meta, value, .. } => {
+                    let size = if let KeyOperation::PutManifest { .. } = operation {
+                        minicbor::decode::<Manifest>(value)
+                            .map_err(ManyError::deserialization_error)?
+                            .size
+                    } else {
+                        value.len() as u64
+                    };
+                    self.check_value_size(size)?;
+                    let stored_size = self.stored_size_after_put(key, size)?;
+                    add_delta(&mut deltas, counted_before, counted(meta, stored_size));
+
+                    let (version, entries) = self.next_version_batch(key, value)?;
+                    batch.extend(entries);
+                    batch.extend(self.expiry_batch(
+                        key,
+                        previous.as_ref(),
+                        meta.expires.as_ref(),
+                    )?);
+                    batch.push((
+                        [KVSTORE_ACL_ROOT, key].concat(),
+                        Op::Put(minicbor::to_vec(meta).map_err(ManyError::serialization_error)?),
+                    ));
+                    batch.push(([KVSTORE_ROOT, key].concat(), Op::Put(value.clone())));
+                    if let KeyOperation::PutManifest { upload, .. } = operation {
+                        batch.push((key_for_manifest(key, version), Op::Put(Vec::new())));
+                        batch.push((key_for_upload(*upload), Op::Delete));
+                        // The upload is not counted on its own anymore.
+                        if let Some(upload) = self.get_upload(*upload)? {
+                            add_delta(&mut deltas, Some((upload.owner, upload.size)), None);
+                        }
+                    }
+                    versions.push(version);
+                }
+                KeyOperation::Delete | KeyOperation::Expire => {
+                    add_delta(&mut deltas, counted_before, None);
+                    batch.extend(self.expiry_batch(key, previous.as_ref(), None)?);
+                    batch.extend(self.purge_versions_batch(key)?);
+                    // Merk fails to delete keys which do not exist.
+                    for prefix in [KVSTORE_ACL_ROOT, KVSTORE_ROOT] {
+                        if self._get(key, prefix)?.is_some() {
+                            batch.push(([prefix, key].concat(), Op::Delete));
+                        }
+                    }
+                    versions.push(0);
+                }
+            }
+        }
+
+        batch.extend(self.usage_batch(deltas)?);
+
+        // Merk requires the batch to be sorted by key.
+        batch.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.persistent_store
+            .apply(&batch)
+            .map_err(|e| ManyError::unknown(e.to_string()))?;
+
+        for (key, operation) in operations {
+            if let KeyOperation::Put { meta, value }
+            | KeyOperation::PutManifest { meta, value, .. } = operation
+            {
+                self.log_event(EventInfo::KvStorePut {
+                    key: key.into(),
+                    value: if self.hash_event_values {
+                        Sha3_256::digest(&value).to_vec().into()
+                    } else {
+                        value.into()
+                    },
+                    owner: meta.owner,
+                });
+            }
+        }
+
+        if !self.blockchain {
+            self.persistent_store.commit(&[]).unwrap();
+        }
+        Ok(versions)
+    }
+}
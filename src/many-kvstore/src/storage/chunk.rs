@@ -0,0 +1,336 @@
+use super::batch::KeyOperation;
+use super::expiry::secs;
+use super::usage::{add_delta, UsageDeltas};
+use super::{decode_u64, KvStoreStorage};
+use crate::error;
+use crate::module::KvStoreMetadata;
+use many_error::ManyError;
+use many_identity::Address;
+use many_types::Timestamp;
+use merk::rocksdb::{IteratorMode, ReadOptions};
+use merk::{BatchEntry, Op};
+use minicbor::bytes::ByteVec;
+use sha3::{Digest, Sha3_256};
+
+/// The uploads in progress, indexed by big-endian upload ID.
+const KVSTORE_UPLOAD_ROOT: &[u8] = b"u";
+
+/// The chunks of uploaded values, indexed by big-endian upload ID and chunk
+/// index.
+const KVSTORE_CHUNK_ROOT: &[u8] = b"c";
+
+/// Marks the versions of keys whose value is a manifest, indexed like the
+/// history.
+const KVSTORE_MANIFEST_ROOT: &[u8] = b"m";
+
+const NEXT_UPLOAD_ID_KEY: &[u8] = b"/config/upload_id";
+
+/// The number of seconds after which an upload which was not finalized is
+/// abandoned, and removed with its chunks.
+pub const UPLOAD_TIMEOUT: u64 = 24 * 60 * 60;
+
+/// An upload in progress.
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct Upload {
+    /// The key to put the value at.
+    #[n(0)]
+    pub key: ByteVec,
+
+    /// The address which sends the chunks.
+    #[n(1)]
+    pub sender: Address,
+
+    /// The owner of the key, unless it already exists. The size of the
+    /// upload counts against its usage until the upload ends.
+    #[n(2)]
+    pub owner: Address,
+
+    #[n(3)]
+    pub expires: Option<Timestamp>,
+
+    /// The size of the whole value.
+    #[n(4)]
+    pub size: u64,
+
+    /// The SHA3-256 hash of the whole value.
+    #[n(5)]
+    pub hash: ByteVec,
+
+    /// The SHA3-256 hashes of the chunks received so far, in order.
+    #[n(6)]
+    pub chunks: Vec<ByteVec>,
+
+    /// The number of bytes received so far.
+    #[n(7)]
+    pub received: u64,
+
+    /// The time the upload started at. Uploads start in the order of their
+    /// IDs.
+    #[n(8)]
+    pub started: Timestamp,
+}
+
+/// The value stored at a key which was uploaded in chunks. Reading the key
+/// returns the chunks reassembled.
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct Manifest {
+    #[n(0)]
+    pub upload: u64,
+
+    #[n(1)]
+    pub size: u64,
+
+    #[n(2)]
+    pub hash: ByteVec,
+
+    #[n(3)]
+    pub chunks: Vec<ByteVec>,
+}
+
+pub(super) fn key_for_upload(id: u64) -> Vec<u8> {
+    [KVSTORE_UPLOAD_ROOT, &id.to_be_bytes()].concat()
+}
+
+fn key_for_chunk(upload: u64, index: usize) -> Vec<u8> {
+    [
+        KVSTORE_CHUNK_ROOT,
+        &upload.to_be_bytes(),
+        &(index as u32).to_be_bytes(),
+    ]
+    .concat()
+}
+
+pub(super) fn key_for_manifest(key: &[u8], version: u64) -> Vec<u8> {
+    [KVSTORE_MANIFEST_ROOT, key, &version.to_be_bytes()].concat()
+}
+
+impl KvStoreStorage {
+    /// Start an upload, returning its ID. Fails if the owner of the upload
+    /// does not have room for its size.
+    pub fn begin_upload(&mut self, upload: &Upload) -> Result<u64, ManyError> {
+        let id = self
+            .persistent_store
+            .get(NEXT_UPLOAD_ID_KEY)
+            .map_err(error::storage_get_failed)?
+            .map_or(Ok(0), |x| decode_u64("upload ID", &x))?;
+
+        let mut deltas = UsageDeltas::new();
+        add_delta(&mut deltas, None, Some((upload.owner, upload.size)));
+        let mut batch = self.usage_batch(deltas)?;
+        batch.push((
+            NEXT_UPLOAD_ID_KEY.to_vec(),
+            Op::Put((id + 1).to_be_bytes().to_vec()),
+        ));
+        batch.push((
+            key_for_upload(id),
+            Op::Put(minicbor::to_vec(upload).map_err(ManyError::serialization_error)?),
+        ));
+        // Merk requires the batch to be sorted by key.
+        batch.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.persistent_store
+            .apply(&batch)
+            .map_err(error::storage_apply_failed)?;
+
+        if !self.blockchain {
+            self.persistent_store.commit(&[]).unwrap();
+        }
+        Ok(id)
+    }
+
+    pub fn get_upload(&self, id: u64) -> Result<Option<Upload>, ManyError> {
+        self.persistent_store
+            .get(&key_for_upload(id))
+            .map_err(error::storage_get_failed)?
+            .map(|cbor| minicbor::decode(&cbor))
+            .transpose()
+            .map_err(ManyError::deserialization_error)
+    }
+
+    /// Store the next chunk of an upload, returning its hash.
+    pub fn put_chunk(
+        &mut self,
+        id: u64,
+        mut upload: Upload,
+        data: Vec<u8>,
+    ) -> Result<ByteVec, ManyError> {
+        let hash: ByteVec = Sha3_256::digest(&data).to_vec().into();
+        let index = upload.chunks.len();
+        upload.chunks.push(hash.clone());
+        upload.received += data.len() as u64;
+
+        // Chunks sort before uploads.
+        self.persistent_store
+            .apply(&[
+                (key_for_chunk(id, index), Op::Put(data)),
+                (
+                    key_for_upload(id),
+                    Op::Put(minicbor::to_vec(&upload).map_err(ManyError::serialization_error)?),
+                ),
+            ])
+            .map_err(error::storage_apply_failed)?;
+
+        if !self.blockchain {
+            self.persistent_store.commit(&[]).unwrap();
+        }
+        Ok(hash)
+    }
+
+    /// Put the uploaded value at its key, as a manifest of its chunks,
+    /// returning its version. Fails if the chunks do not match the hash of the
+    /// upload.
+    pub fn finalize_upload(
+        &mut self,
+        id: u64,
+        upload: Upload,
+        meta: KvStoreMetadata,
+    ) -> Result<u64, ManyError> {
+        let mut hasher = Sha3_256::new();
+        for index in 0..upload.chunks.len() {
+            hasher.update(self.get_chunk(id, index)?);
+        }
+        if hasher.finalize().as_slice() != upload.hash.as_slice() {
+            return Err(error::hash_mismatch());
+        }
+
+        let manifest = Manifest {
+            upload: id,
+            size: upload.size,
+            hash: upload.hash,
+            chunks: upload.chunks,
+        };
+        let versions = self.apply_operations(vec![(
+            upload.key.to_vec(),
+            KeyOperation::PutManifest {
+                meta,
+                value: minicbor::to_vec(manifest).map_err(ManyError::serialization_error)?,
+                upload: id,
+            },
+        )])?;
+        Ok(versions[0])
+    }
+
+    /// Remove an upload and the chunks received.
+    pub fn abort_upload(&mut self, id: u64, upload: &Upload) -> Result<(), ManyError> {
+        self.remove_uploads(vec![(id, upload.clone())])?;
+
+        if !self.blockchain {
+            self.persistent_store.commit(&[]).unwrap();
+        }
+        Ok(())
+    }
+
+    /// Remove uploads and their chunks, and stop counting their sizes
+    /// against their owners.
+    fn remove_uploads(&mut self, uploads: Vec<(u64, Upload)>) -> Result<(), ManyError> {
+        let mut batch: Vec<BatchEntry> = Vec::new();
+        let mut deltas = UsageDeltas::new();
+        for (id, upload) in uploads {
+            batch.extend(
+                (0..upload.chunks.len()).map(|index| (key_for_chunk(id, index), Op::Delete)),
+            );
+            batch.push((key_for_upload(id), Op::Delete));
+            add_delta(&mut deltas, Some((upload.owner, upload.size)), None);
+        }
+        batch.extend(self.usage_batch(deltas)?);
+
+        // Merk requires the batch to be sorted by key.
+        batch.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.persistent_store
+            .apply(&batch)
+            .map_err(error::storage_apply_failed)
+    }
+
+    /// Remove the uploads abandoned at the time of the current block, which
+    /// started `UPLOAD_TIMEOUT` seconds ago or more.
+    pub(super) fn remove_abandoned_uploads(&mut self) -> Result<(), ManyError> {
+        let cutoff = secs(&self.now())?.saturating_sub(UPLOAD_TIMEOUT);
+        let mut opts = ReadOptions::default();
+        opts.set_iterate_lower_bound(key_for_upload(0));
+        opts.set_iterate_upper_bound(key_for_upload(u64::MAX));
+
+        let mut uploads = Vec::new();
+        for item in self.persistent_store.iter_opt(IteratorMode::Start, opts) {
+            let (k, _) = item.map_err(ManyError::unknown)?;
+            let id = decode_u64("upload ID", &k[KVSTORE_UPLOAD_ROOT.len()..])?;
+            // The upload may have ended in this block.
+            match self.get_upload(id)? {
+                // The uploads after this one started later.
+                Some(upload) if secs(&upload.started)? > cutoff => break,
+                Some(upload) => uploads.push((id, upload)),
+                None => {}
+            }
+        }
+        if !uploads.is_empty() {
+            self.remove_uploads(uploads)?;
+        }
+        Ok(())
+    }
+
+    fn get_chunk(&self, upload: u64, index: usize) -> Result<Vec<u8>, ManyError> {
+        self.persistent_store
+            .get(&key_for_chunk(upload, index))
+            .map_err(error::storage_get_failed)?
+            .ok_or_else(|| error::storage_get_failed(format!("missing chunk {index}")))
+    }
+
+    pub(super) fn get_manifest(
+        &self,
+        key: &[u8],
+        version: u64,
+        value: &[u8],
+    ) -> Result<Option<Manifest>, ManyError> {
+        if self
+            .persistent_store
+            .get(&key_for_manifest(key, version))
+            .map_err(error::storage_get_failed)?
+            .is_none()
+        {
+            return Ok(None);
+        }
+        minicbor::decode(value)
+            .map(Some)
+            .map_err(ManyError::deserialization_error)
+    }
+
+    /// The value stored at a version of a key, with its chunks reassembled if
+    /// it was uploaded in chunks.
+    pub(super) fn reassemble(
+        &self,
+        key: &[u8],
+        version: u64,
+        value: Vec<u8>,
+    ) -> Result<Vec<u8>, ManyError> {
+        match self.get_manifest(key, version, &value)? {
+            Some(manifest) => {
+                let mut value = Vec::with_capacity(manifest.size as usize);
+                for index in 0..manifest.chunks.len() {
+                    value.extend(self.get_chunk(manifest.upload, index)?);
+                }
+                Ok(value)
+            }
+            None => Ok(value),
+        }
+    }
+
+    /// The operations removing the chunks of a version of a key pruned from
+    /// the history, if it was uploaded in chunks.
+    pub(super) fn prune_chunks_batch(
+        &self,
+        key: &[u8],
+        version: u64,
+        value: &[u8],
+    ) -> Result<Vec<BatchEntry>, ManyError> {
+        let manifest = match self.get_manifest(key, version, value)? {
+            Some(manifest) => manifest,
+            None => return Ok(vec![]),
+        };
+
+        let mut batch: Vec<BatchEntry> = (0..manifest.chunks.len())
+            .map(|index| (key_for_chunk(manifest.upload, index), Op::Delete))
+            .collect();
+        batch.push((key_for_manifest(key, version), Op::Delete));
+        Ok(batch)
+    }
+}
@@ -0,0 +1,115 @@
+use super::batch::KeyOperation;
+use super::KvStoreStorage;
+use crate::error;
+use crate::module::KvStoreMetadata;
+use many_error::ManyError;
+use many_types::Timestamp;
+use merk::rocksdb::{IteratorMode, ReadOptions};
+use merk::{BatchEntry, Op};
+use std::time::UNIX_EPOCH;
+
+/// The keys which expire, indexed by big-endian expiry (in seconds) and key.
+const KVSTORE_EXPIRY_ROOT: &[u8] = b"x";
+
+pub(super) fn secs(timestamp: &Timestamp) -> Result<u64, ManyError> {
+    Ok(timestamp
+        .as_system_time()?
+        .duration_since(UNIX_EPOCH)
+        .map_err(ManyError::unknown)?
+        .as_secs())
+}
+
+fn key_for_expiry(expires: &Timestamp, key: &[u8]) -> Result<Vec<u8>, ManyError> {
+    Ok([KVSTORE_EXPIRY_ROOT, &secs(expires)?.to_be_bytes(), key].concat())
+}
+
+impl KvStoreStorage {
+    /// Whether a key with this metadata expired, at the time of the current
+    /// block.
+    pub fn is_expired(&self, meta: &KvStoreMetadata) -> Result<bool, ManyError> {
+        match &meta.expires {
+            Some(expires) => Ok(secs(expires)? <= secs(&self.now())?),
+            None => Ok(false),
+        }
+    }
+
+    /// Fail if `expires` is not in the future.
+    pub fn check_expiry(&self, expires: &Timestamp) -> Result<(), ManyError> {
+        if secs(expires)? <= secs(&self.now())? {
+            return Err(error::invalid_expiry());
+        }
+        Ok(())
+    }
+
+    /// The operations updating the expiry index of a key, from the expiry of
+    /// its previous metadata to `expires`.
+    pub(super) fn expiry_batch(
+        &self,
+        key: &[u8],
+        previous: Option<&KvStoreMetadata>,
+        expires: Option<&Timestamp>,
+    ) -> Result<Vec<BatchEntry>, ManyError> {
+        let previous = previous
+            .and_then(|meta| meta.expires.as_ref())
+            .map(|t| key_for_expiry(t, key))
+            .transpose()?;
+        let next = expires.map(|t| key_for_expiry(t, key)).transpose()?;
+
+        let mut batch = Vec::new();
+        if previous != next {
+            if let Some(previous) = previous {
+                // Merk fails to delete keys which do not exist.
+                if self
+                    .persistent_store
+                    .get(&previous)
+                    .map_err(error::storage_get_failed)?
+                    .is_some()
+                {
+                    batch.push((previous, Op::Delete));
+                }
+            }
+            if let Some(next) = next {
+                batch.push((next, Op::Put(Vec::new())));
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Remove the keys which expired at the time of the current block, in the
+    /// order of their expiry.
+    pub(super) fn remove_expired(&mut self) -> Result<(), ManyError> {
+        let mut opts = ReadOptions::default();
+        opts.set_iterate_lower_bound(KVSTORE_EXPIRY_ROOT);
+        opts.set_iterate_upper_bound(
+            [KVSTORE_EXPIRY_ROOT, &(secs(&self.now())? + 1).to_be_bytes()].concat(),
+        );
+
+        // Keys put in this block can only expire after it, so the committed
+        // index is enough.
+        let expired = self
+            .persistent_store
+            .iter_opt(IteratorMode::Start, opts)
+            .map(|item| item.map(|(k, _)| k.to_vec()).map_err(ManyError::unknown))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut operations = Vec::new();
+        for index_key in expired {
+            // The key may have been put again, or deleted, in this block.
+            if self
+                .persistent_store
+                .get(&index_key)
+                .map_err(error::storage_get_failed)?
+                .is_none()
+            {
+                continue;
+            }
+            let key = index_key[KVSTORE_EXPIRY_ROOT.len() + 8..].to_vec();
+            operations.push((key, KeyOperation::Expire));
+        }
+
+        if !operations.is_empty() {
+            self.apply_operations(operations)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use super::{KvStoreStorage, KVSTORE_ROOT};
+use crate::module::KvStoreMetadata;
+use many_error::ManyError;
+use many_types::{CborRange, Either, SortOrder};
+use merk::rocksdb::{IteratorMode, ReadOptions};
+use minicbor::bytes::ByteVec;
+use std::ops::{Bound, RangeBounds};
+
+/// The smallest key greater than every key starting with `prefix`, or `None`
+/// if there is none (the prefix is only `0xFF` bytes).
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut bound = prefix.to_vec();
+    while let Some(last) = bound.pop() {
+        if last < u8::MAX {
+            bound.push(last + 1);
+            return Some(bound);
+        }
+    }
+    None
+}
+
+/// Bounds of a listing over the storage keys, as RocksDB expects them: an
+/// inclusive lower bound and an exclusive upper bound.
+#[derive(Clone, Debug)]
+pub struct KeyBounds {
+    lower: Vec<u8>,
+    upper: Option<Vec<u8>>,
+}
+
+impl Default for KeyBounds {
+    /// All the keys of the store.
+    fn default() -> Self {
+        Self {
+            lower: KVSTORE_ROOT.to_vec(),
+            upper: prefix_upper_bound(KVSTORE_ROOT),
+        }
+    }
+}
+
+impl KeyBounds {
+    fn storage_key(key: &[u8]) -> Vec<u8> {
+        [KVSTORE_ROOT, key].concat()
+    }
+
+    fn restrict_lower(&mut self, lower: Vec<u8>) {
+        if lower > self.lower {
+            self.lower = lower;
+        }
+    }
+
+    fn restrict_upper(&mut self, upper: Vec<u8>) {
+        if self.upper.as_ref().map_or(true, |u| upper < *u) {
+            self.upper = Some(upper);
+        }
+    }
+
+    /// Only keep the keys starting with `prefix`.
+    pub fn with_prefix(mut self, prefix: &[u8]) -> Self {
+        let prefix = Self::storage_key(prefix);
+        if let Some(upper) = prefix_upper_bound(&prefix) {
+            self.restrict_upper(upper);
+        }
+        self.restrict_lower(prefix);
+        self
+    }
+
+    /// Only keep the keys within `range`.
+    pub fn with_range(mut self, range: &CborRange<ByteVec>) -> Self {
+        // The key following `x` is `x` followed by a zero byte.
+        match range.start_bound() {
+            Bound::Included(x) => self.restrict_lower(Self::storage_key(x.as_slice())),
+            Bound::Excluded(x) => self.restrict_lower([KVSTORE_ROOT, x.as_slice(), &[0]].concat()),
+            Bound::Unbounded => {}
+        }
+        match range.end_bound() {
+            Bound::Included(x) => self.restrict_upper([KVSTORE_ROOT, x.as_slice(), &[0]].concat()),
+            Bound::Excluded(x) => self.restrict_upper(Self::storage_key(x.as_slice())),
+            Bound::Unbounded => {}
+        }
+        self
+    }
+
+    /// Only keep the keys after `key` in the given order, to continue a
+    /// previous listing which ended with `key`.
+    pub fn after(self, key: &[u8], order: SortOrder) -> Self {
+        let key = ByteVec::from(key.to_vec());
+        let bound = match order {
+            SortOrder::Indeterminate | SortOrder::Ascending => CborRange {
+                start: Bound::Excluded(key),
+                end: Bound::Unbounded,
+            },
+            SortOrder::Descending => CborRange {
+                start: Bound::Unbounded,
+                end: Bound::Excluded(key),
+            },
+        };
+        self.with_range(&bound)
+    }
+}
+
+/// A key of the store, with its metadata if it has any.
+pub struct ListedKey {
+    pub key: Vec<u8>,
+    pub metadata: Option<KvStoreMetadata>,
+}
+
+impl ListedKey {
+    pub fn is_disabled(&self) -> bool {
+        matches!(
+            self.metadata.as_ref().and_then(|m| m.disabled.as_ref()),
+            Some(Either::Left(true)) | Some(Either::Right(_))
+        )
+    }
+}
+
+impl KvStoreStorage {
+    /// Iterate the committed keys of the store within `bounds`, in order.
+    pub fn list_keys(
+        &self,
+        bounds: KeyBounds,
+        order: SortOrder,
+    ) -> impl Iterator<Item = Result<ListedKey, ManyError>> + '_ {
+        let mut opts = ReadOptions::default();
+        opts.set_iterate_lower_bound(bounds.lower);
+        if let Some(upper) = bounds.upper {
+            opts.set_iterate_upper_bound(upper);
+        }
+
+        let mode = match order {
+            SortOrder::Indeterminate | SortOrder::Ascending => IteratorMode::Start,
+            SortOrder::Descending => IteratorMode::End,
+        };
+
+        self.persistent_store.iter_opt(mode, opts).map(move |item| {
+            let (k, _) = item.map_err(ManyError::unknown)?;
+            let key = k[KVSTORE_ROOT.len()..].to_vec();
+            let metadata = self.get_decoded_metadata(&key)?;
+            Ok(ListedKey { key, metadata })
+        })
+    }
+}
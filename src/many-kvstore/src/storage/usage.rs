@@ -0,0 +1,152 @@
+use super::{decode_u64, KvStoreStorage, KVSTORE_ACL_ROOT};
+use crate::error;
+use crate::module::KvStoreMetadata;
+use many_error::ManyError;
+use many_identity::Address;
+use many_types::Either;
+use merk::{BatchEntry, Op};
+use std::collections::BTreeMap;
+
+/// The number of bytes used by each owner, indexed by address.
+const KVSTORE_USAGE_ROOT: &[u8] = b"/usage/";
+
+/// The limits of the store, set when it is created.
+pub(super) const KVSTORE_LIMITS_KEY: &[u8] = b"/config/limits";
+
+/// The limits on the values of the store. All the nodes of a chain must use
+/// the same limits, so they are kept in the storage.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct KvStoreLimits {
+    /// The maximum size of a value, in bytes.
+    #[n(0)]
+    pub max_value_size: Option<u64>,
+
+    /// The maximum number of bytes of an owner, counting every version kept
+    /// of its keys and its uploads in progress.
+    #[n(1)]
+    pub owner_quota: Option<u64>,
+}
+
+/// The changes of usage of the owners, in bytes.
+pub(super) type UsageDeltas = BTreeMap<Address, i128>;
+
+fn key_for_usage(owner: &Address) -> Vec<u8> {
+    [KVSTORE_USAGE_ROOT, &owner.to_vec()].concat()
+}
+
+/// The owner a key counts against and its size, unless it is disabled.
+pub(super) fn counted(meta: &KvStoreMetadata, size: u64) -> Option<(Address, u64)> {
+    match meta.disabled {
+        None | Some(Either::Left(false)) => Some((meta.owner, size)),
+        _ => None,
+    }
+}
+
+/// Record that the key counted as `previous` now counts as `next`.
+pub(super) fn add_delta(
+    deltas: &mut UsageDeltas,
+    previous: Option<(Address, u64)>,
+    next: Option<(Address, u64)>,
+) {
+    if let Some((owner, size)) = previous {
+        *deltas.entry(owner).or_default() -= size as i128;
+    }
+    if let Some((owner, size)) = next {
+        *deltas.entry(owner).or_default() += size as i128;
+    }
+}
+
+impl KvStoreStorage {
+    /// Set the limits of the store and commit them. This is only done when
+    /// the storage is created, before any block; loading the storage reads
+    /// them back.
+    pub fn set_limits(&mut self, limits: KvStoreLimits) -> Result<(), ManyError> {
+        self.persistent_store
+            .apply(&[(
+                KVSTORE_LIMITS_KEY.to_vec(),
+                Op::Put(minicbor::to_vec(limits).map_err(ManyError::serialization_error)?),
+            )])
+            .map_err(error::storage_apply_failed)?;
+        self.persistent_store
+            .commit(&[])
+            .map_err(error::storage_apply_failed)?;
+        self.limits = limits;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn limits(&self) -> KvStoreLimits {
+        self.limits
+    }
+
+    /// The number of bytes used by an owner: the values kept in the history
+    /// of its keys, and the sizes of the uploads it started which are not
+    /// finalized yet. Values put before usage was tracked are not counted.
+    pub fn get_usage(&self, owner: &Address) -> Result<u64, ManyError> {
+        self.persistent_store
+            .get(&key_for_usage(owner))
+            .map_err(error::storage_get_failed)?
+            .map_or(Ok(0), |x| decode_u64("usage", &x))
+    }
+
+    pub fn check_value_size(&self, size: u64) -> Result<(), ManyError> {
+        match self.limits.max_value_size {
+            Some(max) if size > max => Err(error::value_too_large(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The operations updating the usage of the owners, failing if an owner
+    /// would go over its quota.
+    pub(super) fn usage_batch(&self, deltas: UsageDeltas) -> Result<Vec<BatchEntry>, ManyError> {
+        let mut batch = Vec::new();
+        for (owner, delta) in deltas {
+            if delta == 0 {
+                continue;
+            }
+            let usage = self.get_usage(&owner)? as i128;
+            let new_usage = (usage + delta).max(0) as u64;
+            if let Some(quota) = self.limits.owner_quota {
+                if delta > 0 && new_usage > quota {
+                    return Err(error::quota_exceeded(quota));
+                }
+            }
+            batch.push((
+                key_for_usage(&owner),
+                Op::Put(new_usage.to_be_bytes().to_vec()),
+            ));
+        }
+        Ok(batch)
+    }
+
+    /// Write the metadata of a key, and update the usage of its previous and
+    /// new owners.
+    pub(super) fn put_metadata(
+        &mut self,
+        meta: &KvStoreMetadata,
+        key: &[u8],
+    ) -> Result<(), ManyError> {
+        let previous = self.get_decoded_metadata(key)?;
+        let size = self.stored_size(key)?;
+        let mut deltas = UsageDeltas::new();
+        add_delta(
+            &mut deltas,
+            previous
+                .as_ref()
+                .and_then(|previous| counted(previous, size)),
+            counted(meta, size),
+        );
+
+        let mut batch = self.usage_batch(deltas)?;
+        batch.push((
+            [KVSTORE_ACL_ROOT, key].concat(),
+            Op::Put(minicbor::to_vec(meta).map_err(ManyError::serialization_error)?),
+        ));
+        // Merk requires the batch to be sorted by key.
+        batch.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.persistent_store
+            .apply(&batch)
+            .map_err(|e| ManyError::unknown(e.to_string()))
+    }
+}
@@ -0,0 +1,151 @@
+use super::chunk::Manifest;
+use super::{decode_u64, KvStoreStorage, KVSTORE_ROOT};
+use crate::error;
+use many_error::ManyError;
+use merk::{BatchEntry, Op};
+
+/// The current version of each key.
+const KVSTORE_VERSION_ROOT: &[u8] = b"v";
+
+/// The values of the last `MAXIMUM_VERSIONS` versions of each key, indexed by
+/// key and big-endian version.
+const KVSTORE_HISTORY_ROOT: &[u8] = b"h";
+
+/// The number of versions kept for each key, including the current one.
+pub const MAXIMUM_VERSIONS: u64 = 10;
+
+fn key_for_version(key: &[u8]) -> Vec<u8> {
+    [KVSTORE_VERSION_ROOT, key].concat()
+}
+
+fn key_for_history(key: &[u8], version: u64) -> Vec<u8> {
+    [KVSTORE_HISTORY_ROOT, key, &version.to_be_bytes()].concat()
+}
+
+impl KvStoreStorage {
+    /// The current version of a key. Versions start at 1; a key which was
+    /// never put (or only before versions were kept) is at version 0.
+    pub fn get_version(&self, key: &[u8]) -> Result<u64, ManyError> {
+        self.persistent_store
+            .get(&key_for_version(key))
+            .map_err(error::storage_get_failed)?
+            .map_or(Ok(0), |x| decode_u64("version", &x))
+    }
+
+    /// The value of a key at a given version, if that version is still kept.
+    pub fn get_at_version(&self, key: &[u8], version: u64) -> Result<Option<Vec<u8>>, ManyError> {
+        // A key without metadata does not exist, e.g. it was deleted.
+        if self.get_metadata(key)?.is_none() || !self.check_available(key)? {
+            return Ok(None);
+        }
+        self.persistent_store
+            .get(&key_for_history(key, version))
+            .map_err(error::storage_get_failed)?
+            .map(|value| self.reassemble(key, version, value))
+            .transpose()
+    }
+
+    /// The size of a version of a key, if it is still kept. The size of a
+    /// version uploaded in chunks is the size of its chunks.
+    fn version_size(&self, key: &[u8], version: u64) -> Result<u64, ManyError> {
+        let value = match self
+            .persistent_store
+            .get(&key_for_history(key, version))
+            .map_err(error::storage_get_failed)?
+        {
+            Some(value) => value,
+            None => return Ok(0),
+        };
+        match self.get_manifest(key, version, &value)? {
+            Some(Manifest { size, .. }) => Ok(size),
+            None => Ok(value.len() as u64),
+        }
+    }
+
+    /// The number of bytes kept for a key, whether it is available or not:
+    /// the sizes of the versions in its history, or the size of its value if
+    /// it was put before versions were kept.
+    pub(super) fn stored_size(&self, key: &[u8]) -> Result<u64, ManyError> {
+        let version = self.get_version(key)?;
+        if version == 0 {
+            return Ok(self._get(key, KVSTORE_ROOT)?.map_or(0, |x| x.len() as u64));
+        }
+        let mut size = 0;
+        for version in version.saturating_sub(MAXIMUM_VERSIONS) + 1..=version {
+            size += self.version_size(key, version)?;
+        }
+        Ok(size)
+    }
+
+    /// The number of bytes kept for a key once a value of `size` bytes is put
+    /// as its next version, and the oldest version is pruned if the history
+    /// is full.
+    pub(super) fn stored_size_after_put(&self, key: &[u8], size: u64) -> Result<u64, ManyError> {
+        let version = self.get_version(key)?;
+        if version == 0 {
+            // A value put before versions were kept is not in the history.
+            return Ok(size);
+        }
+        let pruned = if version + 1 > MAXIMUM_VERSIONS {
+            self.version_size(key, version + 1 - MAXIMUM_VERSIONS)?
+        } else {
+            0
+        };
+        Ok(self.stored_size(key)? - pruned + size)
+    }
+
+    /// The operations recording `value` as the next version of `key`, pruning
+    /// the versions which fall out of the history, and that version.
+    pub(super) fn next_version_batch(
+        &self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(u64, Vec<BatchEntry>), ManyError> {
+        let version = self.get_version(key)? + 1;
+        let mut batch = vec![
+            (
+                key_for_version(key),
+                Op::Put(version.to_be_bytes().to_vec()),
+            ),
+            (key_for_history(key, version), Op::Put(value.to_vec())),
+        ];
+
+        if version > MAXIMUM_VERSIONS {
+            let pruned_version = version - MAXIMUM_VERSIONS;
+            let pruned = key_for_history(key, pruned_version);
+            if let Some(value) = self
+                .persistent_store
+                .get(&pruned)
+                .map_err(error::storage_get_failed)?
+            {
+                batch.push((pruned, Op::Delete));
+                batch.extend(self.prune_chunks_batch(key, pruned_version, &value)?);
+            }
+        }
+
+        Ok((version, batch))
+    }
+
+    /// The operations removing the version and the whole history of a key,
+    /// with the chunks of the versions which were uploaded.
+    pub(super) fn purge_versions_batch(&self, key: &[u8]) -> Result<Vec<BatchEntry>, ManyError> {
+        let version = self.get_version(key)?;
+        if version == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut batch = vec![(key_for_version(key), Op::Delete)];
+        for version in version.saturating_sub(MAXIMUM_VERSIONS) + 1..=version {
+            let history = key_for_history(key, version);
+            if let Some(value) = self
+                .persistent_store
+                .get(&history)
+                .map_err(error::storage_get_failed)?
+            {
+                batch.push((history, Op::Delete));
+                batch.extend(self.prune_chunks_batch(key, version, &value)?);
+            }
+        }
+        Ok(batch)
+    }
+}
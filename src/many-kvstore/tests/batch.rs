@@ -0,0 +1,180 @@
+pub mod common;
+
+use crate::common::{setup, Setup};
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_kvstore::error;
+use many_kvstore::module::batch::{
+    BatchArgs, BatchOperation, BatchReturns, MAXIMUM_BATCH_OPERATIONS,
+};
+use many_modules::events::{self, EventInfo, EventsModuleBackend};
+use minicbor::bytes::ByteVec;
+
+fn put_op(key: &[u8], value: &[u8]) -> BatchOperation {
+    BatchOperation {
+        key: key.to_vec().into(),
+        value: Some(value.to_vec().into()),
+        if_version: None,
+        if_value: None,
+        expires: None,
+    }
+}
+
+fn delete_op(key: &[u8]) -> BatchOperation {
+    BatchOperation {
+        key: key.to_vec().into(),
+        value: None,
+        if_version: None,
+        if_value: None,
+        expires: None,
+    }
+}
+
+fn batch(setup: &mut Setup, operations: Vec<BatchOperation>) -> Result<BatchReturns, ManyError> {
+    let id = setup.id;
+    setup.module_impl.batch(
+        &id,
+        BatchArgs {
+            operations,
+            alternative_owner: None,
+        },
+    )
+}
+
+fn value(setup: &Setup, key: &[u8]) -> Option<ByteVec> {
+    let id = setup.id;
+    setup.get(&id, key.to_vec()).unwrap().value
+}
+
+#[test]
+fn batch_put_delete() {
+    let mut setup = setup();
+    let returns = batch(&mut setup, vec![put_op(b"a", b"1"), put_op(b"b", b"2")]).unwrap();
+    assert_eq!(returns.versions, vec![1, 1]);
+
+    let returns = batch(&mut setup, vec![delete_op(b"a"), put_op(b"b", b"3")]).unwrap();
+    assert_eq!(returns.versions, vec![0, 2]);
+    assert_eq!(value(&setup, b"a"), None);
+    assert_eq!(value(&setup, b"b"), Some(b"3".to_vec().into()));
+
+    // A deleted key has no owner anymore.
+    let id = setup.id;
+    assert!(setup.query(&id, b"a".to_vec()).is_err());
+    setup
+        .put(&identity(1), b"a".to_vec(), b"4".to_vec(), None)
+        .unwrap();
+}
+
+#[test]
+fn batch_events() {
+    let mut setup = setup();
+    batch(&mut setup, vec![put_op(b"a", b"1"), put_op(b"b", b"2")]).unwrap();
+    batch(&mut setup, vec![delete_op(b"a")]).unwrap();
+
+    let events = setup
+        .module_impl
+        .list(events::ListArgs {
+            count: None,
+            order: None,
+            filter: None,
+        })
+        .unwrap()
+        .events;
+    // Deleting a key logs no event.
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .all(|e| matches!(&e.content, EventInfo::KvStorePut { .. })));
+}
+
+#[test]
+fn batch_is_atomic() {
+    let mut setup = setup();
+    batch(&mut setup, vec![put_op(b"a", b"1")]).unwrap();
+    setup
+        .put(&identity(1), b"c".to_vec(), b"1".to_vec(), None)
+        .unwrap();
+
+    // The key `c` belongs to someone else.
+    let result = batch(&mut setup, vec![put_op(b"a", b"2"), put_op(b"c", b"2")]);
+    assert_eq!(
+        result.unwrap_err().code(),
+        error::permission_denied().code()
+    );
+
+    // Deleting a key which does not exist.
+    let result = batch(&mut setup, vec![put_op(b"a", b"2"), delete_op(b"d")]);
+    assert_eq!(result.unwrap_err().code(), error::key_not_found().code());
+
+    let result = batch(&mut setup, vec![put_op(b"a", b"2"), put_op(b"a", b"3")]);
+    assert_eq!(result.unwrap_err().code(), error::duplicate_key("").code());
+
+    assert_eq!(value(&setup, b"a"), Some(b"1".to_vec().into()));
+}
+
+#[test]
+fn batch_preconditions() {
+    let mut setup = setup();
+    batch(&mut setup, vec![put_op(b"a", b"1"), put_op(b"b", b"1")]).unwrap();
+
+    let mut op = put_op(b"a", b"2");
+    op.if_version = Some(2);
+    let result = batch(&mut setup, vec![put_op(b"b", b"2"), op.clone()]);
+    assert_eq!(
+        result.unwrap_err().code(),
+        error::version_mismatch(2, 1).code()
+    );
+    assert_eq!(value(&setup, b"b"), Some(b"1".to_vec().into()));
+
+    op.if_version = Some(1);
+    op.if_value = Some(b"0".to_vec().into());
+    let result = batch(&mut setup, vec![put_op(b"b", b"2"), op.clone()]);
+    assert_eq!(result.unwrap_err().code(), error::value_mismatch("").code());
+    assert_eq!(value(&setup, b"b"), Some(b"1".to_vec().into()));
+
+    op.if_value = Some(b"1".to_vec().into());
+    let returns = batch(&mut setup, vec![put_op(b"b", b"2"), op]).unwrap();
+    assert_eq!(returns.versions, vec![2, 2]);
+}
+
+#[test]
+fn batch_block() {
+    let mut setup = Setup::new(true);
+    let (_, result) =
+        setup.block(|setup| batch(setup, vec![put_op(b"a", b"1"), put_op(b"b", b"2")]));
+    assert!(result.is_ok());
+    let (_, result) = setup.block(|setup| batch(setup, vec![delete_op(b"a")]));
+    assert!(result.is_ok());
+    assert_eq!(value(&setup, b"a"), None);
+}
+
+#[test]
+fn batch_too_many_operations() {
+    let mut setup = setup();
+    let operations = (0..=MAXIMUM_BATCH_OPERATIONS)
+        .map(|i| put_op(&i.to_be_bytes(), b"1"))
+        .collect();
+    let result = batch(&mut setup, operations);
+    assert_eq!(
+        result.unwrap_err().code(),
+        error::too_many_operations(0).code()
+    );
+}
+
+#[test]
+fn batch_anonymous() {
+    let mut setup = setup();
+    let result = setup.module_impl.batch(
+        &Address::anonymous(),
+        BatchArgs {
+            operations: vec![put_op(b"a", b"1")],
+            alternative_owner: None,
+        },
+    );
+    assert_eq!(
+        result.unwrap_err().code(),
+        ManyError::invalid_identity().code()
+    );
+    assert_eq!(value(&setup, b"a"), None);
+}
@@ -74,6 +74,11 @@ impl Setup {
         (info.height, r)
     }
 
+    /// Move the time of the next blocks forward.
+    pub fn skip(&mut self, secs: u64) {
+        self.time = self.time.map(|t| t + secs);
+    }
+
     pub fn put(
         &mut self,
         sender: &Address,
@@ -150,3 +150,35 @@ fn list_filter_date() {
     let list_return = result.unwrap();
     assert_eq!(list_return.events.len(), 0);
 }
+
+#[test]
+fn list_hashed_values() {
+    use sha3::{Digest, Sha3_256};
+
+    let mut setup = setup();
+    setup.module_impl.set_hash_event_values(true).unwrap();
+    let id = setup.id;
+    setup
+        .put(&id, vec![11, 11, 12], vec![4, 5, 6], None)
+        .unwrap();
+    let list_return = setup
+        .module_impl
+        .list(events::ListArgs {
+            count: None,
+            order: None,
+            filter: None,
+        })
+        .unwrap();
+    match &list_return.events[0].content {
+        events::EventInfo::KvStorePut { value, .. } => {
+            assert_eq!(value.as_slice(), Sha3_256::digest([4, 5, 6]).as_slice());
+        }
+        _ => unreachable!(),
+    }
+
+    // The value itself is stored.
+    assert_eq!(
+        setup.get(&id, vec![11, 11, 12]).unwrap().value,
+        Some(vec![4, 5, 6].into())
+    );
+}
@@ -0,0 +1,114 @@
+pub mod common;
+
+use crate::common::Setup;
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_kvstore::error;
+use many_kvstore::module::version::{PutArgs, PutReturns};
+use many_modules::events::{self, EventsModuleBackend};
+use many_types::Timestamp;
+
+// The time of the first block of the setup.
+const START: u64 = 1_000_001;
+
+fn put(
+    setup: &mut Setup,
+    sender: &Address,
+    key: &[u8],
+    expires: Option<u64>,
+) -> Result<PutReturns, ManyError> {
+    setup.module_impl.put_versioned(
+        sender,
+        PutArgs {
+            key: key.to_vec().into(),
+            value: vec![1].into(),
+            alternative_owner: None,
+            if_version: None,
+            expires: expires.map(|secs| Timestamp::new(secs).unwrap()),
+        },
+    )
+}
+
+fn event_count(setup: &Setup) -> u64 {
+    EventsModuleBackend::info(&setup.module_impl, events::InfoArgs {})
+        .unwrap()
+        .total
+}
+
+#[test]
+fn expired_keys_are_removed() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    setup.block(|setup| put(setup, &id, b"a", Some(START + 2)).unwrap());
+
+    // Not expired yet.
+    setup.block(|setup| {
+        assert!(setup.get(&id, b"a".to_vec()).unwrap().value.is_some());
+    });
+    let events = event_count(&setup);
+
+    setup.block(|setup| {
+        assert_eq!(setup.get(&id, b"a".to_vec()).unwrap().value, None);
+        assert_eq!(
+            setup.query(&id, b"a".to_vec()).unwrap_err().code(),
+            error::key_not_found().code()
+        );
+    });
+    // Removing an expired key logs no event.
+    assert_eq!(event_count(&setup), events);
+    assert_eq!(setup.get(&id, b"a".to_vec()).unwrap().value, None);
+
+    // The key is free again.
+    let other = identity(1);
+    setup.block(|setup| put(setup, &other, b"a", None).unwrap());
+    assert_eq!(setup.query(&id, b"a".to_vec()).unwrap().owner, other);
+}
+
+#[test]
+fn expired_keys_are_free() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    setup.block(|setup| put(setup, &id, b"a", Some(START + 1)).unwrap());
+
+    // The key expired in this block, before being removed at its end.
+    let other = identity(1);
+    let (_, result) = setup.block(|setup| put(setup, &other, b"a", None));
+    assert!(result.is_ok());
+    assert!(setup.get(&id, b"a".to_vec()).unwrap().value.is_some());
+}
+
+#[test]
+fn put_clears_expiry() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    setup.block(|setup| put(setup, &id, b"a", Some(START + 2)).unwrap());
+    setup.block(|setup| put(setup, &id, b"a", None).unwrap());
+    setup.block(|_| {});
+    setup.block(|_| {});
+
+    assert!(setup.get(&id, b"a".to_vec()).unwrap().value.is_some());
+}
+
+#[test]
+fn disable_keeps_expiry() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    setup.block(|setup| put(setup, &id, b"a", Some(START + 2)).unwrap());
+    setup.block(|setup| setup.disable(&id, b"a".to_vec(), None, None).unwrap());
+    setup.block(|_| {});
+
+    assert_eq!(setup.get(&id, b"a".to_vec()).unwrap().value, None);
+    assert_eq!(
+        setup.query(&id, b"a".to_vec()).unwrap_err().code(),
+        error::key_not_found().code()
+    );
+}
+
+#[test]
+fn expiry_in_the_past() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    let (_, result) = setup.block(|setup| put(setup, &id, b"a", Some(START)));
+    assert_eq!(result.unwrap_err().code(), error::invalid_expiry().code());
+}
@@ -0,0 +1,239 @@
+pub mod common;
+
+use crate::common::{assert_many_err, setup, Setup};
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_kvstore::error;
+use many_kvstore::module::grant::{GrantArgs, Grants, GrantsArgs, KeyPermission, RevokeArgs};
+use many_modules::events::{self, EventsModuleBackend};
+use many_modules::kvstore::{KvStoreTransferModuleBackend, TransferArgs};
+use many_types::Either;
+use std::collections::BTreeSet;
+
+fn grant(
+    setup: &mut Setup,
+    sender: &Address,
+    address: Address,
+    permissions: impl IntoIterator<Item = KeyPermission>,
+) -> Result<(), ManyError> {
+    setup.module_impl.grant(
+        sender,
+        GrantArgs {
+            key: vec![1].into(),
+            address,
+            permissions: permissions.into_iter().collect(),
+            alternative_owner: None,
+        },
+    )?;
+    Ok(())
+}
+
+fn revoke(
+    setup: &mut Setup,
+    sender: &Address,
+    address: Address,
+    permissions: Option<BTreeSet<KeyPermission>>,
+) -> Result<(), ManyError> {
+    setup.module_impl.revoke(
+        sender,
+        RevokeArgs {
+            key: vec![1].into(),
+            address,
+            permissions,
+            alternative_owner: None,
+        },
+    )?;
+    Ok(())
+}
+
+fn event_count(setup: &Setup) -> u64 {
+    EventsModuleBackend::info(&setup.module_impl, events::InfoArgs {})
+        .unwrap()
+        .total
+}
+
+fn grants(setup: &Setup) -> Grants {
+    setup
+        .module_impl
+        .grants(GrantsArgs {
+            key: vec![1].into(),
+        })
+        .unwrap()
+        .grants
+}
+
+fn transfer(setup: &mut Setup, sender: &Address, new_owner: Address) -> Result<(), ManyError> {
+    setup.module_impl.transfer(
+        sender,
+        TransferArgs {
+            key: vec![1].into(),
+            alternative_owner: None,
+            new_owner,
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn grant_put() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+    assert_many_err(
+        setup.put(&identity(5), vec![1], vec![3], None),
+        error::permission_denied(),
+    );
+
+    grant(&mut setup, &id, identity(5), [KeyPermission::Put]).unwrap();
+    setup.put(&identity(5), vec![1], vec![3], None).unwrap();
+    assert_eq!(setup.get(&id, vec![1]).unwrap().value, Some(vec![3].into()));
+
+    // The grantee does not become the owner.
+    assert_eq!(setup.query(&id, vec![1]).unwrap().owner, id);
+    assert_eq!(
+        grants(&setup),
+        Grants::from([(identity(5), [KeyPermission::Put].into())])
+    );
+
+    // Putting does not allow disabling.
+    assert_many_err(
+        setup.disable(&identity(5), vec![1], None, None),
+        error::permission_denied(),
+    );
+}
+
+#[test]
+fn grant_disable() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+    grant(&mut setup, &id, identity(5), [KeyPermission::Disable]).unwrap();
+
+    assert_many_err(
+        setup.put(&identity(5), vec![1], vec![3], None),
+        error::permission_denied(),
+    );
+    setup.disable(&identity(5), vec![1], None, None).unwrap();
+    assert_eq!(
+        setup.query(&id, vec![1]).unwrap().disabled,
+        Some(Either::Left(true))
+    );
+    assert_eq!(setup.query(&id, vec![1]).unwrap().owner, id);
+}
+
+#[test]
+fn grant_transfer_clears_grants() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+    grant(
+        &mut setup,
+        &id,
+        identity(5),
+        [KeyPermission::Put, KeyPermission::Transfer],
+    )
+    .unwrap();
+
+    transfer(&mut setup, &identity(5), identity(6)).unwrap();
+    assert_eq!(setup.query(&id, vec![1]).unwrap().owner, identity(6));
+    assert!(grants(&setup).is_empty());
+    assert_many_err(
+        setup.put(&identity(5), vec![1], vec![3], None),
+        error::permission_denied(),
+    );
+}
+
+#[test]
+fn revoke_permissions() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+    grant(
+        &mut setup,
+        &id,
+        identity(5),
+        [KeyPermission::Put, KeyPermission::Disable],
+    )
+    .unwrap();
+    grant(&mut setup, &id, identity(6), [KeyPermission::Put]).unwrap();
+
+    revoke(
+        &mut setup,
+        &id,
+        identity(5),
+        Some([KeyPermission::Put].into()),
+    )
+    .unwrap();
+    assert_eq!(
+        grants(&setup),
+        Grants::from([
+            (identity(5), [KeyPermission::Disable].into()),
+            (identity(6), [KeyPermission::Put].into()),
+        ])
+    );
+    assert_many_err(
+        setup.put(&identity(5), vec![1], vec![3], None),
+        error::permission_denied(),
+    );
+
+    revoke(&mut setup, &id, identity(6), None).unwrap();
+    assert_eq!(
+        grants(&setup),
+        Grants::from([(identity(5), [KeyPermission::Disable].into())])
+    );
+    assert_many_err(
+        setup.put(&identity(6), vec![1], vec![3], None),
+        error::permission_denied(),
+    );
+}
+
+#[test]
+fn grant_owner_only() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+    grant(&mut setup, &id, identity(5), [KeyPermission::Put]).unwrap();
+
+    // A grantee cannot grant or revoke.
+    assert_many_err(
+        grant(&mut setup, &identity(5), identity(6), [KeyPermission::Put]),
+        error::permission_denied(),
+    );
+    assert_many_err(
+        revoke(&mut setup, &identity(5), identity(5), None),
+        error::permission_denied(),
+    );
+    assert_many_err(
+        grant(&mut setup, &id, Address::anonymous(), [KeyPermission::Put]),
+        error::anon_alt_denied(),
+    );
+}
+
+#[test]
+fn grant_missing_key() {
+    let mut setup = setup();
+    let id = setup.id;
+    assert_many_err(
+        grant(&mut setup, &id, identity(5), [KeyPermission::Put]),
+        error::key_not_found(),
+    );
+    assert_many_err(
+        setup.module_impl.grants(GrantsArgs {
+            key: vec![1].into(),
+        }),
+        error::key_not_found(),
+    );
+}
+
+#[test]
+fn grant_revoke_log_no_events() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+    let total = event_count(&setup);
+
+    grant(&mut setup, &id, identity(5), [KeyPermission::Put]).unwrap();
+    revoke(&mut setup, &id, identity(5), None).unwrap();
+    assert_eq!(event_count(&setup), total);
+}
@@ -1,9 +1,12 @@
 pub mod common;
 
 use crate::common::{setup, Setup};
+use many_error::ManyError;
 use many_identity::testing::identity;
 use many_identity::Address;
 use many_kvstore::error;
+use many_kvstore::module::delete::DeleteArgs;
+use many_kvstore::module::enable::EnableArgs;
 use many_modules::kvstore::{
     InfoArg, KvStoreModuleBackend, KvStoreTransferModuleBackend, TransferArgs,
 };
@@ -196,3 +199,93 @@ fn put_put_illegal() {
     assert!(put.is_err());
     assert_eq!(put.unwrap_err().code(), error::permission_denied().code());
 }
+
+fn delete(setup: &mut Setup, sender: &Address, key: Vec<u8>) -> Result<(), ManyError> {
+    setup
+        .module_impl
+        .delete(
+            sender,
+            DeleteArgs {
+                key: key.into(),
+                alternative_owner: None,
+            },
+        )
+        .map(|_| ())
+}
+
+fn enable(setup: &mut Setup, sender: &Address, key: Vec<u8>) -> Result<(), ManyError> {
+    setup
+        .module_impl
+        .enable(
+            sender,
+            EnableArgs {
+                key: key.into(),
+                alternative_owner: None,
+            },
+        )
+        .map(|_| ())
+}
+
+#[test]
+fn put_delete() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+
+    let result = delete(&mut setup, &identity(1), vec![1]);
+    assert_eq!(
+        result.unwrap_err().code(),
+        error::permission_denied().code()
+    );
+
+    delete(&mut setup, &id, vec![1]).unwrap();
+    assert_eq!(setup.get(&id, vec![1]).unwrap().value, None);
+    assert_eq!(
+        setup.query(&id, vec![1]).unwrap_err().code(),
+        error::key_not_found().code()
+    );
+
+    let result = delete(&mut setup, &id, vec![1]);
+    assert_eq!(result.unwrap_err().code(), error::key_not_found().code());
+
+    // Anyone can put the key again.
+    setup.put(&identity(1), vec![1], vec![3], None).unwrap();
+    assert_eq!(setup.query(&id, vec![1]).unwrap().owner, identity(1));
+}
+
+#[test]
+fn put_disable_delete() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    setup.block(|setup| setup.put(&id, vec![1], vec![2], None).unwrap());
+    setup.block(|setup| setup.disable(&id, vec![1], None, None).unwrap());
+
+    let (_, result) = setup.block(|setup| delete(setup, &id, vec![1]));
+    assert!(result.is_ok());
+    assert_eq!(setup.get(&id, vec![1]).unwrap().value, None);
+}
+
+#[test]
+fn put_disable_enable() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+    setup.disable(&id, vec![1], None, None).unwrap();
+
+    let result = enable(&mut setup, &identity(1), vec![1]);
+    assert_eq!(
+        result.unwrap_err().code(),
+        error::permission_denied().code()
+    );
+
+    enable(&mut setup, &id, vec![1]).unwrap();
+    let get_value = setup.get(&id, vec![1]).unwrap().value.unwrap();
+    assert_eq!(ByteVec::from(vec![2]), get_value);
+    assert_eq!(
+        setup.query(&id, vec![1]).unwrap().disabled,
+        Some(Either::Left(false))
+    );
+
+    let result = enable(&mut setup, &id, vec![2]);
+    assert_eq!(result.unwrap_err().code(), error::key_not_found().code());
+}
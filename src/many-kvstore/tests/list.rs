@@ -0,0 +1,180 @@
+pub mod common;
+
+use crate::common::{setup, Setup};
+use many_identity::testing::identity;
+use many_kvstore::module::list::{ListArgs, ListReturns};
+use many_types::{CborRange, SortOrder};
+use minicbor::bytes::ByteVec;
+use std::ops::Bound;
+
+fn keys(returns: &ListReturns) -> Vec<Vec<u8>> {
+    returns.keys.iter().map(|k| k.to_vec()).collect()
+}
+
+fn setup_keys(keys: &[&[u8]]) -> Setup {
+    let mut setup = setup();
+    let id = setup.id;
+    for key in keys {
+        setup.put(&id, key.to_vec(), vec![1], None).unwrap();
+    }
+    setup
+}
+
+#[test]
+fn list_all() {
+    let setup = setup_keys(&[b"b", b"a", b"c"]);
+    let returns = setup.module_impl.list_keys(ListArgs::default()).unwrap();
+    assert_eq!(
+        keys(&returns),
+        vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]
+    );
+    assert!(returns.next.is_none());
+}
+
+#[test]
+fn list_descending() {
+    let setup = setup_keys(&[b"b", b"a", b"c"]);
+    let returns = setup
+        .module_impl
+        .list_keys(ListArgs {
+            order: Some(SortOrder::Descending),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(
+        keys(&returns),
+        vec![b"c".to_vec(), b"b".to_vec(), b"a".to_vec()]
+    );
+}
+
+#[test]
+fn list_prefix() {
+    let setup = setup_keys(&[b"a", b"ab", b"abc", b"b", &[b'a', 0xFF]]);
+    let returns = setup
+        .module_impl
+        .list_keys(ListArgs {
+            prefix: Some(b"ab".to_vec().into()),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(keys(&returns), vec![b"ab".to_vec(), b"abc".to_vec()]);
+
+    let returns = setup
+        .module_impl
+        .list_keys(ListArgs {
+            prefix: Some(b"a".to_vec().into()),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(returns.keys.len(), 4);
+}
+
+#[test]
+fn list_range() {
+    let setup = setup_keys(&[b"a", b"b", b"ba", b"c", b"d"]);
+    let range = |start, end| ListArgs {
+        range: Some(CborRange { start, end }),
+        ..Default::default()
+    };
+    let key = |k: &[u8]| ByteVec::from(k.to_vec());
+
+    let returns = setup
+        .module_impl
+        .list_keys(range(
+            Bound::Included(key(b"b")),
+            Bound::Excluded(key(b"d")),
+        ))
+        .unwrap();
+    assert_eq!(
+        keys(&returns),
+        vec![b"b".to_vec(), b"ba".to_vec(), b"c".to_vec()]
+    );
+
+    let returns = setup
+        .module_impl
+        .list_keys(range(
+            Bound::Excluded(key(b"b")),
+            Bound::Included(key(b"d")),
+        ))
+        .unwrap();
+    assert_eq!(
+        keys(&returns),
+        vec![b"ba".to_vec(), b"c".to_vec(), b"d".to_vec()]
+    );
+
+    let returns = setup
+        .module_impl
+        .list_keys(range(Bound::Unbounded, Bound::Included(key(b"b"))))
+        .unwrap();
+    assert_eq!(keys(&returns), vec![b"a".to_vec(), b"b".to_vec()]);
+}
+
+#[test]
+fn list_continuation() {
+    let setup = setup_keys(&[b"a", b"b", b"c", b"d", b"e"]);
+    for order in [SortOrder::Ascending, SortOrder::Descending] {
+        let mut listed = Vec::new();
+        let mut after: Option<ByteVec> = None;
+        loop {
+            let returns = setup
+                .module_impl
+                .list_keys(ListArgs {
+                    order: Some(order),
+                    count: Some(2),
+                    after: after.clone(),
+                    ..Default::default()
+                })
+                .unwrap();
+            assert!(returns.keys.len() <= 2);
+            listed.extend(keys(&returns));
+            after = returns.next;
+            if after.is_none() {
+                break;
+            }
+        }
+
+        let mut expected = vec![b"a", b"b", b"c", b"d", b"e"]
+            .into_iter()
+            .map(|k| k.to_vec())
+            .collect::<Vec<_>>();
+        if matches!(order, SortOrder::Descending) {
+            expected.reverse();
+        }
+        assert_eq!(listed, expected);
+    }
+}
+
+#[test]
+fn list_skips_disabled() {
+    let mut setup = setup_keys(&[b"a", b"b", b"c"]);
+    let id = setup.id;
+    setup.disable(&id, b"b".to_vec(), None, None).unwrap();
+
+    let returns = setup.module_impl.list_keys(ListArgs::default()).unwrap();
+    assert_eq!(keys(&returns), vec![b"a".to_vec(), b"c".to_vec()]);
+}
+
+#[test]
+fn list_owner() {
+    let mut setup = setup_keys(&[b"a", b"c"]);
+    let other = identity(1);
+    setup.put(&other, b"b".to_vec(), vec![1], None).unwrap();
+
+    let returns = setup
+        .module_impl
+        .list_keys(ListArgs {
+            owner: Some(other),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(keys(&returns), vec![b"b".to_vec()]);
+
+    let returns = setup
+        .module_impl
+        .list_keys(ListArgs {
+            owner: Some(setup.id),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(keys(&returns), vec![b"a".to_vec(), b"c".to_vec()]);
+}
@@ -1,6 +1,10 @@
 use many_identity::testing::identity;
 use many_kvstore::error;
+use many_kvstore::module::usage::UsageArgs;
+use many_kvstore::module::version;
 use many_kvstore::module::KvStoreModuleImpl;
+use many_kvstore::storage::usage::KvStoreLimits;
+use many_modules::abci_backend::ManyAbciModuleBackend;
 use many_modules::kvstore::{GetArgs, KvStoreCommandsModuleBackend, KvStoreModuleBackend, PutArgs};
 
 /// Verify persistent storage can be re-loaded
@@ -90,3 +94,161 @@ fn load() {
         .unwrap();
     assert_eq!(v, vec![0].into());
 }
+
+/// Verify the initial state can be given by the genesis, and only once
+#[test]
+fn init_chain_with_state() {
+    let path = tempfile::tempdir().unwrap().into_path();
+    let init = r#"{
+        identity: "mahukzwuwgt3porn6q4vq4xu3mwy5gyskhouryzbscq7wb2iow",
+        acl: {
+          "010203": { owner: "maeaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaiye" }
+        }
+    }"#;
+
+    let mut expected = KvStoreModuleImpl::new(
+        json5::from_str(init).unwrap(),
+        tempfile::tempdir().unwrap(),
+        true,
+    )
+    .unwrap();
+
+    let mut module_impl = KvStoreModuleImpl::new_for_genesis(path).unwrap();
+    let hash = module_impl
+        .init_chain_with_state(json5::from_str(init).unwrap())
+        .unwrap();
+    assert_eq!(
+        hash,
+        ManyAbciModuleBackend::info(&expected)
+            .unwrap()
+            .hash
+            .to_vec()
+    );
+
+    // The ACL of the genesis state is applied
+    let p = module_impl.put(
+        &identity(2),
+        PutArgs {
+            key: vec![1, 2, 3].into(),
+            value: vec![0].into(),
+            alternative_owner: None,
+        },
+    );
+    assert_eq!(p.unwrap_err().code(), error::permission_denied().code());
+
+    let r = module_impl.init_chain_with_state(json5::from_str(init).unwrap());
+    assert_eq!(
+        r.unwrap_err().code(),
+        error::genesis_state_conflict().code()
+    );
+
+    // A storage initialized from `--state` refuses the genesis state
+    let r = expected.init_chain_with_state(json5::from_str(init).unwrap());
+    assert_eq!(
+        r.unwrap_err().code(),
+        error::genesis_state_conflict().code()
+    );
+}
+
+/// Verify a corrupted height is an error when loading, not a panic
+#[test]
+fn load_invalid_height() {
+    let path = tempfile::tempdir().unwrap().into_path();
+    let init = r#"{
+        identity: "mahukzwuwgt3porn6q4vq4xu3mwy5gyskhouryzbscq7wb2iow",
+        acl: {}
+    }"#;
+    {
+        let _ = KvStoreModuleImpl::new(json5::from_str(init).unwrap(), path.clone(), true).unwrap();
+    }
+
+    {
+        let mut merk = merk::Merk::open(path.clone()).unwrap();
+        merk.apply(&[(b"/height".to_vec(), merk::Op::Put(vec![1, 2, 3]))])
+            .unwrap();
+        merk.commit(&[]).unwrap();
+    }
+
+    assert!(KvStoreModuleImpl::load(path, true).is_err());
+}
+
+/// Verify a corrupted version is an error, not a panic
+#[test]
+fn get_invalid_version() {
+    let path = tempfile::tempdir().unwrap().into_path();
+    let init = r#"{
+        identity: "mahukzwuwgt3porn6q4vq4xu3mwy5gyskhouryzbscq7wb2iow",
+        acl: {}
+    }"#;
+    {
+        let _ = KvStoreModuleImpl::new(json5::from_str(init).unwrap(), path.clone(), true).unwrap();
+    }
+
+    {
+        let mut merk = merk::Merk::open(path.clone()).unwrap();
+        merk.apply(&[(b"v\x01".to_vec(), merk::Op::Put(vec![1, 2, 3]))])
+            .unwrap();
+        merk.commit(&[]).unwrap();
+    }
+
+    let module_impl = KvStoreModuleImpl::load(path, true).unwrap();
+    let result = module_impl.get_versioned(version::GetArgs {
+        key: vec![1].into(),
+        version: None,
+    });
+    assert_eq!(
+        result.unwrap_err().code(),
+        error::storage_get_failed("").code()
+    );
+}
+
+/// Verify a corrupted usage is an error, not a panic
+#[test]
+fn get_invalid_usage() {
+    let path = tempfile::tempdir().unwrap().into_path();
+    let init = r#"{
+        identity: "mahukzwuwgt3porn6q4vq4xu3mwy5gyskhouryzbscq7wb2iow",
+        acl: {}
+    }"#;
+    {
+        let _ = KvStoreModuleImpl::new(json5::from_str(init).unwrap(), path.clone(), true).unwrap();
+    }
+
+    {
+        let mut merk = merk::Merk::open(path.clone()).unwrap();
+        let key = [b"/usage/".as_slice(), &identity(1).to_vec()].concat();
+        merk.apply(&[(key, merk::Op::Put(vec![1, 2, 3]))]).unwrap();
+        merk.commit(&[]).unwrap();
+    }
+
+    let module_impl = KvStoreModuleImpl::load(path, true).unwrap();
+    let result = module_impl.usage(&identity(1), UsageArgs { owner: None });
+    assert_eq!(
+        result.unwrap_err().code(),
+        error::storage_get_failed("").code()
+    );
+}
+
+/// Verify the settings of the store are kept in the persistent storage
+#[test]
+fn load_settings() {
+    let path = tempfile::tempdir().unwrap().into_path();
+    let init = r#"{
+        identity: "mahukzwuwgt3porn6q4vq4xu3mwy5gyskhouryzbscq7wb2iow",
+        acl: {}
+    }"#;
+    let limits = KvStoreLimits {
+        max_value_size: Some(10),
+        owner_quota: Some(100),
+    };
+    {
+        let mut module_impl =
+            KvStoreModuleImpl::new(json5::from_str(init).unwrap(), path.clone(), true).unwrap();
+        module_impl.set_hash_event_values(true).unwrap();
+        module_impl.set_limits(limits).unwrap();
+    }
+
+    let module_impl = KvStoreModuleImpl::load(path, true).unwrap();
+    assert!(module_impl.hash_event_values());
+    assert_eq!(module_impl.limits(), limits);
+}
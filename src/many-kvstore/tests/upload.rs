@@ -0,0 +1,241 @@
+pub mod common;
+
+use crate::common::{assert_many_err, setup, Setup};
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_kvstore::error;
+use many_kvstore::module::upload::{BeginUploadArgs, PutChunkArgs, UploadArgs};
+use many_kvstore::module::usage::UsageArgs;
+use many_kvstore::module::version::GetArgs;
+use many_kvstore::storage::chunk::UPLOAD_TIMEOUT;
+use many_kvstore::storage::usage::KvStoreLimits;
+use many_kvstore::storage::version::MAXIMUM_VERSIONS;
+use sha3::{Digest, Sha3_256};
+
+fn begin(setup: &mut Setup, sender: &Address, value: &[u8]) -> Result<u64, ManyError> {
+    Ok(setup
+        .module_impl
+        .begin_upload(
+            sender,
+            BeginUploadArgs {
+                key: vec![1].into(),
+                size: value.len() as u64,
+                hash: Sha3_256::digest(value).to_vec().into(),
+                alternative_owner: None,
+                expires: None,
+            },
+        )?
+        .upload)
+}
+
+fn put_chunk(
+    setup: &mut Setup,
+    sender: &Address,
+    upload: u64,
+    index: u32,
+    data: &[u8],
+) -> Result<(), ManyError> {
+    let returns = setup.module_impl.put_chunk(
+        sender,
+        PutChunkArgs {
+            upload,
+            index,
+            data: data.to_vec().into(),
+        },
+    )?;
+    assert_eq!(returns.hash.as_slice(), Sha3_256::digest(data).as_slice());
+    Ok(())
+}
+
+fn finalize(setup: &mut Setup, sender: &Address, upload: u64) -> Result<u64, ManyError> {
+    Ok(setup
+        .module_impl
+        .finalize_upload(sender, UploadArgs { upload })?
+        .version)
+}
+
+/// Upload a value in chunks of 2 bytes.
+fn upload(setup: &mut Setup, sender: &Address, value: &[u8]) -> Result<u64, ManyError> {
+    let upload = begin(setup, sender, value)?;
+    for (index, chunk) in value.chunks(2).enumerate() {
+        put_chunk(setup, sender, upload, index as u32, chunk)?;
+    }
+    finalize(setup, sender, upload)
+}
+
+fn get_at(setup: &Setup, version: u64) -> Result<Option<Vec<u8>>, ManyError> {
+    Ok(setup
+        .module_impl
+        .get_versioned(GetArgs {
+            key: vec![1].into(),
+            version: Some(version),
+        })?
+        .value
+        .map(|value| value.to_vec()))
+}
+
+fn usage(setup: &Setup, owner: &Address) -> u64 {
+    setup
+        .module_impl
+        .usage(owner, UsageArgs::default())
+        .unwrap()
+        .usage
+}
+
+#[test]
+fn upload_get() {
+    let mut setup = setup();
+    let id = setup.id;
+    assert_eq!(upload(&mut setup, &id, b"foobar!").unwrap(), 1);
+
+    assert_eq!(
+        setup.get(&id, vec![1]).unwrap().value,
+        Some(b"foobar!".to_vec().into())
+    );
+    assert_eq!(setup.query(&id, vec![1]).unwrap().owner, id);
+
+    setup.put(&id, vec![1], b"baz".to_vec(), None).unwrap();
+    assert_eq!(get_at(&setup, 1).unwrap(), Some(b"foobar!".to_vec()));
+    assert_eq!(get_at(&setup, 2).unwrap(), Some(b"baz".to_vec()));
+}
+
+#[test]
+fn upload_pruned() {
+    let mut setup = setup();
+    let id = setup.id;
+    upload(&mut setup, &id, b"foobar").unwrap();
+    for i in 0..MAXIMUM_VERSIONS {
+        setup.put(&id, vec![1], vec![i as u8], None).unwrap();
+    }
+    assert_many_err(get_at(&setup, 1), error::version_not_found(1));
+}
+
+#[test]
+fn upload_chunk_errors() {
+    let mut setup = setup();
+    let id = setup.id;
+    let upload = begin(&mut setup, &id, b"foobar").unwrap();
+
+    assert_many_err(
+        put_chunk(&mut setup, &id, upload, 1, b"fo"),
+        error::chunk_out_of_order(0),
+    );
+    assert_many_err(
+        put_chunk(&mut setup, &identity(5), upload, 0, b"fo"),
+        error::permission_denied(),
+    );
+    assert_many_err(
+        put_chunk(&mut setup, &id, upload + 1, 0, b"fo"),
+        error::upload_not_found(upload + 1),
+    );
+    assert_many_err(
+        put_chunk(&mut setup, &id, upload, 0, b"foobar!"),
+        error::upload_size_mismatch(6, 7),
+    );
+
+    put_chunk(&mut setup, &id, upload, 0, b"foo").unwrap();
+    assert_many_err(
+        finalize(&mut setup, &id, upload),
+        error::upload_size_mismatch(6, 3),
+    );
+    put_chunk(&mut setup, &id, upload, 1, b"baz").unwrap();
+    assert_many_err(finalize(&mut setup, &id, upload), error::hash_mismatch());
+    assert_eq!(setup.get(&id, vec![1]).unwrap().value, None);
+}
+
+#[test]
+fn upload_abort() {
+    let mut setup = setup();
+    let id = setup.id;
+    let upload = begin(&mut setup, &id, b"foobar").unwrap();
+    put_chunk(&mut setup, &id, upload, 0, b"foo").unwrap();
+
+    setup
+        .module_impl
+        .abort_upload(&id, UploadArgs { upload })
+        .unwrap();
+    assert_many_err(
+        put_chunk(&mut setup, &id, upload, 1, b"bar"),
+        error::upload_not_found(upload),
+    );
+}
+
+#[test]
+fn upload_permission_denied() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+    assert_many_err(
+        begin(&mut setup, &identity(5), b"foobar"),
+        error::permission_denied(),
+    );
+}
+
+#[test]
+fn upload_key_taken() {
+    let mut setup = setup();
+    let id = setup.id;
+    let upload = begin(&mut setup, &identity(5), b"foobar").unwrap();
+    put_chunk(&mut setup, &identity(5), upload, 0, b"foobar").unwrap();
+
+    // Another address puts the key before the upload ends.
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+    assert_many_err(
+        finalize(&mut setup, &identity(5), upload),
+        error::permission_denied(),
+    );
+}
+
+#[test]
+fn upload_usage() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup
+        .module_impl
+        .set_limits(KvStoreLimits {
+            max_value_size: Some(8),
+            owner_quota: Some(10),
+        })
+        .unwrap();
+
+    assert_many_err(
+        begin(&mut setup, &id, b"foobarbaz"),
+        error::value_too_large(8),
+    );
+
+    // An upload counts against the quota from its start.
+    let aborted = begin(&mut setup, &id, b"foobar").unwrap();
+    assert_eq!(usage(&setup, &id), 6);
+    assert_many_err(begin(&mut setup, &id, b"foobar"), error::quota_exceeded(10));
+
+    setup
+        .module_impl
+        .abort_upload(&id, UploadArgs { upload: aborted })
+        .unwrap();
+    assert_eq!(usage(&setup, &id), 0);
+
+    // A finalized upload only counts as the value of its key.
+    upload(&mut setup, &id, b"foobar").unwrap();
+    assert_eq!(usage(&setup, &id), 6);
+}
+
+#[test]
+fn upload_abandoned() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    let (_, upload) = setup.block(|setup| begin(setup, &id, b"foobar").unwrap());
+
+    setup.skip(UPLOAD_TIMEOUT - 2);
+    setup.block(|setup| put_chunk(setup, &id, upload, 0, b"foo").unwrap());
+    assert_eq!(usage(&setup, &id), 6);
+
+    // The upload is removed at the end of the block `UPLOAD_TIMEOUT` seconds
+    // after it started.
+    setup.block(|_| {});
+    assert_many_err(
+        put_chunk(&mut setup, &id, upload, 1, b"bar"),
+        error::upload_not_found(upload),
+    );
+    assert_eq!(usage(&setup, &id), 0);
+}
@@ -0,0 +1,198 @@
+pub mod common;
+
+use crate::common::{assert_many_err, setup, Setup};
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_kvstore::error;
+use many_kvstore::module::delete::DeleteArgs;
+use many_kvstore::module::enable::EnableArgs;
+use many_kvstore::module::usage::{UsageArgs, UsageReturns};
+use many_kvstore::storage::usage::KvStoreLimits;
+use many_kvstore::storage::version::MAXIMUM_VERSIONS;
+use many_modules::kvstore::{KvStoreTransferModuleBackend, TransferArgs};
+
+fn usage(setup: &Setup, owner: &Address) -> u64 {
+    setup
+        .module_impl
+        .usage(
+            owner,
+            UsageArgs {
+                owner: Some(*owner),
+            },
+        )
+        .unwrap()
+        .usage
+}
+
+fn limited(max_value_size: Option<u64>, owner_quota: Option<u64>) -> Setup {
+    let mut setup = setup();
+    setup
+        .module_impl
+        .set_limits(KvStoreLimits {
+            max_value_size,
+            owner_quota,
+        })
+        .unwrap();
+    setup
+}
+
+#[test]
+fn usage_put() {
+    let mut setup = limited(Some(10), Some(20));
+    let id = setup.id;
+    assert_eq!(
+        setup.module_impl.usage(&id, UsageArgs::default()).unwrap(),
+        UsageReturns {
+            usage: 0,
+            quota: Some(20),
+            max_value_size: Some(10),
+        }
+    );
+
+    setup.put(&id, vec![1], vec![1; 5], None).unwrap();
+    setup.put(&id, vec![2], vec![1; 3], None).unwrap();
+    assert_eq!(usage(&setup, &id), 8);
+
+    // The previous versions of a key are kept, and still count.
+    setup.put(&id, vec![1], vec![1; 2], None).unwrap();
+    assert_eq!(usage(&setup, &id), 10);
+    assert_eq!(usage(&setup, &identity(5)), 0);
+}
+
+#[test]
+fn usage_pruned_versions() {
+    let mut setup = setup();
+    let id = setup.id;
+    for _ in 0..MAXIMUM_VERSIONS {
+        setup.put(&id, vec![1], vec![1; 2], None).unwrap();
+    }
+    assert_eq!(usage(&setup, &id), 2 * MAXIMUM_VERSIONS);
+
+    // The oldest version is pruned.
+    setup.put(&id, vec![1], vec![1; 3], None).unwrap();
+    assert_eq!(usage(&setup, &id), 2 * MAXIMUM_VERSIONS + 1);
+}
+
+#[test]
+fn usage_transfer() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![1; 5], None).unwrap();
+
+    setup
+        .module_impl
+        .transfer(
+            &id,
+            TransferArgs {
+                key: vec![1].into(),
+                alternative_owner: None,
+                new_owner: identity(5),
+            },
+        )
+        .unwrap();
+    assert_eq!(usage(&setup, &id), 0);
+    assert_eq!(usage(&setup, &identity(5)), 5);
+}
+
+#[test]
+fn usage_disable_enable() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![1; 5], None).unwrap();
+
+    setup.disable(&id, vec![1], None, None).unwrap();
+    assert_eq!(usage(&setup, &id), 0);
+
+    setup
+        .module_impl
+        .enable(
+            &id,
+            EnableArgs {
+                key: vec![1].into(),
+                alternative_owner: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(usage(&setup, &id), 5);
+}
+
+#[test]
+fn usage_delete() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![1; 5], None).unwrap();
+
+    setup
+        .module_impl
+        .delete(
+            &id,
+            DeleteArgs {
+                key: vec![1].into(),
+                alternative_owner: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(usage(&setup, &id), 0);
+}
+
+#[test]
+fn value_too_large() {
+    let mut setup = limited(Some(4), None);
+    let id = setup.id;
+    assert_many_err(
+        setup.put(&id, vec![1], vec![1; 5], None),
+        error::value_too_large(4),
+    );
+    setup.put(&id, vec![1], vec![1; 4], None).unwrap();
+}
+
+#[test]
+fn quota_exceeded() {
+    let mut setup = limited(None, Some(8));
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![1; 5], None).unwrap();
+    assert_many_err(
+        setup.put(&id, vec![2], vec![1; 4], None),
+        error::quota_exceeded(8),
+    );
+    assert_eq!(setup.get(&id, vec![2]).unwrap().value, None);
+
+    // The previous versions of a key count, so putting it again does not
+    // free space for other values.
+    setup.put(&id, vec![1], vec![1; 2], None).unwrap();
+    assert_many_err(
+        setup.put(&id, vec![2], vec![1; 4], None),
+        error::quota_exceeded(8),
+    );
+
+    // Deleting a key does.
+    setup
+        .module_impl
+        .delete(
+            &id,
+            DeleteArgs {
+                key: vec![1].into(),
+                alternative_owner: None,
+            },
+        )
+        .unwrap();
+    setup.put(&id, vec![2], vec![1; 4], None).unwrap();
+    assert_eq!(usage(&setup, &id), 4);
+
+    // The new owner of a key must have room for its value.
+    setup.put(&identity(5), vec![3], vec![1; 8], None).unwrap();
+    assert_many_err(
+        setup
+            .module_impl
+            .transfer(
+                &id,
+                TransferArgs {
+                    key: vec![2].into(),
+                    alternative_owner: None,
+                    new_owner: identity(5),
+                },
+            )
+            .map(|_| ()),
+        error::quota_exceeded(8),
+    );
+}
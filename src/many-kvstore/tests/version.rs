@@ -0,0 +1,150 @@
+pub mod common;
+
+use crate::common::{setup, Setup};
+use many_error::ManyError;
+use many_identity::Address;
+use many_kvstore::error;
+use many_kvstore::module::delete::DeleteArgs;
+use many_kvstore::module::version::{GetArgs, GetReturns, PutArgs, PutReturns};
+use many_kvstore::storage::version::MAXIMUM_VERSIONS;
+
+fn put(setup: &mut Setup, value: u64, if_version: Option<u64>) -> Result<PutReturns, ManyError> {
+    let id: Address = setup.id;
+    setup.module_impl.put_versioned(
+        &id,
+        PutArgs {
+            key: vec![1].into(),
+            value: value.to_be_bytes().to_vec().into(),
+            alternative_owner: None,
+            if_version,
+            expires: None,
+        },
+    )
+}
+
+fn get(setup: &Setup, version: Option<u64>) -> Result<GetReturns, ManyError> {
+    setup.module_impl.get_versioned(GetArgs {
+        key: vec![1].into(),
+        version,
+    })
+}
+
+#[test]
+fn versions() {
+    let mut setup = setup();
+    assert_eq!(get(&setup, None).unwrap().version, 0);
+
+    for i in 1..=3 {
+        assert_eq!(put(&mut setup, i, None).unwrap().version, i);
+    }
+
+    let current = get(&setup, None).unwrap();
+    assert_eq!(current.version, 3);
+    assert_eq!(current.value.unwrap().to_vec(), 3u64.to_be_bytes().to_vec());
+
+    for i in 1..=3 {
+        let returns = get(&setup, Some(i)).unwrap();
+        assert_eq!(returns.version, i);
+        assert_eq!(returns.value.unwrap().to_vec(), i.to_be_bytes().to_vec());
+    }
+
+    let err = get(&setup, Some(4)).unwrap_err();
+    assert_eq!(err.code(), error::version_not_found(4).code());
+}
+
+#[test]
+fn versions_are_pruned() {
+    let mut setup = setup();
+    let last = MAXIMUM_VERSIONS + 5;
+    for i in 1..=last {
+        put(&mut setup, i, None).unwrap();
+    }
+
+    for i in 1..=last {
+        let returns = get(&setup, Some(i));
+        if i + MAXIMUM_VERSIONS <= last {
+            assert_eq!(
+                returns.unwrap_err().code(),
+                error::version_not_found(i).code()
+            );
+        } else {
+            assert_eq!(
+                returns.unwrap().value.unwrap().to_vec(),
+                i.to_be_bytes().to_vec()
+            );
+        }
+    }
+}
+
+#[test]
+fn versions_block() {
+    let mut setup = Setup::new(true);
+    let (_, version) = setup.block(|setup| {
+        put(setup, 1, None).unwrap();
+        put(setup, 2, Some(1)).unwrap().version
+    });
+    assert_eq!(version, 2);
+    assert_eq!(
+        get(&setup, Some(1)).unwrap().value.unwrap().to_vec(),
+        1u64.to_be_bytes().to_vec()
+    );
+}
+
+#[test]
+fn conditional_put() {
+    let mut setup = setup();
+    assert_eq!(put(&mut setup, 1, Some(0)).unwrap().version, 1);
+
+    // The key already exists.
+    let err = put(&mut setup, 2, Some(0)).unwrap_err();
+    assert_eq!(err.code(), error::version_mismatch(0, 1).code());
+
+    assert_eq!(put(&mut setup, 2, Some(1)).unwrap().version, 2);
+
+    // Another writer put version 2 in the meantime.
+    let err = put(&mut setup, 3, Some(1)).unwrap_err();
+    assert_eq!(err.code(), error::version_mismatch(1, 2).code());
+    assert_eq!(get(&setup, None).unwrap().version, 2);
+}
+
+#[test]
+fn disabled_versions() {
+    let mut setup = setup();
+    put(&mut setup, 1, None).unwrap();
+    let id = setup.id;
+    setup.disable(&id, vec![1], None, None).unwrap();
+
+    let err = get(&setup, Some(1)).unwrap_err();
+    assert_eq!(err.code(), error::key_disabled().code());
+
+    // Putting the key again enables it, with the next version.
+    assert_eq!(put(&mut setup, 2, None).unwrap().version, 2);
+}
+
+#[test]
+fn deleted_versions() {
+    let mut setup = setup();
+    put(&mut setup, 1, None).unwrap();
+    put(&mut setup, 2, None).unwrap();
+    let id = setup.id;
+    setup
+        .module_impl
+        .delete(
+            &id,
+            DeleteArgs {
+                key: vec![1].into(),
+                alternative_owner: None,
+            },
+        )
+        .unwrap();
+
+    // The history of the key is deleted with it.
+    for i in 1..=2 {
+        let err = get(&setup, Some(i)).unwrap_err();
+        assert_eq!(err.code(), error::version_not_found(i).code());
+    }
+    assert_eq!(get(&setup, None).unwrap().version, 0);
+
+    // Putting the key again starts over.
+    assert_eq!(put(&mut setup, 3, None).unwrap().version, 1);
+}
@@ -17,6 +17,8 @@ define_attribute_many_error!(
         9: pub fn amount_is_zero()
             => "Unable to send zero (0) token.",
         10: pub fn storage_key_not_found(key) => "Key not found in storage: {key:?}.",
+        11: pub fn genesis_state_conflict()
+            => "The ledger was initialized from --state or an existing store, but the genesis also has an application state.",
     }
 );
 
@@ -40,6 +42,18 @@ define_attribute_many_error!(
     }
 );
 
+define_attribute_many_error!(
+    attribute 13 => {
+        1: pub fn invalid_validators_sender() => "Unauthorised validators endpoints sender.",
+        2: pub fn invalid_validator_key(len) => "Invalid validator public key length: {len}, expected 32.",
+        3: pub fn unknown_validator(key) => "Unknown validator: {key}.",
+        4: pub fn validator_power_too_large(max)
+            => "The total voting power of the validators must not exceed {max}.",
+        5: pub fn validators_not_managed()
+            => "This ledger does not manage the validator set.",
+    }
+);
+
 define_application_many_error!(
     {
         1: pub fn storage_apply_failed(desc) => "Unable to apply change to persistent storage: {desc}.",
@@ -47,5 +61,7 @@ define_application_many_error!(
         3: pub fn storage_commit_failed(desc) => "Unable to commit data to persistent storage: {desc}.",
         4: pub fn storage_open_failed(desc) => "Unable to open persistent storage: {desc}.",
         5: pub fn unable_to_load_migrations(desc) => "Unable to load migrations: {desc}.",
+        6: pub fn incomplete_commit(height)
+            => "The persistent storage was interrupted while committing block {height}, and its state is between two blocks. Restore it from a backup at a height lower than {height}, then restart to let tendermint replay the missing blocks.",
     }
 );
@@ -142,12 +142,20 @@ pub struct InitialStateJson {
     pub id_store_seed: Option<u64>,
     pub id_store_keys: Option<BTreeMap<String, String>>,
     pub hash: Option<String>,
+    /// Keep the validator set in the state, starting with the validators of
+    /// the genesis, so it can be changed with `validators.update`.
+    pub manage_validators: Option<bool>,
 }
 
 impl InitialStateJson {
     pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let content = std::fs::read_to_string(path.as_ref()).map_err(Box::new)?;
-        let s: InitialStateJson = json5::from_str(&content).map_err(Box::new)?;
+        Self::from_json_str(&content)
+    }
+
+    /// Parse an initial state, e.g. the `app_state` of a tendermint genesis.
+    pub fn from_json_str(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let s: InitialStateJson = json5::from_str(content).map_err(Box::new)?;
         if let (Some(token_identity), Some(account_identity)) =
             (s.token_identity, s.account_identity)
         {
@@ -1,13 +1,15 @@
 #![feature(used_with_arg)]
 
 use clap::Parser;
+use many_abci::backend::BackendClient;
+use many_abci::frontend::{self, FrontendOptions};
 use many_identity::verifiers::AnonymousVerifier;
 use many_identity::{Address, Identity};
 use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
 use many_identity_webauthn::WebAuthnVerifier;
 use many_migration::MigrationConfig;
 use many_modules::account::features::Feature;
-use many_modules::{abci_backend, account, data, events, idstore, ledger};
+use many_modules::{account, data, events, idstore, ledger};
 use many_protocol::ManyUrl;
 use many_server::transport::http::HttpServer;
 use many_server::ManyServer;
@@ -16,7 +18,7 @@ use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tracing::level_filters::LevelFilter;
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 
 use crate::allow_addrs::AllowAddrsModule;
 
@@ -24,7 +26,9 @@ use crate::allow_addrs::AllowAddrsModule;
 use crate::idstore_webauthn::IdStoreWebAuthnModule;
 use crate::json::InitialStateJson;
 use crate::migration::MIGRATIONS;
+use crate::module::abci::LedgerAbciModule;
 use crate::module::account::AccountFeatureModule;
+use crate::module::validators::ValidatorsModule;
 use module::*;
 
 mod error;
@@ -63,7 +67,30 @@ struct Opts {
     #[clap(long)]
     abci: bool,
 
-    /// Path of a state file (that will be used for the initial setup).
+    /// Run the ABCI application in this process instead of a separate
+    /// `many-abci`, binding the ABCI server to this address. The MANY frontend
+    /// listens on `--addr` and the ledger itself is not exposed. Only the
+    /// ledger can be embedded; many-kvstore always needs a separate
+    /// `many-abci`.
+    #[clap(long, requires = "tendermint")]
+    embedded_abci: Option<String>,
+
+    /// URL for the tendermint server, when using `--embedded-abci`.
+    #[clap(long)]
+    tendermint: Option<String>,
+
+    /// The default ABCI server read buffer size, in bytes, when using `--embedded-abci`.
+    #[clap(long, default_value = "1048576")]
+    abci_read_buf_size: usize,
+
+    /// Number of worker threads used to forward ABCI requests to the ledger,
+    /// when using `--embedded-abci`.
+    #[clap(long, default_value = "4")]
+    abci_workers: usize,
+
+    /// Path of a state file (that will be used for the initial setup). In ABCI
+    /// mode, the initial state can instead be the `app_state` of the tendermint
+    /// genesis.
     #[clap(long)]
     state: Option<PathBuf>,
 
@@ -128,6 +155,10 @@ fn main() {
         pem,
         addr,
         abci,
+        embedded_abci,
+        tendermint,
+        abci_read_buf_size,
+        abci_workers,
         mut state,
         persistent,
         clean,
@@ -200,6 +231,9 @@ fn main() {
         state = None;
     }
 
+    // The embedded ABCI application calls the `abci.*` endpoints of the ledger.
+    let abci = abci || embedded_abci.is_some();
+
     let pem = std::fs::read_to_string(pem).expect("Could not read PEM file.");
     let key = CoseKeyIdentity::from_pem(pem).expect("Could not generate identity from PEM file.");
     info!(address = key.address().to_string().as_str());
@@ -237,7 +271,14 @@ fn main() {
             }
         }
 
-        LedgerModuleImpl::load(maybe_migrations, persistent, abci).unwrap()
+        let path = persistent.display().to_string();
+        match LedgerModuleImpl::load(maybe_migrations, persistent, abci) {
+            Ok(module_impl) => module_impl,
+            Err(e) => {
+                error!("Could not load the persistent store {path}: {e}");
+                std::process::exit(1);
+            }
+        }
     } else if let Some(state) = state {
         #[cfg(feature = "balance_testing")]
         {
@@ -272,6 +313,9 @@ fn main() {
 
         #[cfg(not(feature = "balance_testing"))]
         LedgerModuleImpl::new(state, maybe_migrations, persistent, abci).unwrap()
+    } else if abci {
+        info!("No initial state, waiting for the genesis of the chain.");
+        LedgerModuleImpl::new_for_genesis(maybe_migrations, persistent).unwrap()
     } else {
         panic!("Persistent store or staging file not found.")
     };
@@ -279,11 +323,11 @@ fn main() {
 
     let many = ManyServer::simple(
         "many-ledger",
-        key,
+        key.clone(),
         (
             AnonymousVerifier,
             CoseKeyVerifier,
-            WebAuthnVerifier::new(allow_origin),
+            WebAuthnVerifier::new(allow_origin.clone()),
         ),
         Some(env!("CARGO_PKG_VERSION").to_string()),
     );
@@ -334,12 +378,35 @@ fn main() {
             module_impl.clone(),
         ));
         s.add_module(data::DataModule::new(module_impl.clone()));
+        s.add_module(ValidatorsModule::new(module_impl.clone()));
         if abci {
             s.set_timeout(u64::MAX);
-            s.add_module(abci_backend::AbciModule::new(module_impl));
+            s.add_module(LedgerAbciModule::new(module_impl));
         }
     }
 
+    if let Some(abci_addr) = embedded_abci {
+        let backend =
+            BackendClient::local(many, abci_workers).expect("Could not create the ABCI backend.");
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(frontend::run(
+            vec![backend],
+            FrontendOptions {
+                abci: abci_addr,
+                // Safe unwrap. Clap requires --tendermint with --embedded-abci.
+                tendermint: tendermint.unwrap(),
+                many: addr.to_string(),
+                identity: key,
+                abci_read_buf_size,
+                allow_origin,
+                // The ledger modules already filter commands.
+                allow_addrs: None,
+                direct_queries: false,
+            },
+        ));
+        return;
+    }
+
     let mut many_server = HttpServer::new(many);
 
     signal_hook::flag::register(signal_hook::consts::SIGTERM, many_server.term_signal())
@@ -7,7 +7,7 @@ use std::fmt::Debug;
 use std::path::Path;
 use tracing::info;
 
-mod abci;
+pub mod abci;
 pub mod account;
 pub mod allow_addrs;
 mod data;
@@ -19,6 +19,7 @@ mod ledger_commands;
 mod ledger_mintburn;
 mod ledger_tokens;
 mod multisig;
+pub mod validators;
 
 /// A simple ledger that keeps transactions in memory.
 #[derive(Debug)]
@@ -26,6 +27,44 @@ pub struct LedgerModuleImpl {
     storage: LedgerStorage,
 }
 
+/// Write the initial state in an empty storage, and commit it.
+fn init_storage(storage: &mut LedgerStorage, state: InitialStateJson) -> Result<(), ManyError> {
+    let symbols = state.symbols();
+    let balances = state.balances()?;
+    let symbols_meta = state
+        .symbols_meta
+        .map(|b| b.into_iter().map(|(k, v)| (k, v.into())).collect());
+    let accounts = state
+        .accounts
+        .map(|a| a.into_iter().map(|v| v.into()).collect());
+
+    storage.init_identity(&symbols, state.identity)?;
+    storage.init_balances(&symbols, &balances)?;
+    storage.init_idstore(state.id_store_seed, state.id_store_keys)?;
+    storage.init_tokens(
+        &symbols,
+        symbols_meta,
+        state.token_identity,
+        state.token_next_subresource,
+        balances,
+    )?;
+    storage.init_account(state.account_identity, accounts)?;
+    if state.manage_validators == Some(true) {
+        storage.init_validator_set()?;
+    }
+    storage.build_in_place()?;
+
+    if let Some(h) = state.hash {
+        // Verify the hash.
+        let actual = hex::encode(storage.hash());
+        if actual != h {
+            return Err(error::invalid_initial_state(h, actual));
+        }
+    }
+
+    Ok(())
+}
+
 impl LedgerModuleImpl {
     pub fn new<P: AsRef<Path>>(
         state: InitialStateJson,
@@ -33,37 +72,9 @@ impl LedgerModuleImpl {
         persistence_store_path: P,
         blockchain: bool,
     ) -> Result<Self, ManyError> {
-        let symbols = state.symbols();
-        let balances = state.balances()?;
-        let symbols_meta = state
-            .symbols_meta
-            .map(|b| b.into_iter().map(|(k, v)| (k, v.into())).collect());
-        let accounts = state
-            .accounts
-            .map(|a| a.into_iter().map(|v| v.into()).collect());
-
-        let storage =
-            LedgerStorage::new(&symbols, persistence_store_path, state.identity, blockchain)?
-                .with_migrations(migration_config)?
-                .with_balances(&symbols, &balances)?
-                .with_idstore(state.id_store_seed, state.id_store_keys)?
-                .with_tokens(
-                    &symbols,
-                    symbols_meta,
-                    state.token_identity,
-                    state.token_next_subresource,
-                    balances,
-                )?
-                .with_account(state.account_identity, accounts)?
-                .build()?;
-
-        if let Some(h) = state.hash {
-            // Verify the hash.
-            let actual = hex::encode(storage.hash());
-            if actual != h {
-                return Err(error::invalid_initial_state(h, actual));
-            }
-        }
+        let mut storage = LedgerStorage::open(persistence_store_path, blockchain)?
+            .with_migrations(migration_config)?;
+        init_storage(&mut storage, state)?;
 
         info!(
             height = storage.get_height()?,
@@ -75,12 +86,45 @@ impl LedgerModuleImpl {
         Ok(Self { storage })
     }
 
+    /// Create a ledger with an empty storage. Its initial state is given by
+    /// the genesis of the chain, in `abci.initChain`.
+    pub fn new_for_genesis<P: AsRef<Path>>(
+        migration_config: Option<MigrationConfig>,
+        persistence_store_path: P,
+    ) -> Result<Self, ManyError> {
+        let storage =
+            LedgerStorage::open(persistence_store_path, true)?.with_migrations(migration_config)?;
+
+        Ok(Self { storage })
+    }
+
+    /// Initialize the storage from the genesis state of the chain and return
+    /// the resulting hash.
+    pub fn init_chain_with_state(&mut self, state: InitialStateJson) -> Result<Vec<u8>, ManyError> {
+        if self
+            .storage
+            .get_identity(crate::storage::IDENTITY_ROOT)
+            .is_ok()
+        {
+            return Err(error::genesis_state_conflict());
+        }
+
+        init_storage(&mut self.storage, state)?;
+
+        let hash = self.storage.hash();
+        info!(
+            height = self.storage.get_height()?,
+            hash = hex::encode(&hash).as_str()
+        );
+        Ok(hash)
+    }
+
     pub fn load<P: AsRef<Path>>(
         migrations: Option<MigrationConfig>,
         persistence_store_path: P,
         blockchain: bool,
     ) -> Result<Self, ManyError> {
-        let storage = LedgerStorage::load(persistence_store_path, blockchain, migrations).unwrap();
+        let storage = LedgerStorage::load(persistence_store_path, blockchain, migrations)?;
 
         tracing::debug!("Final migrations: {:?}", storage.migrations());
 
@@ -1,13 +1,52 @@
+use crate::json::InitialStateJson;
+use crate::module::validators::ValidatorUpdate;
 use crate::module::LedgerModuleImpl;
+use coset::CoseSign1;
 use many_error::ManyError;
 use many_modules::abci_backend::{
     AbciBlock, AbciCommitInfo, AbciInfo, AbciInit, BeginBlockReturn, EndpointInfo, InitChainReturn,
     ManyAbciModuleBackend,
 };
+use many_modules::{abci_backend, ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
 use many_types::Timestamp;
+use minicbor::bytes::ByteVec;
 use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
 use tracing::info;
 
+/// The arguments of `abci.initChain`, sent by the ABCI frontend when the
+/// genesis of the chain has an application state or validators.
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct InitChainArgs {
+    /// The `app_state` of the genesis, an `InitialStateJson`. It is `None`
+    /// when the initial state is given with `--state`.
+    #[n(0)]
+    pub app_state: Option<ByteVec>,
+
+    /// The validators of the genesis.
+    #[n(1)]
+    pub validators: Option<Vec<ValidatorUpdate>>,
+}
+
+#[derive(Clone, Debug, Default, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct InitChainReturns {
+    #[n(0)]
+    pub app_hash: Option<ByteVec>,
+}
+
+/// The return value of `abci.endBlock`. Backends which don't manage validators
+/// return an empty value, which the ABCI frontend treats as no updates.
+#[derive(Clone, Debug, Default, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct EndBlockReturns {
+    #[n(0)]
+    pub validator_updates: Vec<ValidatorUpdate>,
+}
+
 // This module is always supported, but will only be added when created using an ABCI
 // flag.
 impl ManyAbciModuleBackend for LedgerModuleImpl {
@@ -61,6 +100,10 @@ impl ManyAbciModuleBackend for LedgerModuleImpl {
                 ("tokens.removeExtendedInfo".to_string(), EndpointInfo { is_command : true }),
                 ("tokens.mint".to_string(), EndpointInfo { is_command : true }),
                 ("tokens.burn".to_string(), EndpointInfo { is_command : true }),
+
+                // Validators
+                ("validators.list".to_string(), EndpointInfo { is_command: false }),
+                ("validators.update".to_string(), EndpointInfo { is_command: true }),
             ]),
         })
     }
@@ -112,3 +155,92 @@ impl ManyAbciModuleBackend for LedgerModuleImpl {
         Ok(result)
     }
 }
+
+/// The ABCI backend module of the ledger. `abci.initChain` also accepts the
+/// genesis state, and `abci.endBlock` also returns the validator updates of the
+/// block.
+pub struct LedgerAbciModule {
+    inner: abci_backend::AbciModule<LedgerModuleImpl>,
+    module_impl: Arc<Mutex<LedgerModuleImpl>>,
+}
+
+impl LedgerAbciModule {
+    pub fn new(module_impl: Arc<Mutex<LedgerModuleImpl>>) -> Self {
+        Self {
+            inner: abci_backend::AbciModule::new(module_impl.clone()),
+            module_impl,
+        }
+    }
+
+    fn init_chain(&self, args: InitChainArgs) -> Result<Vec<u8>, ManyError> {
+        let mut module_impl = self.module_impl.lock().unwrap();
+
+        // With `--state`, the frontend learns the hash at the first commit.
+        let mut app_hash = None;
+        if let Some(app_state) = args.app_state {
+            let content =
+                std::str::from_utf8(&app_state).map_err(ManyError::deserialization_error)?;
+            let state = InitialStateJson::from_json_str(content)
+                .map_err(ManyError::deserialization_error)?;
+            app_hash = Some(module_impl.init_chain_with_state(state)?);
+        }
+        if let Some(validators) = args.validators {
+            let hash = module_impl.init_validators(validators)?;
+            app_hash = app_hash.map(|_| hash);
+        }
+
+        minicbor::to_vec(InitChainReturns {
+            app_hash: app_hash.map(Into::into),
+        })
+        .map_err(ManyError::serialization_error)
+    }
+
+    fn end_block(&self) -> Result<Vec<u8>, ManyError> {
+        let returns = self
+            .module_impl
+            .lock()
+            .unwrap()
+            .end_block_validator_updates()?;
+        minicbor::to_vec(returns).map_err(ManyError::serialization_error)
+    }
+}
+
+impl Debug for LedgerAbciModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LedgerAbciModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for LedgerAbciModule {
+    fn info(&self) -> &ManyModuleInfo {
+        self.inner.info()
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        self.inner.validate(message, envelope)
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        match message.method.as_str() {
+            "abci.initChain" => match minicbor::decode::<InitChainArgs>(&message.data) {
+                Ok(args) => {
+                    let data = self.init_chain(args);
+                    Ok(ResponseMessage::from_request(&message, &message.to, data))
+                }
+                // Without a genesis state, this is the regular `init_chain`.
+                Err(_) => self.inner.execute(message).await,
+            },
+            "abci.endBlock" => {
+                let response = self.inner.execute(message.clone()).await?;
+                if response.data.is_err() {
+                    return Ok(response);
+                }
+
+                let data = self.end_block();
+                Ok(ResponseMessage::from_request(&message, &message.to, data))
+            }
+            _ => self.inner.execute(message).await,
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::error;
+use crate::module::abci::EndBlockReturns;
+use crate::module::LedgerModuleImpl;
+use crate::storage::IDENTITY_ROOT;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use minicbor::bytes::ByteVec;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+pub const VALIDATORS_LIST_ENDPOINT: &str = "validators.list";
+pub const VALIDATORS_UPDATE_ENDPOINT: &str = "validators.update";
+
+/// A change to the validator set, as sent to tendermint. The public key is an
+/// ed25519 key, and a power of zero removes the validator.
+#[derive(Clone, Debug, Eq, PartialEq, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct ValidatorUpdate {
+    #[n(0)]
+    pub pub_key: ByteVec,
+
+    #[n(1)]
+    pub power: u64,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct ValidatorsUpdateArgs {
+    #[n(0)]
+    pub updates: Vec<ValidatorUpdate>,
+}
+
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct ValidatorsListReturns {
+    #[n(0)]
+    pub validators: Vec<ValidatorUpdate>,
+}
+
+impl LedgerModuleImpl {
+    pub fn validators_list(&self) -> Result<ValidatorsListReturns, ManyError> {
+        Ok(ValidatorsListReturns {
+            validators: self
+                .storage
+                .validators()?
+                .into_iter()
+                .map(|(pub_key, power)| ValidatorUpdate { pub_key, power })
+                .collect(),
+        })
+    }
+
+    /// Update the validator set. Only the ledger identity can do this.
+    pub fn validators_update(
+        &mut self,
+        sender: &Address,
+        args: ValidatorsUpdateArgs,
+    ) -> Result<(), ManyError> {
+        if *sender != self.storage.get_identity(IDENTITY_ROOT)? {
+            return Err(error::invalid_validators_sender());
+        }
+        if let Some(update) = args.updates.iter().find(|u| u.pub_key.len() != 32) {
+            return Err(error::invalid_validator_key(update.pub_key.len()));
+        }
+
+        self.storage.update_validators(args.updates)
+    }
+
+    /// Set the validators of the genesis, if the ledger manages the validator
+    /// set. Returns the new hash of the storage.
+    pub fn init_validators(
+        &mut self,
+        validators: Vec<ValidatorUpdate>,
+    ) -> Result<Vec<u8>, ManyError> {
+        if let Some(update) = validators.iter().find(|u| u.pub_key.len() != 32) {
+            return Err(error::invalid_validator_key(update.pub_key.len()));
+        }
+        self.storage.init_validators(validators)?;
+        Ok(self.storage.hash())
+    }
+
+    pub fn end_block_validator_updates(&mut self) -> Result<EndBlockReturns, ManyError> {
+        Ok(EndBlockReturns {
+            validator_updates: self.storage.take_validator_updates()?,
+        })
+    }
+}
+
+/// Endpoints to list and update the validator set.
+pub struct ValidatorsModule {
+    info: ManyModuleInfo,
+    module_impl: Arc<Mutex<LedgerModuleImpl>>,
+}
+
+impl ValidatorsModule {
+    pub fn new(module_impl: Arc<Mutex<LedgerModuleImpl>>) -> Self {
+        Self {
+            info: ManyModuleInfo {
+                name: "ValidatorsModule".to_string(),
+                attribute: None,
+                endpoints: vec![
+                    VALIDATORS_LIST_ENDPOINT.to_string(),
+                    VALIDATORS_UPDATE_ENDPOINT.to_string(),
+                ],
+            },
+            module_impl,
+        }
+    }
+}
+
+impl Debug for ValidatorsModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ValidatorsModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for ValidatorsModule {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, _envelope: &CoseSign1) -> Result<(), ManyError> {
+        if message.method == VALIDATORS_UPDATE_ENDPOINT {
+            minicbor::decode::<ValidatorsUpdateArgs>(&message.data)
+                .map_err(ManyError::deserialization_error)?;
+        }
+        Ok(())
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let mut module_impl = self.module_impl.lock().unwrap();
+        let data = match message.method.as_str() {
+            VALIDATORS_LIST_ENDPOINT => module_impl
+                .validators_list()
+                .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error)),
+            VALIDATORS_UPDATE_ENDPOINT => {
+                let args: ValidatorsUpdateArgs =
+                    minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+                module_impl
+                    .validators_update(&message.from(), args)
+                    .and_then(|r| minicbor::to_vec(r).map_err(ManyError::serialization_error))
+            }
+            _ => return Err(ManyError::invalid_method_name(message.method)),
+        };
+
+        Ok(ResponseMessage::from_request(&message, &message.to, data))
+    }
+}
@@ -25,11 +25,16 @@ pub mod ledger_mintburn;
 pub mod ledger_tokens;
 mod migrations;
 pub mod multisig;
+pub mod validators;
 
 pub const SYMBOLS_ROOT: &str = "/config/symbols";
 pub const IDENTITY_ROOT: &str = "/config/identity";
 pub const HEIGHT_ROOT: &str = "/height";
 
+/// Auxiliary (unhashed) key holding the height of a block whose commit has
+/// started but not finished.
+pub const COMMIT_PENDING_AUX: &str = "/commit_pending";
+
 pub(super) fn key_for_account_balance(id: &Address, symbol: &Symbol) -> Vec<u8> {
     format!("/balances/{id}/{symbol}").into_bytes()
 }
@@ -43,6 +48,18 @@ pub(super) fn key_for_subresource_counter(id: &Address, token_migration_active:
     }
 }
 
+/// Decode a height stored as a big-endian `u64` under `key`.
+fn decode_height(key: &str, value: &[u8]) -> Result<u64, ManyError> {
+    <[u8; 8]>::try_from(value)
+        .map(u64::from_be_bytes)
+        .map_err(|_| {
+            error::storage_get_failed(format!(
+                "Invalid height under '{key}': {}",
+                hex::encode(value)
+            ))
+        })
+}
+
 pub type InnerStorage = merk::Merk;
 
 pub struct LedgerStorage {
@@ -120,8 +137,12 @@ impl LedgerStorage {
 
     #[inline]
     fn commit_storage(&mut self) -> Result<(), ManyError> {
+        self.commit_storage_with_aux(&[])
+    }
+
+    fn commit_storage_with_aux(&mut self, aux: &[(Vec<u8>, Op)]) -> Result<(), ManyError> {
         self.persistent_store
-            .commit(&[])
+            .commit(aux)
             .map_err(error::storage_commit_failed)?;
         Ok(())
     }
@@ -134,14 +155,20 @@ impl LedgerStorage {
         let persistent_store =
             InnerStorage::open(persistent_path).map_err(error::storage_open_failed)?;
 
+        // A block was partially committed (e.g. the process crashed before its
+        // migrations were committed). Its height and hash cannot be trusted.
+        if let Some(pending) = persistent_store
+            .get_aux(COMMIT_PENDING_AUX.as_bytes())
+            .map_err(error::storage_get_failed)?
+        {
+            let height = decode_height(COMMIT_PENDING_AUX, &pending)?;
+            return Err(error::incomplete_commit(height));
+        }
+
         let height = persistent_store
             .get(HEIGHT_ROOT.as_bytes())
             .map_err(error::storage_get_failed)?
-            .map_or(0u64, |x| {
-                let mut bytes = [0u8; 8];
-                bytes.copy_from_slice(x.as_slice());
-                u64::from_be_bytes(bytes)
-            });
+            .map_or(Ok(0), |x| decode_height(HEIGHT_ROOT, &x))?;
 
         // The call to `saturating_sub()` is required to fix
         // https://github.com/liftedinit/many-framework/issues/289
@@ -174,10 +201,32 @@ impl LedgerStorage {
         identity: Address,
         blockchain: bool,
     ) -> Result<Self, ManyError> {
-        let mut persistent_store =
-            InnerStorage::open(persistent_path).map_err(ManyError::unknown)?; // TODO: Custom error
+        let mut storage = Self::open(persistent_path, blockchain)?;
+        storage.init_identity(symbols, identity)?;
+        Ok(storage)
+    }
+
+    /// Open an empty storage, which will be initialized later from the genesis
+    /// of the chain.
+    pub fn open<P: AsRef<Path>>(persistent_path: P, blockchain: bool) -> Result<Self, ManyError> {
+        let persistent_store = InnerStorage::open(persistent_path).map_err(ManyError::unknown)?; // TODO: Custom error
+
+        Ok(Self {
+            persistent_store,
+            blockchain,
+            latest_tid: EventId::from(vec![0]),
+            current_time: None,
+            current_hash: None,
+            migrations: MigrationSet::empty().map_err(ManyError::unknown)?, // TODO: Custom error
+        })
+    }
 
-        persistent_store
+    pub(crate) fn init_identity(
+        &mut self,
+        symbols: &BTreeMap<Symbol, String>,
+        identity: Address,
+    ) -> Result<(), ManyError> {
+        self.persistent_store
             .apply(&[
                 (
                     IDENTITY_ROOT.as_bytes().to_vec(),
@@ -191,27 +240,18 @@ impl LedgerStorage {
             .map_err(error::storage_apply_failed)?;
 
         // We need to commit, because we need IDENTITY_ROOT to be available for the next steps, if any.
-        persistent_store
-            .commit(&[])
-            .map_err(error::storage_commit_failed)?;
-
-        Ok(Self {
-            persistent_store,
-            blockchain,
-            latest_tid: EventId::from(vec![0]),
-            current_time: None,
-            current_hash: None,
-            migrations: MigrationSet::empty().map_err(ManyError::unknown)?, // TODO: Custom error
-        })
+        self.commit_storage()
     }
 
     pub fn build(mut self) -> Result<Self, ManyError> {
-        self.persistent_store
-            .commit(&[])
-            .map_err(error::storage_commit_failed)?;
+        self.build_in_place()?;
         Ok(self)
     }
 
+    pub(crate) fn build_in_place(&mut self) -> Result<(), ManyError> {
+        self.commit_storage()
+    }
+
     /// Kept for backward compatibility
     pub fn get_symbols_and_tickers(&self) -> Result<BTreeMap<Symbol, String>, ManyError> {
         minicbor::decode::<BTreeMap<Symbol, String>>(
@@ -249,15 +289,10 @@ impl LedgerStorage {
     /// Return the current height of the blockchain.
     /// The current height correspond to finished, committed blocks.
     pub fn get_height(&self) -> Result<u64, ManyError> {
-        Ok(self
-            .persistent_store
+        self.persistent_store
             .get(HEIGHT_ROOT.as_bytes())
             .map_err(error::storage_get_failed)?
-            .map_or(0u64, |x| {
-                let mut bytes = [0u8; 8];
-                bytes.copy_from_slice(x.as_slice());
-                u64::from_be_bytes(bytes)
-            }))
+            .map_or(Ok(0), |x| decode_height(HEIGHT_ROOT, &x))
     }
 
     pub fn hash(&self) -> Vec<u8> {
@@ -1,7 +1,8 @@
 use crate::storage::event::HEIGHT_EVENTID_SHIFT;
-use crate::storage::LedgerStorage;
+use crate::storage::{LedgerStorage, COMMIT_PENDING_AUX};
 use many_modules::abci_backend::AbciCommitInfo;
 use many_modules::events::EventId;
+use merk::Op;
 
 impl LedgerStorage {
     pub fn commit(&mut self) -> AbciCommitInfo {
@@ -14,15 +15,21 @@ impl LedgerStorage {
 
         // Committing before the migration so that the migration has
         // the actual state of the database when setting its
-        // attributes.
-        self.commit_storage().expect("Unable to commit to storage.");
+        // attributes. The block is marked as pending until the second commit,
+        // so a crash in between is detected when loading the storage.
+        self.commit_storage_with_aux(&[(
+            COMMIT_PENDING_AUX.as_bytes().to_vec(),
+            Op::Put((height + 1).to_be_bytes().to_vec()),
+        )])
+        .expect("Unable to commit to storage.");
 
         // Initialize/update migrations at current height, if any
         self.migrations
             .update_at_height(&mut self.persistent_store, height + 1)
             .expect("Unable to run migrations");
 
-        self.commit_storage().expect("Unable to commit to storage.");
+        self.commit_storage_with_aux(&[(COMMIT_PENDING_AUX.as_bytes().to_vec(), Op::Delete)])
+            .expect("Unable to commit to storage.");
 
         let hash = self.persistent_store.root_hash().to_vec();
         self.current_hash = Some(hash.clone());
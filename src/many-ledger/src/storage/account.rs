@@ -56,6 +56,15 @@ impl LedgerStorage {
         identity: Option<Address>,
         accounts: Option<Vec<AccountMeta>>,
     ) -> Result<Self, ManyError> {
+        self.init_account(identity, accounts)?;
+        Ok(self)
+    }
+
+    pub(crate) fn init_account(
+        &mut self,
+        identity: Option<Address>,
+        accounts: Option<Vec<AccountMeta>>,
+    ) -> Result<(), ManyError> {
         if self.migrations.is_active(&TOKEN_MIGRATION) {
             let identity = identity.unwrap_or(self.get_identity(IDENTITY_ROOT)?);
             self.persistent_store
@@ -94,7 +103,7 @@ impl LedgerStorage {
                 }
             }
         }
-        Ok(self)
+        Ok(())
     }
 
     pub(crate) fn _add_account(
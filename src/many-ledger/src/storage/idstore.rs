@@ -39,6 +39,15 @@ impl LedgerStorage {
         maybe_seed: Option<u64>,
         maybe_keys: Option<BTreeMap<String, String>>,
     ) -> Result<Self, ManyError> {
+        self.init_idstore(maybe_seed, maybe_keys)?;
+        Ok(self)
+    }
+
+    pub(crate) fn init_idstore(
+        &mut self,
+        maybe_seed: Option<u64>,
+        maybe_keys: Option<BTreeMap<String, String>>,
+    ) -> Result<(), ManyError> {
         let mut batch: Vec<BatchEntry> = Vec::new();
         let maybe_keys = maybe_keys.map(|keys| {
             keys.iter()
@@ -67,7 +76,7 @@ impl LedgerStorage {
             .apply(batch.as_slice())
             .map_err(error::storage_apply_failed)?;
 
-        Ok(self)
+        Ok(())
     }
 
     pub(crate) fn inc_idstore_seed(&mut self) -> Result<u64, ManyError> {
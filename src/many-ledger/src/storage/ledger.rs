@@ -12,6 +12,15 @@ impl LedgerStorage {
         symbols: &BTreeMap<Symbol, String>,
         initial_balances: &BTreeMap<Address, BTreeMap<Symbol, TokenAmount>>,
     ) -> Result<Self, ManyError> {
+        self.init_balances(symbols, initial_balances)?;
+        Ok(self)
+    }
+
+    pub(crate) fn init_balances(
+        &mut self,
+        symbols: &BTreeMap<Symbol, String>,
+        initial_balances: &BTreeMap<Address, BTreeMap<Symbol, TokenAmount>>,
+    ) -> Result<(), ManyError> {
         let mut batch: Vec<BatchEntry> = Vec::new();
         for (k, v) in initial_balances.iter() {
             for (symbol, tokens) in v.iter() {
@@ -30,7 +39,7 @@ impl LedgerStorage {
             .apply(batch.as_slice())
             .map_err(error::storage_apply_failed)?;
 
-        Ok(self)
+        Ok(())
     }
 
     fn get_all_balances(
@@ -93,6 +93,24 @@ impl LedgerStorage {
         token_next_subresource: Option<u32>,
         initial_balances: BTreeMap<Address, BTreeMap<Symbol, TokenAmount>>,
     ) -> Result<Self, ManyError> {
+        self.init_tokens(
+            symbols,
+            symbols_meta,
+            token_identity,
+            token_next_subresource,
+            initial_balances,
+        )?;
+        Ok(self)
+    }
+
+    pub(crate) fn init_tokens(
+        &mut self,
+        symbols: &BTreeMap<Symbol, String>,
+        symbols_meta: Option<BTreeMap<Symbol, SymbolMeta>>,
+        token_identity: Option<Address>,
+        token_next_subresource: Option<u32>,
+        initial_balances: BTreeMap<Address, BTreeMap<Symbol, TokenAmount>>,
+    ) -> Result<(), ManyError> {
         if self.migrations.is_active(&TOKEN_MIGRATION) {
             let symbols_meta = symbols_meta
                 .ok_or_else(|| ManyError::unknown("Symbols metadata needs to be provided"))?; // TODO: Custom error
@@ -155,7 +173,7 @@ impl LedgerStorage {
             self.commit_storage()?;
         }
 
-        Ok(self)
+        Ok(())
     }
 
     pub(crate) fn get_owner(&self, symbol: &Symbol) -> Result<Option<Address>, ManyError> {
@@ -0,0 +1,146 @@
+use crate::error;
+use crate::module::validators::ValidatorUpdate;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use merk::{BatchEntry, Op};
+use minicbor::bytes::ByteVec;
+use std::collections::BTreeMap;
+
+/// The current validator set, a map of public keys to voting power. It only
+/// exists if the ledger manages the validator set.
+pub const VALIDATORS_KEY: &[u8] = b"/config/validators";
+
+/// Validator updates which were not returned to the consensus engine yet.
+pub const VALIDATOR_UPDATES_KEY: &[u8] = b"/config/validator_updates";
+
+/// The maximum total voting power of the validators (tendermint's
+/// `MaxTotalVotingPower`). Tendermint halts if the total goes over it.
+pub const MAX_TOTAL_VOTING_POWER: u64 = (i64::MAX / 8) as u64;
+
+impl LedgerStorage {
+    /// Whether the ledger manages the validator set. Only ledgers whose initial
+    /// state sets `manage_validators` do, so the genesis validators don't change
+    /// the state of the others.
+    pub fn manages_validators(&self) -> Result<bool, ManyError> {
+        Ok(self
+            .persistent_store
+            .get(VALIDATORS_KEY)
+            .map_err(error::storage_get_failed)?
+            .is_some())
+    }
+
+    pub fn validators(&self) -> Result<BTreeMap<ByteVec, u64>, ManyError> {
+        self.persistent_store
+            .get(VALIDATORS_KEY)
+            .map_err(error::storage_get_failed)?
+            .map_or(Ok(BTreeMap::new()), |x| {
+                minicbor::decode(&x).map_err(ManyError::deserialization_error)
+            })
+    }
+
+    fn pending_validator_updates(&self) -> Result<Vec<ValidatorUpdate>, ManyError> {
+        self.persistent_store
+            .get(VALIDATOR_UPDATES_KEY)
+            .map_err(error::storage_get_failed)?
+            .map_or(Ok(Vec::new()), |x| {
+                minicbor::decode(&x).map_err(ManyError::deserialization_error)
+            })
+    }
+
+    /// Start managing the validator set, empty until the genesis validators
+    /// are set by `init_validators`.
+    pub(crate) fn init_validator_set(&mut self) -> Result<(), ManyError> {
+        self.persistent_store
+            .apply(&[(
+                VALIDATORS_KEY.to_vec(),
+                Op::Put(minicbor::to_vec(BTreeMap::<ByteVec, u64>::new()).unwrap()),
+            )])
+            .map_err(error::storage_apply_failed)
+    }
+
+    /// Set the validators of the genesis, which tendermint already knows, so
+    /// they can be updated or removed later. Does nothing if the ledger does
+    /// not manage the validator set.
+    pub fn init_validators(&mut self, validators: Vec<ValidatorUpdate>) -> Result<(), ManyError> {
+        if !self.manages_validators()? {
+            return Ok(());
+        }
+
+        let validators: BTreeMap<ByteVec, u64> = validators
+            .into_iter()
+            .filter(|v| v.power != 0)
+            .map(|v| (v.pub_key, v.power))
+            .collect();
+
+        self.persistent_store
+            .apply(&[(
+                VALIDATORS_KEY.to_vec(),
+                Op::Put(minicbor::to_vec(validators).unwrap()),
+            )])
+            .map_err(error::storage_apply_failed)?;
+        self.commit_storage()
+    }
+
+    /// Apply updates to the validator set. A power of zero removes the
+    /// validator. Only known validators can be removed, as tendermint would
+    /// halt on an unknown one, and the total power cannot exceed
+    /// `MAX_TOTAL_VOTING_POWER`. The updates are returned by the next
+    /// `abci.endBlock`.
+    pub fn update_validators(&mut self, updates: Vec<ValidatorUpdate>) -> Result<(), ManyError> {
+        if !self.manages_validators()? {
+            return Err(error::validators_not_managed());
+        }
+
+        let mut validators = self.validators()?;
+        let mut pending = self.pending_validator_updates()?;
+
+        for update in updates {
+            if update.power == 0 {
+                if validators.remove(&update.pub_key).is_none() {
+                    return Err(error::unknown_validator(hex::encode(
+                        update.pub_key.as_slice(),
+                    )));
+                }
+            } else {
+                validators.insert(update.pub_key.clone(), update.power);
+            }
+            pending.push(update);
+        }
+
+        let total = validators
+            .values()
+            .try_fold(0u64, |total, power| total.checked_add(*power));
+        if !matches!(total, Some(total) if total <= MAX_TOTAL_VOTING_POWER) {
+            return Err(error::validator_power_too_large(MAX_TOTAL_VOTING_POWER));
+        }
+
+        // Keys in batch must be sorted.
+        let batch: Vec<BatchEntry> = vec![
+            (
+                VALIDATOR_UPDATES_KEY.to_vec(),
+                Op::Put(minicbor::to_vec(pending).unwrap()),
+            ),
+            (
+                VALIDATORS_KEY.to_vec(),
+                Op::Put(minicbor::to_vec(validators).unwrap()),
+            ),
+        ];
+        self.persistent_store
+            .apply(&batch)
+            .map_err(error::storage_apply_failed)?;
+
+        self.maybe_commit()
+    }
+
+    /// Remove and return the validator updates applied since the last call.
+    pub fn take_validator_updates(&mut self) -> Result<Vec<ValidatorUpdate>, ManyError> {
+        let pending = self.pending_validator_updates()?;
+        if !pending.is_empty() {
+            self.persistent_store
+                .apply(&[(VALIDATOR_UPDATES_KEY.to_vec(), Op::Delete)])
+                .map_err(error::storage_apply_failed)?;
+            self.maybe_commit()?;
+        }
+        Ok(pending)
+    }
+}
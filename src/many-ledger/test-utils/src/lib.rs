@@ -186,6 +186,7 @@ impl Setup {
         blockchain: bool,
         migration_config: Option<MigrationConfig>,
         skip_hash_check: bool, // If true, skip the staging file hash check
+        manage_validators: bool,
     ) -> Self {
         let id = generate_random_ed25519_identity();
         let public_key = PublicKey(id.public_key().to_vec().unwrap().into());
@@ -199,6 +200,9 @@ impl Setup {
         if skip_hash_check {
             state.hash = None;
         }
+        if manage_validators {
+            state.manage_validators = Some(true);
+        }
 
         Self {
             module_impl: LedgerModuleImpl::new(state, migration_config, store_path, blockchain)
@@ -211,7 +215,13 @@ impl Setup {
     }
 
     pub fn new(blockchain: bool) -> Self {
-        Setup::_new(blockchain, None, false)
+        Setup::_new(blockchain, None, false, false)
+    }
+
+    /// A ledger which manages the validator set. The staging hash does not
+    /// account for the validator set, so it is not checked.
+    pub fn new_with_validators(blockchain: bool) -> Self {
+        Setup::_new(blockchain, None, true, true)
     }
 
     pub fn new_with_migrations(
@@ -231,6 +241,7 @@ impl Setup {
             blockchain,
             Some(serde_json::from_str(&migrations).unwrap()),
             skip_hash_check,
+            false,
         )
     }
 
@@ -1,9 +1,12 @@
 use many_identity::testing::identity;
 use many_identity::Address;
+use many_ledger::json::InitialStateJson;
 use many_ledger::migration::tokens::TOKEN_MIGRATION;
+use many_ledger::module::LedgerModuleImpl;
 use many_ledger::storage::ledger_tokens::SymbolMeta;
-use many_ledger::{module::LedgerModuleImpl, storage::LedgerStorage};
+use many_ledger::storage::{LedgerStorage, COMMIT_PENDING_AUX};
 use many_migration::{Metadata, MigrationConfig};
+use many_modules::abci_backend::ManyAbciModuleBackend;
 use many_modules::account::features::FeatureInfo;
 use many_modules::account::AccountModuleBackend;
 use many_modules::ledger::{LedgerModuleBackend, LedgerTokensModuleBackend, TokenInfoArgs};
@@ -187,3 +190,100 @@ fn load_symbol_meta() {
     assert_eq!(info.summary.ticker, "MF0".to_string());
     assert_eq!(info.summary.decimals, 9);
 }
+
+/// Verify the initial state can be given by the genesis, and only once
+#[test]
+fn init_chain_with_state() {
+    let content = std::fs::read_to_string("../../staging/ledger_state.json5")
+        .or_else(|_| std::fs::read_to_string("staging/ledger_state.json5"))
+        .unwrap();
+
+    let mut expected = LedgerModuleImpl::new(
+        InitialStateJson::from_json_str(&content).unwrap(),
+        None,
+        tempfile::tempdir().unwrap(),
+        true,
+    )
+    .unwrap();
+
+    let mut module_impl =
+        LedgerModuleImpl::new_for_genesis(None, tempfile::tempdir().unwrap()).unwrap();
+    let hash = module_impl
+        .init_chain_with_state(InitialStateJson::from_json_str(&content).unwrap())
+        .unwrap();
+    assert_eq!(
+        hash,
+        ManyAbciModuleBackend::info(&expected)
+            .unwrap()
+            .hash
+            .to_vec()
+    );
+
+    let r = module_impl.init_chain_with_state(InitialStateJson::from_json_str(&content).unwrap());
+    assert_eq!(
+        r.unwrap_err().code(),
+        many_ledger::error::genesis_state_conflict().code()
+    );
+
+    // A storage initialized from `--state` refuses the genesis state
+    let r = expected.init_chain_with_state(InitialStateJson::from_json_str(&content).unwrap());
+    assert_eq!(
+        r.unwrap_err().code(),
+        many_ledger::error::genesis_state_conflict().code()
+    );
+}
+
+/// Verify a storage interrupted while committing a block refuses to load
+#[test]
+fn load_incomplete_commit() {
+    let path = tempfile::tempdir().unwrap().into_path();
+    {
+        let _ = LedgerStorage::new(&BTreeMap::new(), path.clone(), identity(666), true)
+            .unwrap()
+            .build()
+            .unwrap();
+    }
+
+    // Simulate a crash between the two commits of a block.
+    {
+        let mut merk = merk::Merk::open(path.clone()).unwrap();
+        merk.commit(&[(
+            COMMIT_PENDING_AUX.as_bytes().to_vec(),
+            merk::Op::Put(1u64.to_be_bytes().to_vec()),
+        )])
+        .unwrap();
+    }
+
+    let r = LedgerModuleImpl::load(None, path, true);
+    assert_eq!(
+        r.unwrap_err().code(),
+        many_ledger::error::incomplete_commit(1).code()
+    );
+}
+
+/// Verify a corrupted pending commit marker is an error, not a panic
+#[test]
+fn load_invalid_commit_pending() {
+    let path = tempfile::tempdir().unwrap().into_path();
+    {
+        let _ = LedgerStorage::new(&BTreeMap::new(), path.clone(), identity(666), true)
+            .unwrap()
+            .build()
+            .unwrap();
+    }
+
+    {
+        let mut merk = merk::Merk::open(path.clone()).unwrap();
+        merk.commit(&[(
+            COMMIT_PENDING_AUX.as_bytes().to_vec(),
+            merk::Op::Put(vec![1, 2, 3]),
+        )])
+        .unwrap();
+    }
+
+    let r = LedgerModuleImpl::load(None, path, true);
+    assert_eq!(
+        r.unwrap_err().code(),
+        many_ledger::error::storage_get_failed("").code()
+    );
+}
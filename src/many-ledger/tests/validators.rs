@@ -0,0 +1,180 @@
+//! Tests regarding validator set updates.
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::error;
+use many_ledger::module::validators::{ValidatorUpdate, ValidatorsUpdateArgs};
+use many_ledger::storage::validators::MAX_TOTAL_VOTING_POWER;
+use many_ledger_test_utils::*;
+use many_modules::abci_backend::ManyAbciModuleBackend;
+use std::str::FromStr;
+
+/// The identity of the staging ledger state.
+fn ledger_identity() -> Address {
+    Address::from_str("mahukzwuwgt3porn6q4vq4xu3mwy5gyskhouryzbscq7wb2iow").unwrap()
+}
+
+fn update(key: u8, power: u64) -> ValidatorUpdate {
+    ValidatorUpdate {
+        pub_key: vec![key; 32].into(),
+        power,
+    }
+}
+
+fn update_args(updates: Vec<ValidatorUpdate>) -> ValidatorsUpdateArgs {
+    ValidatorsUpdateArgs { updates }
+}
+
+#[test]
+fn updates_are_returned_once() {
+    let mut harness = Setup::new_with_validators(true);
+
+    let (_, updates) = harness.block(|harness| {
+        harness
+            .module_impl
+            .validators_update(
+                &ledger_identity(),
+                update_args(vec![update(1, 10), update(2, 20)]),
+            )
+            .unwrap();
+        harness.module_impl.end_block_validator_updates().unwrap()
+    });
+    assert_eq!(
+        updates.validator_updates,
+        vec![update(1, 10), update(2, 20)]
+    );
+
+    let (_, updates) =
+        harness.block(|harness| harness.module_impl.end_block_validator_updates().unwrap());
+    assert!(updates.validator_updates.is_empty());
+
+    let validators = harness.module_impl.validators_list().unwrap().validators;
+    assert_eq!(validators, vec![update(1, 10), update(2, 20)]);
+}
+
+#[test]
+fn remove() {
+    let mut harness = Setup::new_with_validators(false);
+    harness
+        .module_impl
+        .validators_update(
+            &ledger_identity(),
+            update_args(vec![update(1, 10), update(2, 20)]),
+        )
+        .unwrap();
+    harness
+        .module_impl
+        .validators_update(&ledger_identity(), update_args(vec![update(1, 0)]))
+        .unwrap();
+
+    let validators = harness.module_impl.validators_list().unwrap().validators;
+    assert_eq!(validators, vec![update(2, 20)]);
+}
+
+#[test]
+fn remove_unknown() {
+    let mut harness = Setup::new_with_validators(false);
+    let result = harness
+        .module_impl
+        .validators_update(&ledger_identity(), update_args(vec![update(1, 0)]));
+    assert_many_err(result, error::unknown_validator(hex::encode([1u8; 32])));
+}
+
+#[test]
+fn invalid_sender() {
+    let mut harness = Setup::new(false);
+    let result = harness
+        .module_impl
+        .validators_update(&identity(1), update_args(vec![update(1, 10)]));
+    assert_many_err(result, error::invalid_validators_sender());
+}
+
+#[test]
+fn invalid_key() {
+    let mut harness = Setup::new(false);
+    let result = harness.module_impl.validators_update(
+        &ledger_identity(),
+        update_args(vec![ValidatorUpdate {
+            pub_key: vec![1; 33].into(),
+            power: 10,
+        }]),
+    );
+    assert_many_err(result, error::invalid_validator_key(33));
+}
+
+#[test]
+fn remove_genesis() {
+    let mut harness = Setup::new_with_validators(false);
+    harness
+        .module_impl
+        .init_validators(vec![update(1, 10), update(2, 20)])
+        .unwrap();
+    harness
+        .module_impl
+        .validators_update(&ledger_identity(), update_args(vec![update(1, 0)]))
+        .unwrap();
+
+    let validators = harness.module_impl.validators_list().unwrap().validators;
+    assert_eq!(validators, vec![update(2, 20)]);
+}
+
+#[test]
+fn power_too_large() {
+    let mut harness = Setup::new_with_validators(false);
+    for power in [u64::MAX, MAX_TOTAL_VOTING_POWER + 1] {
+        let result = harness
+            .module_impl
+            .validators_update(&ledger_identity(), update_args(vec![update(1, power)]));
+        assert_many_err(
+            result,
+            error::validator_power_too_large(MAX_TOTAL_VOTING_POWER),
+        );
+    }
+
+    // The power of all the validators counts.
+    harness
+        .module_impl
+        .validators_update(
+            &ledger_identity(),
+            update_args(vec![update(1, MAX_TOTAL_VOTING_POWER)]),
+        )
+        .unwrap();
+    let result = harness
+        .module_impl
+        .validators_update(&ledger_identity(), update_args(vec![update(2, 1)]));
+    assert_many_err(
+        result,
+        error::validator_power_too_large(MAX_TOTAL_VOTING_POWER),
+    );
+
+    let validators = harness.module_impl.validators_list().unwrap().validators;
+    assert_eq!(validators, vec![update(1, MAX_TOTAL_VOTING_POWER)]);
+}
+
+#[test]
+fn genesis_is_ignored_unless_managed() {
+    let mut harness = Setup::new(false);
+    let hash = ManyAbciModuleBackend::info(&harness.module_impl)
+        .unwrap()
+        .hash
+        .to_vec();
+    let new_hash = harness
+        .module_impl
+        .init_validators(vec![update(1, 10)])
+        .unwrap();
+    assert_eq!(new_hash, hash);
+    assert!(harness
+        .module_impl
+        .validators_list()
+        .unwrap()
+        .validators
+        .is_empty());
+}
+
+#[test]
+fn updates_need_managed_validators() {
+    let mut harness = Setup::new(false);
+    let result = harness
+        .module_impl
+        .validators_update(&ledger_identity(), update_args(vec![update(1, 10)]));
+    assert_many_err(result, error::validators_not_managed());
+}
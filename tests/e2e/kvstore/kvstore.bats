@@ -102,3 +102,19 @@ function teardown() {
   call_kvstore --pem=1 --port=8000 get "112233"
   assert_output --partial "bateau"
 }
+
+@test "$SUITE: can put encrypted data and decrypt it with the same pem" {
+  call_kvstore --pem=1 --port=8000 put --encrypt "445566" "foobar"
+  call_kvstore --pem=1 --port=8000 get "445566"
+  refute_output --partial "foobar"
+  call_kvstore --pem=1 --port=8000 get --decrypt "445566"
+  assert_output --partial "foobar"
+  call_kvstore --pem=2 --port=8000 get --decrypt "445566"
+  assert_output --partial "Could not decrypt the value."
+}
+
+@test "$SUITE: can put data in chunks and get it" {
+  call_kvstore --pem=1 --port=8000 put --chunk-size 2 "778899" "foobar"
+  call_kvstore --pem=1 --port=8000 get "778899"
+  assert_output --partial "foobar"
+}